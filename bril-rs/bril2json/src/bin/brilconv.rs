@@ -0,0 +1,62 @@
+use std::io::{Read, Write};
+
+use bril2json::cli::{ConvCli, Format};
+use bril2json::try_parse_abstract_program_from_read;
+use bril_rs::{binary, bytecode, error::LoadError, output_program, Program};
+use clap::Parser;
+
+fn load(args: &ConvCli) -> Result<Program, LoadError> {
+    let mut buffer = Vec::new();
+    match &args.file {
+        None => std::io::stdin().read_to_end(&mut buffer)?,
+        Some(f) => std::fs::File::open(f)?.read_to_end(&mut buffer)?,
+    };
+    let from = args.from.unwrap_or_else(|| {
+        if binary::is_binary(&buffer) {
+            Format::Binary
+        } else if buffer.trim_ascii_start().starts_with(b"{") {
+            Format::Json
+        } else {
+            Format::Text
+        }
+    });
+    let abstract_program = match from {
+        Format::Binary => return Ok(binary::read_binary_from_slice(&buffer)?),
+        Format::Bytecode => return Ok(bytecode::read_bytecode_from_slice(&buffer)?),
+        Format::Json => bril_rs::try_load_abstract_program_from_read(buffer.as_slice())?,
+        Format::Text => try_parse_abstract_program_from_read(
+            buffer.as_slice(),
+            args.position >= 1,
+            args.position >= 2,
+            args.file.clone(),
+        )?,
+    };
+    Ok(abstract_program.try_into()?)
+}
+
+fn main() {
+    let args = ConvCli::parse();
+    let program = match load(&args) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(2)
+        }
+    };
+    match args.to {
+        Format::Json => output_program(&program),
+        Format::Text => print!("{program}"),
+        Format::Binary => {
+            let mut stdout = std::io::stdout().lock();
+            binary::write_binary(&program, args.position >= 1, &mut stdout).unwrap();
+            stdout.flush().unwrap();
+        }
+        Format::Bytecode => {
+            let mut stdout = std::io::stdout().lock();
+            if let Err(e) = bytecode::write_bytecode(&program, &mut stdout) {
+                eprintln!("error: {e}");
+                std::process::exit(2)
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{ArgAction::Count, Parser};
+use clap::{ArgAction::Count, Parser, ValueEnum};
 
 #[derive(Parser)]
 #[command(about, version, author)] // keeps the cli synced with Cargo.toml
@@ -10,3 +10,37 @@ pub struct Cli {
     #[arg(short, action = Count)]
     pub position: u8,
 }
+
+/// The representations of a Bril program which `brilconv` can produce
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// Bril JSON
+    Json,
+    /// The Bril text format
+    Text,
+    /// The compact binary encoding from `bril_rs::binary`
+    Binary,
+    /// The bytecode of `fastbril`, from `bril_rs::bytecode`
+    Bytecode,
+}
+
+#[derive(Parser)]
+#[command(
+    about = "Converts a Bril program between its JSON, text, binary, and fastbril bytecode forms",
+    version,
+    author
+)]
+pub struct ConvCli {
+    /// The bril file to convert. stdin is assumed if file is not provided.
+    #[arg(short, long, action)]
+    pub file: Option<String>,
+    /// The format of the input. It is detected automatically if not provided, except for bytecode which has no header to detect.
+    #[arg(long, value_enum)]
+    pub from: Option<Format>,
+    /// The format to output
+    #[arg(short, long, value_enum, default_value = "json")]
+    pub to: Format,
+    /// Flag for whether position information should be included
+    #[arg(short, action = Count)]
+    pub position: u8,
+}
@@ -10,7 +10,11 @@ pub mod bril_grammar;
 pub mod cli;
 use std::fs::File;
 
-use bril_rs::{AbstractProgram, ColRow, Position};
+use bril_rs::{
+    error::{LoadError, ParseError},
+    AbstractProgram, ColRow, Position,
+};
+use lalrpop_util::lexer::Token;
 
 /// A helper function for processing the accepted Bril characters from their text representation
 #[must_use]
@@ -34,10 +38,14 @@ pub fn escape_control_chars(s: &str) -> Option<char> {
 pub struct Lines {
     use_pos: bool,
     with_end: bool,
-    new_lines: Vec<usize>,
+    newline_indices: Vec<usize>,
     src_name: Option<String>,
 }
 
+// The error of a fallible action in the parser: the span of the offending token and what is wrong with it
+#[doc(hidden)]
+pub type ActionError = (usize, &'static str, usize);
+
 // For use in the parser
 enum ParsingArgs {
     Func(String),
@@ -51,7 +59,7 @@ impl Lines {
             use_pos,
             with_end,
             src_name,
-            new_lines: input
+            newline_indices: input
                 .as_bytes()
                 .iter()
                 .enumerate()
@@ -78,61 +86,148 @@ impl Lines {
 
     fn get_row_col(&self, index: usize) -> Option<ColRow> {
         if self.use_pos {
-            Some(
-                self.new_lines
-                    .iter()
-                    .enumerate()
-                    //(i+1) because line numbers start at 1
-                    .map(|(i, j)| (i + 1, j))
-                    .fold(
-                        ColRow {
-                            // (index + 1) because column numbers start at 1
-                            col: (index + 1) as u64,
-                            // Hard code the first row to be 1
-                            row: 1,
-                        },
-                        |current, (line_num, idx)| {
-                            if *idx < index {
-                                ColRow {
-                                    // (line_num + 1) because line numbers start at 1
-                                    row: (line_num + 1) as u64,
-                                    // column values are kept relative to the previous index
-                                    col: ((index) - idx) as u64,
-                                }
-                            } else {
-                                current
-                            }
-                        },
-                    ),
-            )
+            Some(self.row_col(index))
         } else {
             None
         }
     }
+
+    // Errors always get a position, regardless of `use_pos`
+    fn get_error_position(&self, starting_index: usize, ending_index: Option<usize>) -> Position {
+        Position {
+            pos: self.row_col(starting_index),
+            pos_end: ending_index.map(|i| self.row_col(i)),
+            src: self.src_name.clone(),
+        }
+    }
+
+    fn row_col(&self, index: usize) -> ColRow {
+        self.newline_indices
+            .iter()
+            .enumerate()
+            //(i+1) because line numbers start at 1
+            .map(|(i, j)| (i + 1, j))
+            .fold(
+                ColRow {
+                    // (index + 1) because column numbers start at 1
+                    col: (index + 1) as u64,
+                    // Hard code the first row to be 1
+                    row: 1,
+                },
+                |current, (line_num, idx)| {
+                    if *idx < index {
+                        ColRow {
+                            // (line_num + 1) because line numbers start at 1
+                            row: (line_num + 1) as u64,
+                            // column values are kept relative to the previous index
+                            col: ((index) - idx) as u64,
+                        }
+                    } else {
+                        current
+                    }
+                },
+            )
+    }
+
+    fn convert_parse_error(
+        &self,
+        e: lalrpop_util::ParseError<usize, Token<'_>, ActionError>,
+    ) -> ParseError {
+        match e {
+            lalrpop_util::ParseError::InvalidToken { location } => ParseError {
+                message: "Invalid token".to_string(),
+                token: None,
+                expected: Vec::new(),
+                pos: Some(self.get_error_position(location, None)),
+            },
+            lalrpop_util::ParseError::UnrecognizedEof { location, expected } => ParseError {
+                message: "Unexpected end of file".to_string(),
+                token: None,
+                expected,
+                pos: Some(self.get_error_position(location, None)),
+            },
+            lalrpop_util::ParseError::UnrecognizedToken {
+                token: (start, Token(_, t), end),
+                expected,
+            } => ParseError {
+                message: "Unrecognized token".to_string(),
+                token: Some(t.to_string()),
+                expected,
+                pos: Some(self.get_error_position(start, Some(end))),
+            },
+            lalrpop_util::ParseError::ExtraToken {
+                token: (start, Token(_, t), end),
+            } => ParseError {
+                message: "Extra token".to_string(),
+                token: Some(t.to_string()),
+                expected: Vec::new(),
+                pos: Some(self.get_error_position(start, Some(end))),
+            },
+            lalrpop_util::ParseError::User {
+                error: (start, message, end),
+            } => ParseError {
+                message: message.to_string(),
+                token: None,
+                expected: Vec::new(),
+                pos: Some(self.get_error_position(start, Some(end))),
+            },
+        }
+    }
 }
 
 /// The entrance point to the bril2json parser. It takes an ```input```:[`std::io::Read`] which should be the Bril text file. You can control whether it includes source code positions with ```use_pos```.
-/// # Panics
-/// Will panic if the input is not well-formed Bril text
-pub fn parse_abstract_program_from_read<R: std::io::Read>(
+/// # Errors
+/// Will return an error if the input could not be read, if `file_name` does not exist, or if the input is not well-formed Bril text
+pub fn try_parse_abstract_program_from_read<R: std::io::Read>(
     mut input: R,
     use_pos: bool,
     with_end: bool,
     file_name: Option<String>,
-) -> AbstractProgram {
+) -> Result<AbstractProgram, LoadError> {
     let mut buffer = String::new();
-    input.read_to_string(&mut buffer).unwrap();
+    input.read_to_string(&mut buffer)?;
     let parser = bril_grammar::AbstractProgramParser::new();
 
-    let src_name = file_name.map(|f| std::fs::canonicalize(f).unwrap().display().to_string());
+    let src_name = file_name
+        .map(|f| std::fs::canonicalize(f).map(|p| p.display().to_string()))
+        .transpose()?;
 
+    let lines = Lines::new(&buffer, use_pos, with_end, src_name);
     parser
-        .parse(&Lines::new(&buffer, use_pos, with_end, src_name), &buffer)
-        .unwrap()
+        .parse(&lines, &buffer)
+        .map_err(|e| lines.convert_parse_error(e).into())
+}
+
+/// A panicking wrapper of [`try_parse_abstract_program_from_read`]
+/// # Panics
+/// Will panic if the input is not well-formed Bril text
+pub fn parse_abstract_program_from_read<R: std::io::Read>(
+    input: R,
+    use_pos: bool,
+    with_end: bool,
+    file_name: Option<String>,
+) -> AbstractProgram {
+    try_parse_abstract_program_from_read(input, use_pos, with_end, file_name).unwrap()
+}
+
+/// A wrapper around [`try_parse_abstract_program_from_read`] which assumes [`std::io::Stdin`] if `file_name` is [`None`]
+/// # Errors
+/// Will return an error if the input is not well-formed Bril text or if `file_name` does not exist
+pub fn try_parse_abstract_program(
+    use_pos: bool,
+    with_end: bool,
+    file_name: Option<String>,
+) -> Result<AbstractProgram, LoadError> {
+    let input: Box<dyn std::io::Read> = match file_name.as_ref() {
+        None => Box::new(std::io::stdin()),
+        Some(f) => Box::new(File::open(f)?),
+    };
+
+    try_parse_abstract_program_from_read(input, use_pos, with_end, file_name)
 }
 
 #[must_use]
-/// A wrapper around [`parse_abstract_program_from_read`] which assumes [`std::io::Stdin`] if `file_name` is [`None`]
+/// A panicking wrapper of [`try_parse_abstract_program`]
 /// # Panics
 /// Will panic if the input is not well-formed Bril text or if `file_name` does not exist
 pub fn parse_abstract_program(
@@ -140,10 +235,5 @@ pub fn parse_abstract_program(
     with_end: bool,
     file_name: Option<String>,
 ) -> AbstractProgram {
-    let input = file_name.clone().map_or_else(
-        || -> Box<dyn std::io::Read> { Box::new(std::io::stdin()) },
-        |f| Box::new(File::open(f).unwrap()),
-    );
-
-    parse_abstract_program_from_read(input, use_pos, with_end, file_name)
+    try_parse_abstract_program(use_pos, with_end, file_name).unwrap()
 }
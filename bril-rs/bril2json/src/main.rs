@@ -1,13 +1,15 @@
 use bril2json::cli::Cli;
-use bril2json::parse_abstract_program;
+use bril2json::try_parse_abstract_program;
 use bril_rs::output_abstract_program;
 use clap::Parser;
 
 fn main() {
     let args = Cli::parse();
-    output_abstract_program(&parse_abstract_program(
-        args.position >= 1,
-        args.position >= 2,
-        args.file,
-    ))
+    match try_parse_abstract_program(args.position >= 1, args.position >= 2, args.file) {
+        Ok(prog) => output_abstract_program(&prog),
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(2)
+        }
+    }
 }
@@ -10,4 +10,7 @@ pub struct Cli {
     /// A list of library paths to look for Bril files.
     #[arg(short, long, action, num_args=1..)]
     pub libs: Vec<PathBuf>,
+    /// Remove the functions which can't be called from `main` in the linked program.
+    #[arg(short, long, action)]
+    pub prune: bool,
 }
@@ -20,7 +20,7 @@ fn main() -> Result<(), BrildError> {
         handle_program(&mut map, program, &PathBuf::new(), &args.libs, true)?;
     }
 
-    let result = map.into_iter().fold(
+    let mut result = map.into_iter().fold(
         AbstractProgram {
             imports: Vec::new(),
             functions: Vec::new(),
@@ -31,6 +31,10 @@ fn main() -> Result<(), BrildError> {
         },
     );
 
+    if args.prune {
+        result.remove_unreachable_functions();
+    }
+
     output_abstract_program(&result);
 
     Ok(())
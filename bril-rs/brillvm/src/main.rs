@@ -1,4 +1,4 @@
-use bril_rs::load_program_from_read;
+use bril_rs::{extensions::Extension, try_load_abstract_program_from_read, Program};
 use brillvm::{cli::Cli, llvm::create_module_from_program};
 use clap::Parser;
 use inkwell::{
@@ -19,7 +19,25 @@ fn main() {
     } else {
         std::io::stdin().read_to_string(&mut src).unwrap()
     };
-    let prog = load_program_from_read(src.as_bytes());
+    let abstract_prog = try_load_abstract_program_from_read(src.as_bytes()).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(2)
+    });
+    // Reject programs using extensions that brillvm does not handle before trying to translate them.
+    // This works on the `AbstractProgram` so that it can find extensions which bril-rs is built without here.
+    if let Err(e) = abstract_prog.extensions().check(&[
+        Extension::Float,
+        Extension::Ssa,
+        Extension::Memory,
+        Extension::Position,
+    ]) {
+        eprintln!("error: {e}");
+        std::process::exit(2);
+    }
+    let prog = Program::try_from(abstract_prog).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(2)
+    });
 
     let context = Context::create();
     let runtime_path = args.runtime.as_ref().map_or("rt.bc", |f| f);
@@ -0,0 +1,65 @@
+//! Rebuilds every function of a Bril program read from stdin through `FunctionBuilder`, reporting the first label or variable which a function refers to but never defines
+//!
+//! Usage: `bril2json -p < prog.bril | brilbuild`
+
+use bril_rs::{
+    builder::FunctionBuilder, load_program, output_program, Code, EffectOps, Function, Instruction,
+    ValueOps,
+};
+
+fn rebuild(function: &Function) -> FunctionBuilder {
+    let mut builder = FunctionBuilder::new(&function.name, function.return_type.clone());
+    for a in &function.args {
+        builder.arg(&a.name, a.arg_type.clone());
+    }
+    for code in &function.instrs {
+        match code {
+            Code::Label { label, pos } => {
+                builder.set_pos(pos.clone());
+                builder.label(label);
+            }
+            Code::Instruction(i) => {
+                builder.set_pos(i.get_pos());
+                let args: Vec<&str> = i.args().iter().map(String::as_str).collect();
+                let funcs: Vec<&str> = i.funcs().iter().map(String::as_str).collect();
+                let labels: Vec<&str> = i.labels().iter().map(String::as_str).collect();
+                match i {
+                    Instruction::Constant {
+                        dest,
+                        const_type,
+                        value,
+                        ..
+                    } => builder.constant_to(dest, const_type.clone(), value.clone()),
+                    Instruction::Value {
+                        dest, op, op_type, ..
+                    } => builder.value_to(
+                        dest,
+                        ValueOps::clone(op),
+                        op_type.clone(),
+                        &args,
+                        &funcs,
+                        &labels,
+                    ),
+                    Instruction::Effect { op, .. } => {
+                        builder.effect(EffectOps::clone(op), &args, &funcs, &labels);
+                    }
+                }
+            }
+        }
+    }
+    builder
+}
+
+fn main() {
+    let mut program = load_program();
+    for function in &mut program.functions {
+        *function = rebuild(function).finish().unwrap_or_else(|e| {
+            match e.pos() {
+                Some(p) => eprintln!("Line {}, Column {}: {e}", p.pos.row, p.pos.col),
+                None => eprintln!("{e}"),
+            }
+            std::process::exit(1);
+        });
+    }
+    output_program(&program);
+}
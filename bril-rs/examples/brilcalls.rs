@@ -0,0 +1,21 @@
+//! Prints the call graph of a Bril program read from stdin in the DOT format, or removes the functions which are unreachable from `main`
+//!
+//! Usage: `bril2json < prog.bril | brilcalls [dot|prune]`
+
+use bril_rs::{callgraph::CallGraph, load_program, output_program};
+
+fn main() {
+    let mode = std::env::args().nth(1).unwrap_or_else(|| "dot".to_string());
+    let mut program = load_program();
+    match mode.as_str() {
+        "dot" => print!("{}", CallGraph::new(&program).to_dot()),
+        "prune" => {
+            program.remove_unreachable_functions();
+            output_program(&program);
+        }
+        _ => {
+            eprintln!("usage: brilcalls [dot|prune]");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Renames the variables and labels of a Bril program read from stdin into a canonical form, or compares two programs up to renaming
+//!
+//! Usage: `bril2json < prog.bril | brilcanon` or `brilcanon first.json second.json`
+
+use bril_rs::{
+    canonical::{alpha_eq, canonicalize},
+    load_program, load_program_from_read, output_program,
+};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => {
+            let mut program = load_program();
+            for f in &mut program.functions {
+                if let Err(e) = canonicalize(f) {
+                    eprintln!("error: {e}");
+                    std::process::exit(2);
+                }
+            }
+            output_program(&program);
+        }
+        [first, second] => {
+            let [first, second] = [first, second].map(|path| {
+                load_program_from_read(std::fs::File::open(path).unwrap_or_else(|e| {
+                    eprintln!("error: {path}: {e}");
+                    std::process::exit(2);
+                }))
+            });
+            if let Err(d) = alpha_eq(&first, &second) {
+                eprintln!("{d}");
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            eprintln!("usage: brilcanon [first.json second.json]");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Prints the control-flow graph of each function of a Bril program read from stdin in the DOT format, like `examples/cfg_dot.py`
+//!
+//! Usage: `bril2json < prog.bril | brilcfg`
+
+use bril_rs::{cfg::Cfg, load_program};
+
+// `examples/cfg_dot.py` only quotes names which aren't alphanumeric
+fn quote_if_needed(s: &str) -> String {
+    if s.chars().all(char::is_alphanumeric) {
+        s.to_string()
+    } else {
+        format!("\"{s}\"")
+    }
+}
+
+fn main() {
+    let program = load_program();
+    for function in program.functions {
+        let cfg = Cfg::try_from(function).unwrap_or_else(|e| {
+            eprintln!("error: {e}");
+            std::process::exit(2);
+        });
+        println!("digraph {} {{", cfg.name);
+        for block in &cfg.blocks {
+            println!("  {};", block.name);
+        }
+        for block in &cfg.blocks {
+            for s in &block.successors {
+                println!(
+                    "  {} -> {};",
+                    quote_if_needed(&block.name),
+                    quote_if_needed(&cfg.blocks[*s].name)
+                );
+            }
+        }
+        println!("}}");
+    }
+}
@@ -0,0 +1,39 @@
+//! Removes dead code from a Bril program read from stdin, like `examples/tdce.py`
+//!
+//! Usage: `bril2json < prog.bril | brildce [tdce|tdcep|dkp|tdce+|global]`
+
+use bril_rs::{
+    cfg::Cfg,
+    dce::{drop_killed_pass, global_dce, trivial_dce, trivial_dce_pass, trivial_dce_plus},
+    load_program, output_program, Function,
+};
+
+fn global(function: &mut Function) {
+    let mut cfg = Cfg::try_from(function.clone()).unwrap();
+    global_dce(&mut cfg);
+    *function = cfg.into();
+}
+
+fn main() {
+    let mode = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "tdce".to_string());
+    let pass: fn(&mut Function) = match mode.as_str() {
+        "tdce" => trivial_dce,
+        "tdcep" => |f| {
+            trivial_dce_pass(f);
+        },
+        "dkp" => |f| {
+            drop_killed_pass(f);
+        },
+        "tdce+" => trivial_dce_plus,
+        "global" => global,
+        _ => {
+            eprintln!("usage: brildce [tdce|tdcep|dkp|tdce+|global]");
+            std::process::exit(2);
+        }
+    };
+    let mut program = load_program();
+    program.functions.iter_mut().for_each(pass);
+    output_program(&program);
+}
@@ -0,0 +1,89 @@
+//! Prints the facts at the start and end of every basic block for one of the built-in dataflow analyses, in the same format as `examples/df.py`
+//!
+//! Usage: `bril2json < prog.bril | brildf <defined|reaching|live|avail|cprop>`
+
+use std::{collections::BTreeSet, fmt::Display};
+
+use bril_rs::{
+    cfg::Cfg,
+    dataflow::{
+        solve, Analysis, AvailableExpressions, ConstValue, ConstantPropagation, Definition,
+        LiveVariables, ReachingDefinitions,
+    },
+    load_program,
+};
+
+fn fmt_set(items: impl Iterator<Item = String>) -> String {
+    let mut items: Vec<String> = items.collect();
+    if items.is_empty() {
+        return "∅".to_string();
+    }
+    items.sort();
+    items.join(", ")
+}
+
+fn print_analysis<A: Analysis>(analysis: &A, cfg: &Cfg, fmt: impl Fn(&Cfg, &A::Domain) -> String) {
+    let result = solve(analysis, cfg);
+    for (i, block) in cfg.blocks.iter().enumerate() {
+        println!("{}:", block.name);
+        println!("  in:  {}", fmt(cfg, &result.ins[i]));
+        println!("  out: {}", fmt(cfg, &result.outs[i]));
+    }
+}
+
+fn fmt_definition(cfg: &Cfg, d: &Definition) -> String {
+    d.site.map_or_else(
+        || format!("{}@args", d.var),
+        |l| format!("{}@{}.{}", d.var, cfg.blocks[l.block].name, l.index),
+    )
+}
+
+fn fmt_strings<T: Display>(items: impl Iterator<Item = T>) -> String {
+    fmt_set(items.map(|i| i.to_string()))
+}
+
+fn main() {
+    let analysis = std::env::args().nth(1).unwrap_or_default();
+    let program = load_program();
+    for function in program.functions {
+        let cfg = Cfg::try_from(function).unwrap();
+        match analysis.as_str() {
+            // The variables which may have been assigned, like `examples/df.py defined`, are those with a reaching definition from an instruction
+            "defined" => print_analysis(&ReachingDefinitions, &cfg, |_, defs| {
+                let vars: BTreeSet<&str> = defs
+                    .iter()
+                    .filter(|d| d.site.is_some())
+                    .map(|d| d.var.as_str())
+                    .collect();
+                fmt_strings(vars.into_iter())
+            }),
+            "reaching" => print_analysis(&ReachingDefinitions, &cfg, |cfg, defs| {
+                fmt_set(defs.iter().map(|d| fmt_definition(cfg, d)))
+            }),
+            "live" => print_analysis(&LiveVariables, &cfg, |_, vars| fmt_strings(vars.iter())),
+            "avail" => print_analysis(&AvailableExpressions, &cfg, |_, exprs| {
+                exprs.as_ref().map_or_else(
+                    || "⊤".to_string(),
+                    |exprs| {
+                        fmt_set(exprs.iter().map(|e| {
+                            std::iter::once(e.op.to_string())
+                                .chain(e.args.iter().cloned())
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        }))
+                    },
+                )
+            }),
+            "cprop" => print_analysis(&ConstantPropagation, &cfg, |_, consts| {
+                fmt_set(consts.iter().map(|(var, c)| match c {
+                    ConstValue::Known(l) => format!("{var}: {l}"),
+                    ConstValue::Unknown => format!("{var}: ?"),
+                }))
+            }),
+            _ => {
+                eprintln!("usage: brildf <defined|reaching|live|avail|cprop>");
+                std::process::exit(2);
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Prints the dominators, dominance frontier, or dominator tree children of every basic block as JSON, in the same format as `examples/dom.py`
+//!
+//! Usage: `bril2json < prog.bril | brildom <dom|front|tree>`
+
+use std::collections::BTreeMap;
+
+use bril_rs::{cfg::Cfg, dom::Dominators, load_program};
+
+fn main() {
+    let mode = std::env::args().nth(1).unwrap_or_default();
+    let program = load_program();
+    for function in program.functions {
+        let cfg = Cfg::try_from(function).unwrap();
+        let dom = Dominators::new(&cfg);
+        let related: fn(&Dominators, usize) -> Vec<usize> = match mode.as_str() {
+            "dom" => Dominators::dominators,
+            "front" => |dom, b| dom.frontier(b).to_vec(),
+            "tree" => |dom, b| dom.children(b).to_vec(),
+            _ => {
+                eprintln!("usage: brildom <dom|front|tree>");
+                std::process::exit(2);
+            }
+        };
+        let result: BTreeMap<&str, Vec<&str>> = cfg
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(b, block)| {
+                let mut names: Vec<&str> = related(&dom, b)
+                    .into_iter()
+                    .map(|r| cfg.blocks[r].name.as_str())
+                    .collect();
+                names.sort_unstable();
+                (block.name.as_str(), names)
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&result).unwrap());
+    }
+}
@@ -0,0 +1,14 @@
+//! Runs global value numbering over a Bril program in SSA form read from stdin
+//!
+//! Usage: `bril2json < prog.bril | brilssa to | brilgvn`
+
+use bril_rs::{gvn::gvn, load_program, output_program};
+
+fn main() {
+    let mut program = load_program();
+    if let Err(e) = gvn(&mut program) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+    output_program(&program);
+}
@@ -0,0 +1,15 @@
+//! Fills in the missing types of a Bril program read from stdin, like `type-infer/infer.py`
+//!
+//! Usage: `bril2json < prog.bril | brilinfer`
+
+use bril_rs::{infer::infer_types, load_abstract_program, output_program};
+
+fn main() {
+    match infer_types(load_abstract_program()) {
+        Ok(program) => output_program(&program),
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Inlines function calls in a Bril program read from stdin
+//!
+//! Usage: `bril2json < prog.bril | brilinline [callee size] [caller size] [recursion depth]`
+
+use bril_rs::{
+    inline::{inline, InlineOptions},
+    load_program, output_program,
+};
+
+fn main() {
+    let args: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|a| {
+            a.parse().unwrap_or_else(|_| {
+                eprintln!("usage: brilinline [callee size] [caller size] [recursion depth]");
+                std::process::exit(2);
+            })
+        })
+        .collect();
+    let default = InlineOptions::default();
+    let options = InlineOptions {
+        max_callee_size: args.first().copied().unwrap_or(default.max_callee_size),
+        max_caller_size: args.get(1).copied().unwrap_or(default.max_caller_size),
+        max_recursion_depth: args.get(2).copied().unwrap_or(default.max_recursion_depth),
+    };
+    let mut program = load_program();
+    inline(&mut program, options);
+    output_program(&program);
+}
@@ -0,0 +1,70 @@
+//! Reports the instructions of a Bril program read from stdin which may fail at runtime according to an interval analysis, or prints the range of each integer and pointer at the start and end of every basic block in the same format as `brildf`
+//!
+//! Usage: `bril2json -p < prog.bril | brilinterval [check|ranges]`
+
+use bril_rs::{
+    absint::interpret,
+    cfg::Cfg,
+    interval::{check_function, AbstractValue, IntervalAnalysis, IntervalState},
+    load_program,
+};
+
+fn fmt_state(state: Option<&IntervalState>) -> String {
+    let Some(state) = state else {
+        return "⊥".to_string();
+    };
+    let ranges: Vec<String> = state
+        .vars
+        .iter()
+        .filter_map(|(var, v)| match v {
+            AbstractValue::Int(i) => Some(format!("{var}: {i}")),
+            AbstractValue::Pointer { size, offset } => Some(format!("{var}: {offset} of {size}")),
+            AbstractValue::Comparison { .. } | AbstractValue::Unknown => None,
+        })
+        .collect();
+    if ranges.is_empty() {
+        "∅".to_string()
+    } else {
+        ranges.join(", ")
+    }
+}
+
+fn main() {
+    let mode = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "check".to_string());
+    let program = load_program();
+    let mut failed = false;
+    for function in program.functions {
+        match mode.as_str() {
+            "check" => match check_function(&function) {
+                Ok(warnings) => {
+                    for w in warnings {
+                        failed = true;
+                        eprintln!("{w}");
+                    }
+                }
+                Err(e) => {
+                    eprintln!("@{}: {e}", function.name);
+                    std::process::exit(2);
+                }
+            },
+            "ranges" => {
+                let cfg = Cfg::try_from(function).unwrap();
+                let result = interpret(&IntervalAnalysis, &cfg);
+                for (i, block) in cfg.blocks.iter().enumerate() {
+                    println!("{}:", block.name);
+                    println!("  in:  {}", fmt_state(result.ins[i].as_ref()));
+                    println!("  out: {}", fmt_state(result.outs[i].as_ref()));
+                }
+            }
+            _ => {
+                eprintln!("usage: brilinterval [check|ranges]");
+                std::process::exit(2);
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,50 @@
+//! Hoists loop-invariant code out of the loops of a Bril program read from stdin, or prints the natural loops of each function
+//!
+//! Usage: `bril2json < prog.bril | brillicm [licm|loops]`
+
+use bril_rs::{
+    cfg::Cfg,
+    dom::Dominators,
+    licm::licm,
+    load_program,
+    loops::{insert_preheaders, natural_loops},
+    output_program,
+};
+
+fn main() {
+    let mode = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "licm".to_string());
+    let mut program = load_program();
+    match mode.as_str() {
+        "licm" => {
+            for f in &mut program.functions {
+                licm(f).unwrap();
+            }
+            output_program(&program);
+        }
+        "loops" => {
+            for f in program.functions {
+                let mut cfg = Cfg::try_from(f).unwrap();
+                insert_preheaders(&mut cfg);
+                let loops = natural_loops(&cfg, &Dominators::new(&cfg));
+                println!("@{}:", cfg.name);
+                for l in loops {
+                    let name = |b: &usize| format!(".{}", cfg.blocks[*b].name);
+                    let blocks: Vec<String> = l.blocks.iter().map(name).collect();
+                    println!(
+                        "  {} (depth {}, preheader {}): {}",
+                        name(&l.header),
+                        l.depth,
+                        l.preheader(&cfg).as_ref().map_or("none".to_string(), name),
+                        blocks.join(" ")
+                    );
+                }
+            }
+        }
+        _ => {
+            eprintln!("usage: brillicm [licm|loops]");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Runs local value numbering over a Bril program read from stdin, like `examples/lvn.py`
+//!
+//! Usage: `bril2json < prog.bril | brillvn [-p] [-c] [-f]`
+
+use bril_rs::{
+    load_program,
+    lvn::{lvn, LvnOptions},
+    output_program,
+};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let has = |flag: &str| args.iter().any(|a| a == flag);
+    let options = LvnOptions {
+        propagate: has("-p"),
+        canonicalize: has("-c"),
+        fold: has("-f"),
+    };
+    let mut program = load_program();
+    lvn(&mut program, options);
+    output_program(&program);
+}
@@ -0,0 +1,21 @@
+//! Statically checks a Bril program read from stdin for leaks, double frees, uses after free, and frees of offset pointers, which `brilirs` only finds while running it
+//!
+//! Usage: `bril2json -p < prog.bril | brilmemcheck`
+
+use bril_rs::{load_program, memcheck::check_program};
+
+fn main() {
+    match check_program(&load_program()) {
+        Ok(errors) if errors.is_empty() => {}
+        Ok(errors) => {
+            for e in errors {
+                eprintln!("{e}");
+            }
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Runs sparse conditional constant propagation over a Bril program in SSA form read from stdin
+//!
+//! Usage: `bril2json < prog.bril | brilssa to | brilsccp`
+
+use bril_rs::{load_program, output_program, sccp::sccp};
+
+fn main() {
+    let mut program = load_program();
+    if let Err(e) = sccp(&mut program) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+    output_program(&program);
+}
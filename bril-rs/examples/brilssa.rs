@@ -0,0 +1,40 @@
+//! Converts a Bril program read from stdin into or out of SSA form, or checks whether it is in SSA form like `examples/is_ssa.py`
+//!
+//! Usage: `bril2json < prog.bril | brilssa <to|from|check>`
+
+use bril_rs::{
+    cfg::Cfg,
+    load_program, output_program,
+    ssa::{from_ssa, is_ssa, to_ssa},
+    Function,
+};
+
+fn transform(function: Function, pass: fn(&mut Cfg)) -> Function {
+    let mut cfg = Cfg::try_from(function).unwrap();
+    pass(&mut cfg);
+    cfg.into()
+}
+
+fn main() {
+    let mode = std::env::args().nth(1).unwrap_or_default();
+    let mut program = load_program();
+    let pass = match mode.as_str() {
+        "to" => to_ssa,
+        "from" => from_ssa,
+        "check" => {
+            let ssa = program.functions.iter().all(is_ssa);
+            println!("{}", if ssa { "yes" } else { "no" });
+            return;
+        }
+        _ => {
+            eprintln!("usage: brilssa <to|from|check>");
+            std::process::exit(2);
+        }
+    };
+    program.functions = program
+        .functions
+        .into_iter()
+        .map(|f| transform(f, pass))
+        .collect();
+    output_program(&program);
+}
@@ -0,0 +1,114 @@
+//! Walks a Bril program read from stdin with the `visit` traits, either renaming everything in it or printing every name and type in it as JSON
+//!
+//! Usage: `bril2json < prog.bril | brilvisit <rename|names>`
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bril_rs::{
+    load_program, output_program,
+    visit::{walk_argument, walk_argument_mut, walk_function_mut, walk_type, Visit, VisitMut},
+    Argument, Function, Type,
+};
+
+// Puts `v.` in front of variables, `l.` in front of labels, and `f.` in front of every function but `main`
+struct Rename;
+
+fn function(name: &mut String) {
+    if name != "main" {
+        *name = format!("f.{name}");
+    }
+}
+
+impl VisitMut for Rename {
+    fn visit_function_mut(&mut self, f: &mut Function) {
+        function(&mut f.name);
+        walk_function_mut(self, f);
+    }
+
+    fn visit_argument_mut(&mut self, argument: &mut Argument) {
+        argument.name = format!("v.{}", argument.name);
+        walk_argument_mut(self, argument);
+    }
+
+    fn visit_label_mut(&mut self, label: &mut String) {
+        *label = format!("l.{label}");
+    }
+
+    fn visit_dest_mut(&mut self, dest: &mut String) {
+        *dest = format!("v.{dest}");
+    }
+
+    fn visit_arg_mut(&mut self, arg: &mut String) {
+        *arg = format!("v.{arg}");
+    }
+
+    fn visit_func_mut(&mut self, func: &mut String) {
+        function(func);
+    }
+
+    fn visit_target_mut(&mut self, target: &mut String) {
+        *target = format!("l.{target}");
+    }
+}
+
+// Every name of each kind, and every type including the pointees of pointer types
+#[derive(Default)]
+struct Names(BTreeMap<&'static str, BTreeSet<String>>);
+
+impl Names {
+    fn insert(&mut self, kind: &'static str, name: &str) {
+        self.0.entry(kind).or_default().insert(name.to_string());
+    }
+}
+
+impl Visit for Names {
+    fn visit_argument(&mut self, argument: &Argument) {
+        self.insert("args", &argument.name);
+        walk_argument(self, argument);
+    }
+
+    fn visit_label(&mut self, label: &str) {
+        self.insert("labels", label);
+    }
+
+    fn visit_dest(&mut self, dest: &str) {
+        self.insert("dests", dest);
+    }
+
+    fn visit_arg(&mut self, arg: &str) {
+        self.insert("uses", arg);
+    }
+
+    fn visit_func(&mut self, func: &str) {
+        self.insert("funcs", func);
+    }
+
+    fn visit_target(&mut self, target: &str) {
+        self.insert("targets", target);
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        self.insert("types", &ty.to_string());
+        walk_type(self, ty);
+    }
+}
+
+fn main() {
+    let mode = std::env::args().nth(1).unwrap_or_default();
+    let mut program = load_program();
+    match mode.as_str() {
+        "rename" => {
+            Rename.visit_program_mut(&mut program);
+            output_program(&program);
+        }
+        "names" => {
+            let mut names = Names::default();
+            names.visit_program(&program);
+            println!("{}", serde_json::to_string_pretty(&names.0).unwrap());
+        }
+        _ => {
+            eprintln!("usage: brilvisit <rename|names>");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Checks that a Bril program read from stdin is well formed, like `brilck` but without checking the types of arguments
+//!
+//! Usage: `bril2json -p < prog.bril | brilwf`
+
+use bril_rs::{load_program, wellformed::check_program};
+
+fn main() {
+    if let Err(errors) = check_program(&load_program()) {
+        for e in errors {
+            eprintln!("{e}");
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    cfg::Cfg,
+    dataflow::{DataflowResult, Location},
+    dom::Dominators,
+    loops::natural_loops,
+    Instruction,
+};
+
+/// How many times [`interpret`] recomputes every block with [`Lattice::narrow`] after reaching a fixed point
+const NARROWING_PASSES: usize = 2;
+
+/// The abstract values of an [`AbstractInterpretation`], where joining values moves up the lattice
+///
+/// The bottom of the lattice, for program points which are never reached, is represented by [`None`] in [`interpret`].
+pub trait Lattice: Clone + PartialEq {
+    /// Updates `self` to the least upper bound of `self` and `other`, where control-flow merges
+    fn join(&mut self, other: &Self);
+
+    /// Updates `self`, the value at a loop head from the previous iteration, to a value above both `self` and `other`
+    ///
+    /// Widening `self` over and over again must reach a fixed point after finitely many steps, even if the lattice has infinite ascending chains.
+    fn widen(&mut self, other: &Self);
+
+    /// Updates `self`, a value reached by widening, to one between `self` and `other`, which was recomputed from `self`
+    ///
+    /// This wins back some of the precision lost to [`Lattice::widen`]. The default keeps `self`.
+    fn narrow(&mut self, other: &Self) {
+        let _ = other;
+    }
+}
+
+/// A forward abstract interpretation which can be run over a [`Cfg`] with [`interpret`]
+///
+/// Unlike an [`crate::dataflow::Analysis`], it can look at each edge of the [`Cfg`] separately, which lets branches refine what they know about their condition, and its lattice can have infinite ascending chains thanks to [`Lattice::widen`].
+pub trait AbstractInterpretation {
+    /// The facts computed at each program point
+    type State: Lattice;
+
+    /// The state at the start of the entry block
+    fn entry(&self, cfg: &Cfg) -> Self::State;
+
+    /// Updates `state` to reflect executing `instr`, which is found at `loc`
+    fn transfer(&self, state: &mut Self::State, instr: &Instruction, loc: Location);
+
+    /// Updates `state`, the state at the end of block `from`, to what holds when control goes from there to block `to`
+    ///
+    /// Returns `false` if the edge can't be taken from `state`. The default keeps `state` as is.
+    fn edge(&self, state: &mut Self::State, cfg: &Cfg, from: usize, to: usize) -> bool {
+        let _ = (state, cfg, from, to);
+        true
+    }
+}
+
+/// Solves `analysis` over `cfg` until a fixed point is reached, widening at loop heads and then narrowing
+///
+/// This follows the recursive strategy of "Efficient chaotic iteration strategies with widenings" by Bourdoncle: blocks are visited in reverse post-order, except that each natural loop is visited as a unit, starting from its header, until the state at its header stops changing.
+/// The state flowing into the header is widened on each visit, and once it is stable the loop is visited a couple more times with [`Lattice::narrow`] in place of widening, before moving on to the blocks after the loop.
+/// Any edge which can still change the result afterwards, which only happens with irreducible control flow, is then followed until a fixed point is reached, widening at the target of every edge which goes backwards in reverse post-order.
+/// The states of blocks which are never reached, including those which are only reached through edges that [`AbstractInterpretation::edge`] rules out, are [`None`].
+#[must_use]
+pub fn interpret<A: AbstractInterpretation>(
+    analysis: &A,
+    cfg: &Cfg,
+) -> DataflowResult<Option<A::State>> {
+    let num_blocks = cfg.blocks.len();
+    let order = cfg.reverse_post_order();
+    let mut solver = Solver {
+        analysis,
+        cfg,
+        schedule: Schedule::new(cfg, &order),
+        result: DataflowResult {
+            ins: vec![None; num_blocks],
+            outs: vec![None; num_blocks],
+        },
+        visited: vec![false; num_blocks],
+    };
+    let top = std::mem::take(&mut solver.schedule.top);
+    solver.run(&top);
+
+    let mut rank = vec![usize::MAX; num_blocks];
+    for (r, &b) in order.iter().enumerate() {
+        rank[b] = r;
+    }
+    // The worklist holds ranks, so that blocks are always taken in reverse post-order
+    let mut worklist: BTreeSet<usize> = (0..order.len()).collect();
+    while let Some(r) = worklist.pop_first() {
+        let b = order[r];
+        let retreating = cfg.blocks[b].predecessors.iter().any(|p| rank[*p] >= r);
+        if solver.update(b, if retreating { Mode::Widen } else { Mode::Join }) {
+            worklist.extend(cfg.blocks[b].successors.iter().map(|s| rank[*s]));
+        }
+    }
+    solver.result
+}
+
+// How a block combines the state it had with the state flowing into it
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Join,
+    Widen,
+    Narrow,
+}
+
+// An element of the order that blocks are visited in: either a block which isn't the header of a loop, or an index into `Schedule::loops`
+enum Item {
+    Block(usize),
+    Loop(usize),
+}
+
+// The blocks of the cfg in reverse post-order, where the blocks of each natural loop are grouped after its header
+struct Schedule {
+    top: Vec<Item>,
+    // The header of each loop, which is visited before the rest of the loop, along with the rest of the loop
+    loops: Vec<(usize, Vec<Item>)>,
+}
+
+impl Schedule {
+    fn new(cfg: &Cfg, order: &[usize]) -> Self {
+        let dom = Dominators::new(cfg);
+        let loops = natural_loops(cfg, &dom);
+        // Loops come before the loops that contain them, so the last one written for each block is the innermost
+        let mut innermost = vec![None; cfg.blocks.len()];
+        for (i, l) in loops.iter().enumerate().rev() {
+            for &b in &l.blocks {
+                innermost[b] = Some(i);
+            }
+        }
+
+        let mut schedule = Self {
+            top: Vec::new(),
+            loops: loops.iter().map(|l| (l.header, Vec::new())).collect(),
+        };
+        for &b in order {
+            let (item, within) = match innermost[b] {
+                Some(l) if loops[l].header == b => (Item::Loop(l), loops[l].parent),
+                l => (Item::Block(b), l),
+            };
+            match within {
+                Some(l) => schedule.loops[l].1.push(item),
+                None => schedule.top.push(item),
+            }
+        }
+        schedule
+    }
+}
+
+struct Solver<'a, A: AbstractInterpretation> {
+    analysis: &'a A,
+    cfg: &'a Cfg,
+    schedule: Schedule,
+    result: DataflowResult<Option<A::State>>,
+    visited: Vec<bool>,
+}
+
+impl<A: AbstractInterpretation> Solver<'_, A> {
+    fn run(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Block(b) => {
+                    self.update(*b, Mode::Join);
+                }
+                Item::Loop(l) => {
+                    let header = self.schedule.loops[*l].0;
+                    let body = std::mem::take(&mut self.schedule.loops[*l].1);
+                    while self.update(header, Mode::Widen) {
+                        self.run(&body);
+                    }
+                    for _ in 0..NARROWING_PASSES {
+                        self.update(header, Mode::Narrow);
+                        self.run(&body);
+                    }
+                    self.schedule.loops[*l].1 = body;
+                }
+            }
+        }
+    }
+
+    // Recomputes the state of block `b` from the states flowing into it, returning whether it changed
+    fn update(&mut self, b: usize, mode: Mode) -> bool {
+        let mut value = incoming(self.analysis, self.cfg, &self.result.outs, b);
+        if let Some(old) = &self.result.ins[b] {
+            match (mode, &value) {
+                (Mode::Join, _) => {}
+                (Mode::Widen, Some(new)) => {
+                    let mut widened = old.clone();
+                    widened.widen(new);
+                    value = Some(widened);
+                }
+                (Mode::Narrow, new) => {
+                    let mut narrowed = old.clone();
+                    if let Some(new) = new {
+                        narrowed.narrow(new);
+                    }
+                    value = Some(narrowed);
+                }
+                (Mode::Widen, None) => value = Some(old.clone()),
+            }
+        }
+        if self.visited[b] && value == self.result.ins[b] {
+            return false;
+        }
+        self.visited[b] = true;
+        self.result.ins[b].clone_from(&value);
+        self.result.outs[b] = run_block(self.analysis, self.cfg, b, value);
+        true
+    }
+}
+
+// The join of the states flowing into block `b` along each edge which can be taken, along with the entry state for the entry block
+fn incoming<A: AbstractInterpretation>(
+    analysis: &A,
+    cfg: &Cfg,
+    outs: &[Option<A::State>],
+    b: usize,
+) -> Option<A::State> {
+    let mut value = (b == Cfg::ENTRY).then(|| analysis.entry(cfg));
+    for &p in &cfg.blocks[b].predecessors {
+        let Some(mut state) = outs[p].clone() else {
+            continue;
+        };
+        if !analysis.edge(&mut state, cfg, p, b) {
+            continue;
+        }
+        match &mut value {
+            Some(v) => v.join(&state),
+            None => value = Some(state),
+        }
+    }
+    value
+}
+
+fn run_block<A: AbstractInterpretation>(
+    analysis: &A,
+    cfg: &Cfg,
+    block: usize,
+    mut value: Option<A::State>,
+) -> Option<A::State> {
+    if let Some(state) = &mut value {
+        for (index, instr) in cfg.blocks[block].instrs.iter().enumerate() {
+            analysis.transfer(state, instr, Location { block, index });
+        }
+    }
+    value
+}
@@ -259,7 +259,7 @@ impl Display for AbstractInstruction {
 }
 
 /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#type>
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum AbstractType {
     /// For example `bool` => `Primitive("bool")`
     Primitive(String),
@@ -0,0 +1,679 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+};
+
+use thiserror::Error;
+
+use crate::{
+    AbstractType, Argument, Code, ColRow, ConstOps, EffectOps, Function, Instruction, Literal,
+    Position, Program, Type, ValueOps,
+};
+#[cfg(feature = "import")]
+use crate::{Import, ImportedFunction};
+
+/// The bytes every binary Bril program starts with, which can never begin a JSON or text program
+pub const MAGIC: [u8; 4] = *b"\0BRL";
+
+/// The version of the encoding written by [`write_binary`]
+pub const VERSION: u8 = 1;
+
+const FLAG_POSITIONS: u8 = 1;
+
+const CODE_LABEL: u8 = 0;
+const CODE_CONSTANT: u8 = 1;
+const CODE_VALUE: u8 = 2;
+const CODE_EFFECT: u8 = 3;
+
+const TYPE_INT: u8 = 0;
+const TYPE_BOOL: u8 = 1;
+const TYPE_FLOAT: u8 = 2;
+const TYPE_CHAR: u8 = 3;
+const TYPE_POINTER: u8 = 4;
+// Followed by the type as it was written, for types from extensions which `bril_rs` does not know about
+const TYPE_OTHER: u8 = 5;
+
+// Types are nested at most this deep, so that reading a malformed program can't overflow the stack
+const MAX_TYPE_DEPTH: usize = 64;
+
+const LITERAL_INT: u8 = 0;
+const LITERAL_BOOL: u8 = 1;
+const LITERAL_FLOAT: u8 = 2;
+const LITERAL_CHAR: u8 = 3;
+
+/// Errors from reading a binary Bril program
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum BinaryError {
+    /// There has been an io error while reading the input
+    #[error("There has been an io error: {0}")]
+    Io(#[from] io::Error),
+    /// The input does not start with [`MAGIC`]
+    #[error("input is not a binary Bril program")]
+    NotBinary,
+    /// The input was written by an incompatible version of the encoding
+    #[error("unsupported binary Bril version {0}, expected {VERSION}")]
+    UnsupportedVersion(u8),
+    /// The input ended in the middle of the program
+    #[error("unexpected end of binary Bril program")]
+    Truncated,
+    /// The input is not a well-formed encoding of a program
+    #[error("malformed binary Bril program: {0}")]
+    Malformed(String),
+    /// The program uses an extension which is not enabled in this build of `bril_rs`
+    #[error("binary Bril program uses {0}, which is not supported")]
+    Unsupported(String),
+}
+
+/// Whether `bytes` starts like a binary Bril program, as opposed to JSON or text
+#[must_use]
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// Writes `program` to `output` in the compact binary encoding
+///
+/// Every string is stored once in a table and referred to by index, and all integers are variable length.
+/// Source positions are only included when `with_pos` is set.
+/// # Errors
+/// Will return an error if `output` can't be written to
+pub fn write_binary<W: Write>(program: &Program, with_pos: bool, mut output: W) -> io::Result<()> {
+    let mut encoder = Encoder {
+        with_pos,
+        ..Encoder::default()
+    };
+    encoder.program(program);
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC);
+    header.push(VERSION);
+    header.push(if with_pos { FLAG_POSITIONS } else { 0 });
+    write_varint(&mut header, encoder.table.len() as u64);
+    for s in &encoder.table {
+        write_varint(&mut header, s.len() as u64);
+        header.extend_from_slice(s.as_bytes());
+    }
+    output.write_all(&header)?;
+    output.write_all(&encoder.body)?;
+    output.flush()
+}
+
+/// Reads a program written by [`write_binary`] from `input`
+/// # Errors
+/// Will return an error if the input could not be read, is not a binary Bril program, or uses an extension which is not enabled
+pub fn read_binary<R: Read>(mut input: R) -> Result<Program, BinaryError> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    read_binary_from_slice(&bytes)
+}
+
+/// Like [`read_binary`], for input which has already been read into memory
+/// # Errors
+/// Will return an error if `bytes` is not a binary Bril program or uses an extension which is not enabled
+pub fn read_binary_from_slice(bytes: &[u8]) -> Result<Program, BinaryError> {
+    let rest = bytes.strip_prefix(&MAGIC).ok_or(BinaryError::NotBinary)?;
+    let mut decoder = Decoder {
+        bytes: rest,
+        strings: Vec::new(),
+        with_pos: false,
+    };
+    let version = decoder.byte()?;
+    if version != VERSION {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+    decoder.with_pos = decoder.byte()? & FLAG_POSITIONS != 0;
+    for _ in 0..decoder.len()? {
+        let len = decoder.len()?;
+        let s = decoder.take(len)?;
+        let s = std::str::from_utf8(s).map_err(|e| BinaryError::Malformed(e.to_string()))?;
+        decoder.strings.push(s.to_string());
+    }
+    let program = decoder.program()?;
+    if !decoder.bytes.is_empty() {
+        return Err(BinaryError::Malformed(
+            "trailing bytes after program".to_string(),
+        ));
+    }
+    Ok(program)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n.to_le_bytes()[0] | 0x80);
+        n >>= 7;
+    }
+    out.push(n.to_le_bytes()[0]);
+}
+
+#[derive(Default)]
+struct Encoder {
+    with_pos: bool,
+    indices: HashMap<String, u64>,
+    table: Vec<String>,
+    body: Vec<u8>,
+}
+
+impl Encoder {
+    fn byte(&mut self, b: u8) {
+        self.body.push(b);
+    }
+
+    fn varint(&mut self, n: u64) {
+        write_varint(&mut self.body, n);
+    }
+
+    fn len(&mut self, n: usize) {
+        self.varint(n as u64);
+    }
+
+    fn string(&mut self, s: &str) {
+        let index = if let Some(i) = self.indices.get(s) {
+            *i
+        } else {
+            let i = self.table.len() as u64;
+            self.indices.insert(s.to_string(), i);
+            self.table.push(s.to_string());
+            i
+        };
+        self.varint(index);
+    }
+
+    fn strings(&mut self, strings: &[String]) {
+        self.len(strings.len());
+        for s in strings {
+            self.string(s);
+        }
+    }
+
+    fn option<T>(&mut self, value: Option<&T>, f: impl FnOnce(&mut Self, &T)) {
+        if let Some(v) = value {
+            self.byte(1);
+            f(self, v);
+        } else {
+            self.byte(0);
+        }
+    }
+
+    fn program(&mut self, program: &Program) {
+        #[cfg(feature = "import")]
+        {
+            self.len(program.imports.len());
+            for i in &program.imports {
+                self.string(&i.path.to_string_lossy());
+                self.len(i.functions.len());
+                for f in &i.functions {
+                    self.string(&f.name);
+                    self.option(f.alias.as_ref(), |e, a| e.string(a));
+                }
+            }
+        }
+        #[cfg(not(feature = "import"))]
+        self.len(0);
+
+        self.len(program.functions.len());
+        for f in &program.functions {
+            self.function(f);
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.string(&function.name);
+        self.len(function.args.len());
+        for a in &function.args {
+            self.string(&a.name);
+            self.ty(&a.arg_type);
+        }
+        self.option(function.return_type.as_ref(), Self::ty);
+        #[cfg(feature = "position")]
+        self.pos(function.pos.as_ref());
+        #[cfg(not(feature = "position"))]
+        self.pos(None);
+        self.len(function.instrs.len());
+        for c in &function.instrs {
+            self.code(c);
+        }
+    }
+
+    fn code(&mut self, code: &Code) {
+        match code {
+            Code::Label {
+                label,
+                #[cfg(feature = "position")]
+                pos,
+            } => {
+                self.byte(CODE_LABEL);
+                self.string(label);
+                #[cfg(feature = "position")]
+                self.pos(pos.as_ref());
+                #[cfg(not(feature = "position"))]
+                self.pos(None);
+            }
+            Code::Instruction(i) => self.instruction(i),
+        }
+    }
+
+    fn instruction(&mut self, instr: &Instruction) {
+        match instr {
+            Instruction::Constant {
+                dest,
+                const_type,
+                value,
+                ..
+            } => {
+                self.byte(CODE_CONSTANT);
+                self.string(dest);
+                self.ty(const_type);
+                self.literal(value);
+            }
+            Instruction::Value {
+                args,
+                dest,
+                funcs,
+                labels,
+                op,
+                op_type,
+                ..
+            } => {
+                self.byte(CODE_VALUE);
+                self.string(&op.to_string());
+                self.string(dest);
+                self.ty(op_type);
+                self.strings(args);
+                self.strings(funcs);
+                self.strings(labels);
+            }
+            Instruction::Effect {
+                args,
+                funcs,
+                labels,
+                op,
+                ..
+            } => {
+                self.byte(CODE_EFFECT);
+                self.string(&op.to_string());
+                self.strings(args);
+                self.strings(funcs);
+                self.strings(labels);
+            }
+        }
+        #[cfg(feature = "position")]
+        self.pos(instr.get_pos().as_ref());
+        #[cfg(not(feature = "position"))]
+        self.pos(None);
+    }
+
+    fn ty(&mut self, ty: &Type) {
+        match ty {
+            Type::Int => self.byte(TYPE_INT),
+            Type::Bool => self.byte(TYPE_BOOL),
+            #[cfg(feature = "float")]
+            Type::Float => self.byte(TYPE_FLOAT),
+            #[cfg(feature = "char")]
+            Type::Char => self.byte(TYPE_CHAR),
+            #[cfg(feature = "memory")]
+            Type::Pointer(t) => {
+                self.byte(TYPE_POINTER);
+                self.ty(t);
+            }
+            #[cfg(feature = "other")]
+            Type::Other(t) => {
+                self.byte(TYPE_OTHER);
+                self.abstract_ty(t);
+            }
+        }
+    }
+
+    #[cfg(feature = "other")]
+    fn abstract_ty(&mut self, ty: &AbstractType) {
+        match ty {
+            AbstractType::Primitive(name) => {
+                self.string(name);
+                self.option(None, Self::abstract_ty);
+            }
+            AbstractType::Parameterized(name, t) => {
+                self.string(name);
+                self.option(Some(t.as_ref()), Self::abstract_ty);
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Int(i) => {
+                self.byte(LITERAL_INT);
+                // Zigzag encoding keeps small negative numbers short
+                self.varint(((i << 1) ^ (i >> 63)).cast_unsigned());
+            }
+            Literal::Bool(b) => {
+                self.byte(LITERAL_BOOL);
+                self.byte(u8::from(*b));
+            }
+            #[cfg(feature = "float")]
+            Literal::Float(f) => {
+                self.byte(LITERAL_FLOAT);
+                self.body.extend_from_slice(&f.to_le_bytes());
+            }
+            #[cfg(feature = "char")]
+            Literal::Char(c) => {
+                self.byte(LITERAL_CHAR);
+                self.varint(u64::from(*c));
+            }
+        }
+    }
+
+    // Positions are only written when requested, and then every position slot has a presence byte
+    fn pos(&mut self, pos: Option<&Position>) {
+        if !self.with_pos {
+            return;
+        }
+        self.option(pos, |e, p| {
+            e.varint(p.pos.row);
+            e.varint(p.pos.col);
+            e.option(p.pos_end.as_ref(), |e, end| {
+                e.varint(end.row);
+                e.varint(end.col);
+            });
+            e.option(p.src.as_ref(), |e, src| e.string(src));
+        });
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    strings: Vec<String>,
+    with_pos: bool,
+}
+
+impl<'a> Decoder<'a> {
+    const fn take(&mut self, n: usize) -> Result<&'a [u8], BinaryError> {
+        if self.bytes.len() < n {
+            return Err(BinaryError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, BinaryError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, BinaryError> {
+        let mut n = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            // Only the lowest bit of the tenth byte still fits in a `u64`
+            if shift == 63 && b > 1 {
+                return Err(BinaryError::Malformed("varint is too large".to_string()));
+            }
+            n |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(BinaryError::Malformed("varint is too long".to_string()))
+    }
+
+    fn len(&mut self) -> Result<usize, BinaryError> {
+        usize::try_from(self.varint()?).map_err(|e| BinaryError::Malformed(e.to_string()))
+    }
+
+    fn string(&mut self) -> Result<String, BinaryError> {
+        let i = self.len()?;
+        self.strings
+            .get(i)
+            .cloned()
+            .ok_or_else(|| BinaryError::Malformed(format!("string index {i} is out of bounds")))
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, BinaryError> {
+        (0..self.len()?).map(|_| self.string()).collect()
+    }
+
+    fn option<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, BinaryError>,
+    ) -> Result<Option<T>, BinaryError> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => f(self).map(Some),
+            b => Err(BinaryError::Malformed(format!("invalid option tag {b}"))),
+        }
+    }
+
+    fn program(&mut self) -> Result<Program, BinaryError> {
+        let num_imports = self.len()?;
+        #[cfg(feature = "import")]
+        let imports = (0..num_imports)
+            .map(|_| {
+                let path = self.string()?.into();
+                let functions = (0..self.len()?)
+                    .map(|_| {
+                        let name = self.string()?;
+                        let alias = self.option(Self::string)?;
+                        Ok(ImportedFunction { alias, name })
+                    })
+                    .collect::<Result<_, BinaryError>>()?;
+                Ok(Import { functions, path })
+            })
+            .collect::<Result<_, BinaryError>>()?;
+        #[cfg(not(feature = "import"))]
+        if num_imports != 0 {
+            return Err(BinaryError::Unsupported("imports".to_string()));
+        }
+
+        let functions = (0..self.len()?)
+            .map(|_| self.function())
+            .collect::<Result<_, _>>()?;
+        Ok(Program {
+            functions,
+            #[cfg(feature = "import")]
+            imports,
+        })
+    }
+
+    fn function(&mut self) -> Result<Function, BinaryError> {
+        let name = self.string()?;
+        let args = (0..self.len()?)
+            .map(|_| {
+                Ok(Argument {
+                    name: self.string()?,
+                    arg_type: self.ty()?,
+                })
+            })
+            .collect::<Result<_, BinaryError>>()?;
+        let return_type = self.option(Self::ty)?;
+        #[cfg_attr(
+            not(feature = "position"),
+            expect(unused_variables, reason = "Positions are read but dropped")
+        )]
+        let pos = self.pos()?;
+        let instrs = (0..self.len()?)
+            .map(|_| self.code())
+            .collect::<Result<_, _>>()?;
+        Ok(Function {
+            args,
+            instrs,
+            name,
+            #[cfg(feature = "position")]
+            pos,
+            return_type,
+        })
+    }
+
+    #[cfg_attr(
+        not(feature = "position"),
+        expect(unused_variables, reason = "Positions are read but dropped")
+    )]
+    fn code(&mut self) -> Result<Code, BinaryError> {
+        let code = match self.byte()? {
+            CODE_LABEL => {
+                let label = self.string()?;
+                let pos = self.pos()?;
+                return Ok(Code::Label {
+                    label,
+                    #[cfg(feature = "position")]
+                    pos,
+                });
+            }
+            CODE_CONSTANT => {
+                let dest = self.string()?;
+                let const_type = self.ty()?;
+                let value = self.literal()?;
+                let pos = self.pos()?;
+                Instruction::Constant {
+                    dest,
+                    op: ConstOps::Const,
+                    #[cfg(feature = "position")]
+                    pos,
+                    const_type,
+                    value,
+                }
+            }
+            CODE_VALUE => {
+                let op = self.op::<ValueOps>()?;
+                let dest = self.string()?;
+                let op_type = self.ty()?;
+                let args = self.strings()?;
+                let funcs = self.strings()?;
+                let labels = self.strings()?;
+                let pos = self.pos()?;
+                Instruction::Value {
+                    args,
+                    dest,
+                    funcs,
+                    labels,
+                    op,
+                    #[cfg(feature = "position")]
+                    pos,
+                    op_type,
+                }
+            }
+            CODE_EFFECT => {
+                let op = self.op::<EffectOps>()?;
+                let args = self.strings()?;
+                let funcs = self.strings()?;
+                let labels = self.strings()?;
+                let pos = self.pos()?;
+                Instruction::Effect {
+                    args,
+                    funcs,
+                    labels,
+                    op,
+                    #[cfg(feature = "position")]
+                    pos,
+                }
+            }
+            b => return Err(BinaryError::Malformed(format!("invalid code tag {b}"))),
+        };
+        Ok(Code::Instruction(code))
+    }
+
+    fn op<T: std::str::FromStr>(&mut self) -> Result<T, BinaryError> {
+        let op = self.string()?;
+        op.parse()
+            .map_err(|_| BinaryError::Unsupported(format!("the `{op}` operation")))
+    }
+
+    fn ty(&mut self) -> Result<Type, BinaryError> {
+        self.nested_ty(0)
+    }
+
+    fn nested_ty(&mut self, depth: usize) -> Result<Type, BinaryError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(BinaryError::Malformed(
+                "type is nested too deeply".to_string(),
+            ));
+        }
+        match self.byte()? {
+            TYPE_INT => Ok(Type::Int),
+            TYPE_BOOL => Ok(Type::Bool),
+            #[cfg(feature = "float")]
+            TYPE_FLOAT => Ok(Type::Float),
+            #[cfg(feature = "char")]
+            TYPE_CHAR => Ok(Type::Char),
+            #[cfg(feature = "memory")]
+            TYPE_POINTER => Ok(Type::Pointer(Box::new(self.nested_ty(depth + 1)?))),
+            #[cfg(not(feature = "float"))]
+            TYPE_FLOAT => Err(BinaryError::Unsupported("the float type".to_string())),
+            #[cfg(not(feature = "char"))]
+            TYPE_CHAR => Err(BinaryError::Unsupported("the char type".to_string())),
+            #[cfg(not(feature = "memory"))]
+            TYPE_POINTER => Err(BinaryError::Unsupported("pointer types".to_string())),
+            TYPE_OTHER => {
+                let ty = self.abstract_ty(depth)?;
+                Type::try_from(ty.clone())
+                    .map_err(|_| BinaryError::Unsupported(format!("the {ty} type")))
+            }
+            b => Err(BinaryError::Malformed(format!("invalid type tag {b}"))),
+        }
+    }
+
+    fn abstract_ty(&mut self, depth: usize) -> Result<AbstractType, BinaryError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(BinaryError::Malformed(
+                "type is nested too deeply".to_string(),
+            ));
+        }
+        let name = self.string()?;
+        Ok(match self.option(|d| d.abstract_ty(depth + 1))? {
+            None => AbstractType::Primitive(name),
+            Some(t) => AbstractType::Parameterized(name, Box::new(t)),
+        })
+    }
+
+    fn literal(&mut self) -> Result<Literal, BinaryError> {
+        match self.byte()? {
+            LITERAL_INT => {
+                let n = self.varint()?;
+                Ok(Literal::Int(
+                    (n >> 1).cast_signed() ^ -(n & 1).cast_signed(),
+                ))
+            }
+            LITERAL_BOOL => Ok(Literal::Bool(self.byte()? != 0)),
+            #[cfg(feature = "float")]
+            LITERAL_FLOAT => {
+                let bytes = self.take(8)?.try_into().unwrap();
+                Ok(Literal::Float(f64::from_le_bytes(bytes)))
+            }
+            #[cfg(feature = "char")]
+            LITERAL_CHAR => {
+                let c = self.varint()?;
+                u32::try_from(c)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .map(Literal::Char)
+                    .ok_or_else(|| BinaryError::Malformed(format!("invalid char {c}")))
+            }
+            #[cfg(not(feature = "float"))]
+            LITERAL_FLOAT => Err(BinaryError::Unsupported("float literals".to_string())),
+            #[cfg(not(feature = "char"))]
+            LITERAL_CHAR => Err(BinaryError::Unsupported("char literals".to_string())),
+            b => Err(BinaryError::Malformed(format!("invalid literal tag {b}"))),
+        }
+    }
+
+    fn pos(&mut self) -> Result<Option<Position>, BinaryError> {
+        if !self.with_pos {
+            return Ok(None);
+        }
+        self.option(|d| {
+            let pos = ColRow {
+                row: d.varint()?,
+                col: d.varint()?,
+            };
+            let pos_end = d.option(|d| {
+                Ok(ColRow {
+                    row: d.varint()?,
+                    col: d.varint()?,
+                })
+            })?;
+            let src = d.option(Self::string)?;
+            Ok(Position { pos, pos_end, src })
+        })
+    }
+}
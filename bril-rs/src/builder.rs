@@ -0,0 +1,534 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::{
+    cfg::fresh_name, Argument, Code, ConstOps, EffectOps, Function, Instruction, Literal, Position,
+    Type, ValueOps,
+};
+
+#[cfg(not(feature = "position"))]
+#[expect(
+    non_upper_case_globals,
+    reason = "This is a nifty trick to supply a global value for pos when it is not defined"
+)]
+const pos: Option<Position> = None;
+
+/// Errors from [`FunctionBuilder::finish`] when the function refers to something it never defines
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum BuilderError {
+    /// An instruction reads a variable which is neither an argument nor assigned anywhere in the function
+    #[error("undefined variable `{0}`")]
+    UndefinedVariable(String, Option<Position>),
+    /// An instruction refers to a label which is not defined in the function
+    #[error("Could not find label: {0}")]
+    MissingLabel(String, Option<Position>),
+    /// A label is defined more than once in the function
+    #[error("duplicate label `{0}` found")]
+    DuplicateLabel(String, Option<Position>),
+}
+
+impl BuilderError {
+    /// The source position of the offending label or instruction if it is available
+    #[must_use]
+    pub const fn pos(&self) -> Option<&Position> {
+        match self {
+            Self::UndefinedVariable(_, p)
+            | Self::MissingLabel(_, p)
+            | Self::DuplicateLabel(_, p) => p.as_ref(),
+        }
+    }
+}
+
+/// Incrementally constructs a [Function], filling in the fields of each [Instruction]
+///
+/// Methods for value operations write to a fresh temporary and return its name, while the `*_to` variants write to a given variable.
+/// Fresh variables and labels never clash with a name which has already been used in the builder.
+#[derive(Debug, Clone)]
+pub struct FunctionBuilder {
+    name: String,
+    args: Vec<Argument>,
+    return_type: Option<Type>,
+    instrs: Vec<Code>,
+    #[cfg(feature = "position")]
+    pos: Option<Position>,
+    names: HashSet<String>,
+}
+
+impl FunctionBuilder {
+    /// Starts building an empty function called `name` which returns `return_type`
+    #[must_use]
+    pub fn new(name: impl Into<String>, return_type: Option<Type>) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+            return_type,
+            instrs: Vec::new(),
+            #[cfg(feature = "position")]
+            pos: None,
+            names: HashSet::new(),
+        }
+    }
+
+    /// Adds a parameter to the function, returning its name
+    pub fn arg(&mut self, name: impl Into<String>, arg_type: Type) -> String {
+        let name = name.into();
+        self.names.insert(name.clone());
+        self.args.push(Argument {
+            name: name.clone(),
+            arg_type,
+        });
+        name
+    }
+
+    /// Tags every label and instruction added from now on with `position`
+    #[cfg(feature = "position")]
+    pub fn set_pos(&mut self, position: Option<Position>) {
+        self.pos = position;
+    }
+
+    /// A variable name which has not been used in this function yet
+    pub fn fresh_var(&mut self) -> String {
+        let name = fresh_name("tmp", |n| self.names.contains(n));
+        self.names.insert(name.clone());
+        name
+    }
+
+    /// A label name which has not been used in this function yet
+    pub fn fresh_label(&mut self) -> String {
+        let name = fresh_name("label", |n| self.names.contains(n));
+        self.names.insert(name.clone());
+        name
+    }
+
+    /// Places `label` at the current end of the function
+    pub fn label(&mut self, label: &str) {
+        self.names.insert(label.to_string());
+        self.instrs.push(Code::Label {
+            label: label.to_string(),
+            #[cfg(feature = "position")]
+            pos: self.pos.clone(),
+        });
+    }
+
+    /// Appends an already constructed instruction
+    pub fn push(&mut self, instr: Instruction) {
+        self.names.extend(instr.dest().map(ToString::to_string));
+        self.names.extend(instr.args().iter().cloned());
+        self.names.extend(instr.labels().iter().cloned());
+        self.instrs.push(Code::Instruction(instr));
+    }
+
+    /// Assigns `value` to `dest`
+    pub fn constant_to(&mut self, dest: &str, const_type: Type, value: Literal) {
+        self.push(Instruction::Constant {
+            dest: dest.to_string(),
+            op: ConstOps::Const,
+            #[cfg(feature = "position")]
+            pos: self.pos.clone(),
+            const_type,
+            value,
+        });
+    }
+
+    /// Assigns `value` to a fresh variable
+    pub fn constant(&mut self, const_type: Type, value: Literal) -> String {
+        let dest = self.fresh_var();
+        self.constant_to(&dest, const_type, value);
+        dest
+    }
+
+    /// Assigns the result of any value operation to `dest`
+    pub fn value_to(
+        &mut self,
+        dest: &str,
+        op: ValueOps,
+        op_type: Type,
+        args: &[&str],
+        funcs: &[&str],
+        labels: &[&str],
+    ) {
+        self.push(Instruction::Value {
+            args: strings(args),
+            dest: dest.to_string(),
+            funcs: strings(funcs),
+            labels: strings(labels),
+            op,
+            #[cfg(feature = "position")]
+            pos: self.pos.clone(),
+            op_type,
+        });
+    }
+
+    /// Assigns the result of any value operation to a fresh variable
+    pub fn value(
+        &mut self,
+        op: ValueOps,
+        op_type: Type,
+        args: &[&str],
+        funcs: &[&str],
+        labels: &[&str],
+    ) -> String {
+        let dest = self.fresh_var();
+        self.value_to(&dest, op, op_type, args, funcs, labels);
+        dest
+    }
+
+    /// Appends any effect operation
+    pub fn effect(&mut self, op: EffectOps, args: &[&str], funcs: &[&str], labels: &[&str]) {
+        self.push(Instruction::Effect {
+            args: strings(args),
+            funcs: strings(funcs),
+            labels: strings(labels),
+            op,
+            #[cfg(feature = "position")]
+            pos: self.pos.clone(),
+        });
+    }
+
+    fn binary(&mut self, op: ValueOps, op_type: Type, lhs: &str, rhs: &str) -> String {
+        self.value(op, op_type, &[lhs, rhs], &[], &[])
+    }
+
+    /// `id`
+    pub fn id(&mut self, op_type: Type, arg: &str) -> String {
+        self.value(ValueOps::Id, op_type, &[arg], &[], &[])
+    }
+
+    /// `id` into an existing variable, which is how a variable is reassigned
+    pub fn id_to(&mut self, dest: &str, op_type: Type, arg: &str) {
+        self.value_to(dest, ValueOps::Id, op_type, &[arg], &[], &[]);
+    }
+
+    /// `add`
+    pub fn add(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Add, Type::Int, lhs, rhs)
+    }
+
+    /// `sub`
+    pub fn sub(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Sub, Type::Int, lhs, rhs)
+    }
+
+    /// `mul`
+    pub fn mul(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Mul, Type::Int, lhs, rhs)
+    }
+
+    /// `div`
+    pub fn div(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Div, Type::Int, lhs, rhs)
+    }
+
+    /// `eq`
+    pub fn eq(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Eq, Type::Bool, lhs, rhs)
+    }
+
+    /// `lt`
+    pub fn lt(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Lt, Type::Bool, lhs, rhs)
+    }
+
+    /// `gt`
+    pub fn gt(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Gt, Type::Bool, lhs, rhs)
+    }
+
+    /// `le`
+    pub fn le(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Le, Type::Bool, lhs, rhs)
+    }
+
+    /// `ge`
+    pub fn ge(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Ge, Type::Bool, lhs, rhs)
+    }
+
+    /// `not`
+    pub fn not(&mut self, arg: &str) -> String {
+        self.value(ValueOps::Not, Type::Bool, &[arg], &[], &[])
+    }
+
+    /// `and`
+    pub fn and(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::And, Type::Bool, lhs, rhs)
+    }
+
+    /// `or`
+    pub fn or(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Or, Type::Bool, lhs, rhs)
+    }
+
+    /// `call`, returning the variable holding the result if `return_type` is not [None]
+    pub fn call(&mut self, func: &str, args: &[&str], return_type: Option<Type>) -> Option<String> {
+        if let Some(t) = return_type {
+            Some(self.value(ValueOps::Call, t, args, &[func], &[]))
+        } else {
+            self.effect(EffectOps::Call, args, &[func], &[]);
+            None
+        }
+    }
+
+    /// `jmp`
+    pub fn jmp(&mut self, label: &str) {
+        self.effect(EffectOps::Jump, &[], &[], &[label]);
+    }
+
+    /// `br`
+    pub fn br(&mut self, cond: &str, then_label: &str, else_label: &str) {
+        self.effect(EffectOps::Branch, &[cond], &[], &[then_label, else_label]);
+    }
+
+    /// `ret`
+    pub fn ret(&mut self, arg: Option<&str>) {
+        self.effect(EffectOps::Return, arg.as_slice(), &[], &[]);
+    }
+
+    /// `print`
+    pub fn print(&mut self, args: &[&str]) {
+        self.effect(EffectOps::Print, args, &[], &[]);
+    }
+
+    /// `nop`
+    pub fn nop(&mut self) {
+        self.effect(EffectOps::Nop, &[], &[], &[]);
+    }
+
+    /// `fadd`
+    #[cfg(feature = "float")]
+    pub fn fadd(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Fadd, Type::Float, lhs, rhs)
+    }
+
+    /// `fsub`
+    #[cfg(feature = "float")]
+    pub fn fsub(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Fsub, Type::Float, lhs, rhs)
+    }
+
+    /// `fmul`
+    #[cfg(feature = "float")]
+    pub fn fmul(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Fmul, Type::Float, lhs, rhs)
+    }
+
+    /// `fdiv`
+    #[cfg(feature = "float")]
+    pub fn fdiv(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Fdiv, Type::Float, lhs, rhs)
+    }
+
+    /// `feq`
+    #[cfg(feature = "float")]
+    pub fn feq(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Feq, Type::Bool, lhs, rhs)
+    }
+
+    /// `flt`
+    #[cfg(feature = "float")]
+    pub fn flt(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Flt, Type::Bool, lhs, rhs)
+    }
+
+    /// `fgt`
+    #[cfg(feature = "float")]
+    pub fn fgt(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Fgt, Type::Bool, lhs, rhs)
+    }
+
+    /// `fle`
+    #[cfg(feature = "float")]
+    pub fn fle(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Fle, Type::Bool, lhs, rhs)
+    }
+
+    /// `fge`
+    #[cfg(feature = "float")]
+    pub fn fge(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Fge, Type::Bool, lhs, rhs)
+    }
+
+    /// `ceq`
+    #[cfg(feature = "char")]
+    pub fn ceq(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Ceq, Type::Bool, lhs, rhs)
+    }
+
+    /// `clt`
+    #[cfg(feature = "char")]
+    pub fn clt(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Clt, Type::Bool, lhs, rhs)
+    }
+
+    /// `cgt`
+    #[cfg(feature = "char")]
+    pub fn cgt(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Cgt, Type::Bool, lhs, rhs)
+    }
+
+    /// `cle`
+    #[cfg(feature = "char")]
+    pub fn cle(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Cle, Type::Bool, lhs, rhs)
+    }
+
+    /// `cge`
+    #[cfg(feature = "char")]
+    pub fn cge(&mut self, lhs: &str, rhs: &str) -> String {
+        self.binary(ValueOps::Cge, Type::Bool, lhs, rhs)
+    }
+
+    /// `char2int`
+    #[cfg(feature = "char")]
+    pub fn char2int(&mut self, arg: &str) -> String {
+        self.value(ValueOps::Char2int, Type::Int, &[arg], &[], &[])
+    }
+
+    /// `int2char`
+    #[cfg(feature = "char")]
+    pub fn int2char(&mut self, arg: &str) -> String {
+        self.value(ValueOps::Int2char, Type::Char, &[arg], &[], &[])
+    }
+
+    /// `alloc` of `size` elements of type `elem_type`
+    #[cfg(feature = "memory")]
+    pub fn alloc(&mut self, elem_type: Type, size: &str) -> String {
+        let ptr_type = Type::Pointer(Box::new(elem_type));
+        self.value(ValueOps::Alloc, ptr_type, &[size], &[], &[])
+    }
+
+    /// `load` from a pointer to `elem_type`
+    #[cfg(feature = "memory")]
+    pub fn load(&mut self, elem_type: Type, ptr: &str) -> String {
+        self.value(ValueOps::Load, elem_type, &[ptr], &[], &[])
+    }
+
+    /// `ptradd` on a pointer to `elem_type`
+    #[cfg(feature = "memory")]
+    pub fn ptradd(&mut self, elem_type: Type, ptr: &str, offset: &str) -> String {
+        let ptr_type = Type::Pointer(Box::new(elem_type));
+        self.binary(ValueOps::PtrAdd, ptr_type, ptr, offset)
+    }
+
+    /// `store`
+    #[cfg(feature = "memory")]
+    pub fn store(&mut self, ptr: &str, value: &str) {
+        self.effect(EffectOps::Store, &[ptr, value], &[], &[]);
+    }
+
+    /// `free`
+    #[cfg(feature = "memory")]
+    pub fn free(&mut self, ptr: &str) {
+        self.effect(EffectOps::Free, &[ptr], &[], &[]);
+    }
+
+    /// `phi` choosing between `(variable, label)` pairs by the block control came from
+    #[cfg(feature = "ssa")]
+    pub fn phi(&mut self, op_type: Type, incoming: &[(&str, &str)]) -> String {
+        let (args, labels): (Vec<&str>, Vec<&str>) = incoming.iter().copied().unzip();
+        self.value(ValueOps::Phi, op_type, &args, &[], &labels)
+    }
+
+    /// `speculate`
+    #[cfg(feature = "speculate")]
+    pub fn speculate(&mut self) {
+        self.effect(EffectOps::Speculate, &[], &[], &[]);
+    }
+
+    /// `commit`
+    #[cfg(feature = "speculate")]
+    pub fn commit(&mut self) {
+        self.effect(EffectOps::Commit, &[], &[], &[]);
+    }
+
+    /// `guard`
+    #[cfg(feature = "speculate")]
+    pub fn guard(&mut self, cond: &str, label: &str) {
+        self.effect(EffectOps::Guard, &[cond], &[], &[label]);
+    }
+
+    /// `set` of the shadow variable of `shadow` to `value`
+    #[cfg(feature = "ssa2")]
+    pub fn set(&mut self, shadow: &str, value: &str) {
+        self.effect(EffectOps::Set, &[shadow, value], &[], &[]);
+    }
+
+    /// `get` of the shadow variable of `dest` into `dest`
+    #[cfg(feature = "ssa2")]
+    pub fn get(&mut self, dest: &str, op_type: Type) {
+        self.value_to(dest, ValueOps::Get, op_type, &[], &[], &[]);
+    }
+
+    /// `undef`
+    #[cfg(feature = "ssa2")]
+    pub fn undef(&mut self, op_type: Type) -> String {
+        self.value(ValueOps::Undef, op_type, &[], &[], &[])
+    }
+
+    /// Produces the [Function] after checking that every label it refers to is defined exactly once and every variable it reads is assigned somewhere
+    ///
+    /// The arguments of `phi` are not checked, since they may be undefined along some paths.
+    /// # Errors
+    /// Will return an error if the function refers to a label or variable which it does not define
+    pub fn finish(self) -> Result<Function, BuilderError> {
+        let mut labels = HashSet::new();
+        let mut vars: HashSet<&str> = self.args.iter().map(|a| a.name.as_str()).collect();
+        for code in &self.instrs {
+            match code {
+                Code::Label {
+                    label,
+                    #[cfg(feature = "position")]
+                    pos,
+                } => {
+                    if !labels.insert(label.as_str()) {
+                        return Err(BuilderError::DuplicateLabel(label.clone(), pos.clone()));
+                    }
+                }
+                Code::Instruction(i) => vars.extend(i.dest()),
+            }
+        }
+        for code in &self.instrs {
+            let Code::Instruction(i) = code else {
+                continue;
+            };
+            #[cfg(feature = "ssa")]
+            let is_phi = matches!(
+                i,
+                Instruction::Value {
+                    op: ValueOps::Phi,
+                    ..
+                }
+            );
+            #[cfg(not(feature = "ssa"))]
+            let is_phi = false;
+            #[cfg(feature = "position")]
+            let pos = i.get_pos();
+            if !is_phi {
+                if let Some(a) = i.args().iter().find(|a| !vars.contains(a.as_str())) {
+                    return Err(BuilderError::UndefinedVariable(a.clone(), pos));
+                }
+            }
+            if let Some(l) = i.labels().iter().find(|l| !labels.contains(l.as_str())) {
+                return Err(BuilderError::MissingLabel(l.clone(), pos));
+            }
+        }
+
+        Ok(Function {
+            args: self.args,
+            instrs: self.instrs,
+            name: self.name,
+            #[cfg(feature = "position")]
+            pos: None,
+            return_type: self.return_type,
+        })
+    }
+}
+
+fn strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(ToString::to_string).collect()
+}
@@ -0,0 +1,1013 @@
+use std::{
+    collections::{hash_map::Entry, BTreeSet, HashMap},
+    io::{self, Read, Write},
+};
+
+use thiserror::Error;
+
+use crate::{
+    Argument, Code, ConstOps, EffectOps, Function, Instruction, Literal, Program, Type, ValueOps,
+};
+
+// Opcodes from fastbril/config/*.cf, which generates the headers in fastbril/src/bril-insns
+const CONST: u16 = 1;
+const ADD: u16 = 2;
+const MUL: u16 = 3;
+const SUB: u16 = 4;
+const DIV: u16 = 5;
+const EQ: u16 = 6;
+const LT: u16 = 7;
+const GT: u16 = 8;
+const LE: u16 = 9;
+const GE: u16 = 10;
+const NOT: u16 = 11;
+const AND: u16 = 12;
+const OR: u16 = 13;
+const JMP: u16 = 14;
+const BR: u16 = 15;
+const CALL: u16 = 16;
+const RET: u16 = 17;
+const PRINT: u16 = 18;
+const LCONST: u16 = 19;
+const NOP: u16 = 20;
+const ID: u16 = 21;
+const PHI: u16 = 22;
+const ALLOC: u16 = 23;
+const FREE: u16 = 24;
+const STORE: u16 = 25;
+const LOAD: u16 = 26;
+const PTRADD: u16 = 27;
+const FADD: u16 = 28;
+const FMUL: u16 = 29;
+const FSUB: u16 = 30;
+const FDIV: u16 = 31;
+const FEQ: u16 = 32;
+const FLT: u16 = 33;
+const FLE: u16 = 34;
+const FGT: u16 = 35;
+const FGE: u16 = 36;
+
+// The operations which are written with their own opcode, as named in Bril
+const OPCODES: [(&str, u16); 35] = [
+    ("add", ADD),
+    ("mul", MUL),
+    ("sub", SUB),
+    ("div", DIV),
+    ("eq", EQ),
+    ("lt", LT),
+    ("gt", GT),
+    ("le", LE),
+    ("ge", GE),
+    ("not", NOT),
+    ("and", AND),
+    ("or", OR),
+    ("jmp", JMP),
+    ("br", BR),
+    ("call", CALL),
+    ("ret", RET),
+    ("print", PRINT),
+    ("nop", NOP),
+    ("id", ID),
+    ("phi", PHI),
+    ("alloc", ALLOC),
+    ("free", FREE),
+    ("store", STORE),
+    ("load", LOAD),
+    ("ptradd", PTRADD),
+    ("fadd", FADD),
+    ("fmul", FMUL),
+    ("fsub", FSUB),
+    ("fdiv", FDIV),
+    ("feq", FEQ),
+    ("flt", FLT),
+    ("fle", FLE),
+    ("fgt", FGT),
+    ("fge", FGE),
+    ("const", CONST),
+];
+
+// The high bit of an opcode marks an instruction which directly follows a label
+const LABELLED: u16 = 0x8000;
+// Stands in for a missing destination or argument
+const NONE: u16 = 0xffff;
+
+// Types are a base type in the low two bits and the depth of pointers above it
+const TYPE_INT: u16 = 0;
+const TYPE_BOOL: u16 = 1;
+const TYPE_FLOAT: u16 = 2;
+const TYPE_VOID: u16 = 3;
+const POINTER: u16 = 1 << 2;
+
+// Every instruction is a 64 bit word, which fastbril treats as four 16 bit fields
+type Word = [u16; 4];
+
+/// Errors from converting between a [Program] and fastbril bytecode
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum BytecodeError {
+    /// There has been an io error while reading or writing the bytecode
+    #[error("There has been an io error: {0}")]
+    Io(#[from] io::Error),
+    /// The program uses something which fastbril does not implement
+    #[error("fastbril bytecode does not support {0}")]
+    Unsupported(String),
+    /// The program has more functions, instructions, or variables than fit in the 16 bit fields of an instruction
+    #[error("too many {0} for fastbril bytecode")]
+    TooLarge(String),
+    /// An instruction jumps to a label which is not in its function
+    #[error("undefined label .{0}")]
+    UndefinedLabel(String),
+    /// An instruction calls a function which is not in the program
+    #[error("undefined function @{0}")]
+    UndefinedFunction(String),
+    /// The bytecode ended in the middle of the program
+    #[error("unexpected end of fastbril bytecode")]
+    Truncated,
+    /// The program or bytecode is not well-formed
+    #[error("malformed program: {0}")]
+    Malformed(String),
+}
+
+/// Writes `program` to `output` as the bytecode read by `fastbrili -b`
+///
+/// This is the same bytecode `fastbrili -bo` writes for the JSON of `program`, as laid out on a 64-bit little-endian machine, with any fields it leaves uninitialized set to zero.
+/// Variables are numbered in the order they appear in the JSON, which is why the numbering can differ from JSON produced by other tools.
+/// # Errors
+/// Will return an error if `output` can't be written to, or if `program` uses an extension other than `float`, `memory`, and `ssa` or does not fit into the bytecode
+pub fn write_bytecode<W: Write>(program: &Program, mut output: W) -> Result<(), BytecodeError> {
+    #[cfg(feature = "import")]
+    if !program.imports.is_empty() {
+        return Err(BytecodeError::Unsupported("imports".to_string()));
+    }
+
+    // Like fastbril, a later function with the same name wins
+    let mut funcs = HashMap::new();
+    for (i, f) in program.functions.iter().enumerate() {
+        let i = u16::try_from(i).map_err(|_| BytecodeError::TooLarge("functions".to_string()))?;
+        funcs.insert(f.name.as_str(), i);
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(program.functions.len() as u64).to_le_bytes());
+    for f in &program.functions {
+        encode_function(f, &funcs, &mut bytes)?;
+    }
+    output.write_all(&bytes)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Reads bytecode written by `fastbrili -bo` or [`write_bytecode`] from `input`
+///
+/// The bytecode does not keep the names of variables and labels, so variables are named `tN` after their number and labels are named `LN` after the index of the instruction they mark, like `fastbrili -pr` prints them.
+/// Most types are not recorded either, and are inferred from how each variable is used, falling back to `int`.
+/// # Errors
+/// Will return an error if the input could not be read, is not well-formed bytecode, or uses an extension which is not enabled
+pub fn read_bytecode<R: Read>(mut input: R) -> Result<Program, BytecodeError> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    read_bytecode_from_slice(&bytes)
+}
+
+/// Like [`read_bytecode`], for input which has already been read into memory
+/// # Errors
+/// Will return an error if `bytes` is not well-formed bytecode or uses an extension which is not enabled
+pub fn read_bytecode_from_slice(mut bytes: &[u8]) -> Result<Program, BytecodeError> {
+    let num_funcs = read_len(&mut bytes)?;
+    let mut raw = Vec::new();
+    for _ in 0..num_funcs {
+        raw.push(RawFunction::read(&mut bytes)?);
+    }
+    if !bytes.is_empty() {
+        return Err(BytecodeError::Malformed(
+            "trailing bytes after program".to_string(),
+        ));
+    }
+    let functions = raw
+        .iter()
+        .map(|f| f.decode(&raw))
+        .collect::<Result<_, _>>()?;
+    Ok(Program {
+        functions,
+        #[cfg(feature = "import")]
+        imports: Vec::new(),
+    })
+}
+
+#[cfg_attr(
+    not(any(feature = "char", feature = "memory", feature = "other")),
+    expect(
+        clippy::unnecessary_wraps,
+        clippy::missing_const_for_fn,
+        reason = "Only char, pointer, and other types can fail to be encoded"
+    )
+)]
+fn encode_type(ty: Option<&Type>) -> Result<u16, BytecodeError> {
+    match ty {
+        None => Ok(TYPE_VOID),
+        Some(Type::Int) => Ok(TYPE_INT),
+        Some(Type::Bool) => Ok(TYPE_BOOL),
+        #[cfg(feature = "float")]
+        Some(Type::Float) => Ok(TYPE_FLOAT),
+        #[cfg(feature = "char")]
+        Some(Type::Char) => Err(BytecodeError::Unsupported("the char type".to_string())),
+        #[cfg(feature = "other")]
+        Some(Type::Other(t)) => Err(BytecodeError::Unsupported(format!("the {t} type"))),
+        #[cfg(feature = "memory")]
+        Some(Type::Pointer(t)) => encode_type(Some(t))?
+            .checked_add(POINTER)
+            .ok_or_else(|| BytecodeError::TooLarge("nested pointers".to_string())),
+    }
+}
+
+fn decode_type(ty: u16) -> Result<Option<Type>, BytecodeError> {
+    let base = match ty % POINTER {
+        TYPE_INT => Type::Int,
+        TYPE_BOOL => Type::Bool,
+        #[cfg(feature = "float")]
+        TYPE_FLOAT => Type::Float,
+        #[cfg(not(feature = "float"))]
+        TYPE_FLOAT => return Err(BytecodeError::Unsupported("the float type".to_string())),
+        _ if ty == TYPE_VOID => return Ok(None),
+        _ => return Err(BytecodeError::Malformed(format!("invalid type {ty}"))),
+    };
+    #[cfg(feature = "memory")]
+    let base = (0..ty / POINTER).fold(base, |t, _| Type::Pointer(Box::new(t)));
+    #[cfg(not(feature = "memory"))]
+    if ty >= POINTER {
+        return Err(BytecodeError::Unsupported("pointer types".to_string()));
+    }
+    Ok(Some(base))
+}
+
+// The low and high halves of a 32 bit field
+const fn halves(n: u32) -> [u16; 2] {
+    let b = n.to_le_bytes();
+    [
+        u16::from_le_bytes([b[0], b[1]]),
+        u16::from_le_bytes([b[2], b[3]]),
+    ]
+}
+
+// A word holding a whole `int64_t` or `double`
+const fn word_from_bits(n: u64) -> Word {
+    let b = n.to_le_bytes();
+    [
+        u16::from_le_bytes([b[0], b[1]]),
+        u16::from_le_bytes([b[2], b[3]]),
+        u16::from_le_bytes([b[4], b[5]]),
+        u16::from_le_bytes([b[6], b[7]]),
+    ]
+}
+
+fn bits_from_word(w: Word) -> u64 {
+    w.iter()
+        .rev()
+        .fold(0, |n, half| (n << 16) | u64::from(*half))
+}
+
+// The type fastbril records for a constant along with its value, as the bits of an `int64_t` or `double`
+fn constant(const_type: &Type, value: &Literal) -> Result<(u16, u64), BytecodeError> {
+    let ty = encode_type(Some(const_type))?;
+    let bits = match value {
+        // Integer literals are allowed for floats in JSON
+        #[cfg(feature = "float")]
+        Literal::Int(i) if ty == TYPE_FLOAT => {
+            #[expect(
+                clippy::cast_precision_loss,
+                reason = "This is how fastbril reads the number"
+            )]
+            let f = *i as f64;
+            f.to_bits()
+        }
+        Literal::Int(i) => i.cast_unsigned(),
+        Literal::Bool(b) => u64::from(*b),
+        #[cfg(feature = "float")]
+        Literal::Float(f) => f.to_bits(),
+        #[cfg(feature = "char")]
+        Literal::Char(_) => return Err(BytecodeError::Unsupported("the char type".to_string())),
+    };
+    Ok((ty, bits))
+}
+
+// An integer or boolean constant which fits in the 32 bit field of a `const` instruction, rather than needing an `lconst` with a second word
+fn short_constant(ty: u16, bits: u64) -> Option<i32> {
+    if ty == TYPE_INT || ty == TYPE_BOOL {
+        i32::try_from(bits.cast_signed()).ok()
+    } else {
+        None
+    }
+}
+
+// The number of words an instruction takes up after its first
+fn extra_words(instr: &Instruction) -> Result<usize, BytecodeError> {
+    let n = instr.args().len();
+    Ok(match instr {
+        Instruction::Constant {
+            const_type, value, ..
+        } => {
+            let (ty, bits) = constant(const_type, value)?;
+            usize::from(short_constant(ty, bits).is_none())
+        }
+        Instruction::Value {
+            op: ValueOps::Call, ..
+        }
+        | Instruction::Effect {
+            op: EffectOps::Call,
+            ..
+        } => n.div_ceil(4),
+        #[cfg(feature = "ssa")]
+        Instruction::Value {
+            op: ValueOps::Phi, ..
+        } => n.div_ceil(2),
+        Instruction::Effect {
+            op: EffectOps::Print,
+            ..
+        } => n / 2,
+        _ => 0,
+    })
+}
+
+fn encode_function(
+    function: &Function,
+    funcs: &HashMap<&str, u16>,
+    out: &mut Vec<u8>,
+) -> Result<(), BytecodeError> {
+    // fastbril reads the name up to the end of the line
+    if function.name.contains('\n') {
+        return Err(BytecodeError::Unsupported(
+            "function names containing newlines".to_string(),
+        ));
+    }
+    let encoder = FunctionEncoder::new(function, funcs)?;
+    let words = encoder.instructions(function)?;
+
+    out.extend_from_slice(function.name.as_bytes());
+    out.push(b'\n');
+    out.extend_from_slice(&(function.args.len() as u64).to_le_bytes());
+    for a in &function.args {
+        out.extend_from_slice(&encode_type(Some(&a.arg_type))?.to_le_bytes());
+    }
+    out.extend_from_slice(&encode_type(function.return_type.as_ref())?.to_le_bytes());
+    out.extend_from_slice(&(words.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(encoder.temp_types.len() as u64).to_le_bytes());
+    for w in words {
+        for half in w {
+            out.extend_from_slice(&half.to_le_bytes());
+        }
+    }
+    Ok(())
+}
+
+struct FunctionEncoder<'a> {
+    funcs: &'a HashMap<&'a str, u16>,
+    // The number of every variable, with arguments first and then in order of appearance
+    temps: HashMap<&'a str, u16>,
+    // The type `print` records for each variable, which is that of its last definition like in fastbril
+    temp_types: Vec<u16>,
+    // The index of the instruction after each label
+    labels: HashMap<&'a str, u16>,
+}
+
+impl<'a> FunctionEncoder<'a> {
+    fn new(
+        function: &'a Function,
+        funcs: &'a HashMap<&'a str, u16>,
+    ) -> Result<Self, BytecodeError> {
+        let mut encoder = Self {
+            funcs,
+            temps: HashMap::new(),
+            temp_types: Vec::new(),
+            labels: HashMap::new(),
+        };
+        for a in &function.args {
+            let t = encoder.new_temp()?;
+            encoder.temps.insert(&a.name, t);
+            encoder.temp_types[usize::from(t)] = encode_type(Some(&a.arg_type))?;
+        }
+
+        let mut index = 0;
+        for code in &function.instrs {
+            // fastbril stops one short of the largest index, which means a missing field
+            let index16 = u16::try_from(index)
+                .ok()
+                .filter(|i| *i < NONE - 1)
+                .ok_or_else(|| BytecodeError::TooLarge("instructions".to_string()))?;
+            match code {
+                Code::Label { label, .. } => {
+                    encoder.labels.insert(label, index16);
+                }
+                Code::Instruction(instr) => {
+                    // Arguments come before the destination in the JSON
+                    for a in instr.args() {
+                        encoder.temp(a)?;
+                    }
+                    let (dest, ty) = match instr {
+                        Instruction::Constant {
+                            dest, const_type, ..
+                        } => (Some(dest), Some(const_type)),
+                        Instruction::Value { dest, op_type, .. } => (Some(dest), Some(op_type)),
+                        Instruction::Effect { .. } => (None, None),
+                    };
+                    if let Some(dest) = dest {
+                        let t = encoder.temp(dest)?;
+                        encoder.temp_types[usize::from(t)] = encode_type(ty)?;
+                    }
+                    index += 1 + extra_words(instr)?;
+                }
+            }
+        }
+        Ok(encoder)
+    }
+
+    fn new_temp(&mut self) -> Result<u16, BytecodeError> {
+        let t = u16::try_from(self.temp_types.len())
+            .ok()
+            .filter(|t| *t < NONE - 1)
+            .ok_or_else(|| BytecodeError::TooLarge("variables".to_string()))?;
+        self.temp_types.push(TYPE_VOID);
+        Ok(t)
+    }
+
+    fn temp(&mut self, name: &'a str) -> Result<u16, BytecodeError> {
+        if let Some(t) = self.temps.get(name) {
+            return Ok(*t);
+        }
+        let t = self.new_temp()?;
+        self.temps.insert(name, t);
+        Ok(t)
+    }
+
+    fn label(&self, label: &str) -> Result<u16, BytecodeError> {
+        self.labels
+            .get(label)
+            .copied()
+            .ok_or_else(|| BytecodeError::UndefinedLabel(label.to_string()))
+    }
+
+    fn instructions(&self, function: &Function) -> Result<Vec<Word>, BytecodeError> {
+        let mut words = Vec::new();
+        let mut labelled = false;
+        for code in &function.instrs {
+            match code {
+                Code::Label { .. } => labelled = true,
+                Code::Instruction(instr) => {
+                    self.instruction(instr, labelled, &mut words)?;
+                    labelled = false;
+                }
+            }
+        }
+        Ok(words)
+    }
+
+    fn instruction(
+        &self,
+        instr: &Instruction,
+        labelled: bool,
+        words: &mut Vec<Word>,
+    ) -> Result<(), BytecodeError> {
+        let tag = |opcode: u16| if labelled { opcode | LABELLED } else { opcode };
+        let args: Vec<u16> = instr
+            .args()
+            .iter()
+            .map(|a| self.temps[a.as_str()])
+            .collect();
+        let arg = |i: usize| args.get(i).copied().unwrap_or(NONE);
+        let num_args = u16::try_from(args.len())
+            .map_err(|_| BytecodeError::TooLarge("arguments".to_string()))?;
+        let missing = || BytecodeError::Malformed(format!("`{instr}` is missing an argument"));
+
+        let (op, dest, op_type) = match instr {
+            Instruction::Constant {
+                dest,
+                const_type,
+                value,
+                ..
+            } => {
+                let dest = self.temps[dest.as_str()];
+                let (ty, bits) = constant(const_type, value)?;
+                if let Some(value) = short_constant(ty, bits) {
+                    let [lo, hi] = halves(value.cast_unsigned());
+                    words.push([tag(CONST), dest, lo, hi]);
+                } else {
+                    words.push([tag(LCONST), dest, ty, 0]);
+                    words.push(word_from_bits(bits));
+                }
+                return Ok(());
+            }
+            Instruction::Value {
+                dest, op, op_type, ..
+            } => (op.to_string(), self.temps[dest.as_str()], Some(op_type)),
+            Instruction::Effect { op, .. } => (op.to_string(), NONE, None),
+        };
+        let opcode = OPCODES
+            .iter()
+            .find(|(name, _)| *name == op)
+            .ok_or_else(|| BytecodeError::Unsupported(format!("the `{op}` operation")))?
+            .1;
+
+        match opcode {
+            JMP => {
+                let target = instr.labels().first().ok_or_else(missing)?;
+                words.push([tag(JMP), self.label(target)?, 0, 0]);
+            }
+            BR => {
+                let [t, f] = instr.labels() else {
+                    return Err(missing());
+                };
+                let test = *args.first().ok_or_else(missing)?;
+                words.push([tag(BR), test, self.label(t)?, self.label(f)?]);
+            }
+            ID => words.push([tag(ID), dest, arg(0), encode_type(op_type)?]),
+            CALL => {
+                let name = instr.funcs().first().ok_or_else(missing)?;
+                let target = *self
+                    .funcs
+                    .get(name.as_str())
+                    .ok_or_else(|| BytecodeError::UndefinedFunction(name.clone()))?;
+                words.push([tag(CALL), dest, num_args, target]);
+                spill(words, &args);
+            }
+            PHI => {
+                let labels = instr.labels();
+                if labels.len() != args.len() {
+                    return Err(BytecodeError::Malformed(format!(
+                        "`{instr}` has a different number of arguments and labels"
+                    )));
+                }
+                words.push([tag(PHI), dest, num_args, 0]);
+                let mut choices = Vec::new();
+                for (l, a) in labels.iter().zip(&args) {
+                    choices.extend([self.label(l)?, *a]);
+                }
+                spill(words, &choices);
+            }
+            PRINT => {
+                // Each argument is printed according to the type recorded beside it
+                let typed: Vec<u16> = args
+                    .iter()
+                    .flat_map(|a| [self.temp_types[usize::from(*a)], *a])
+                    .collect();
+                let first = |i: usize| typed.get(i).copied().unwrap_or(0);
+                words.push([tag(PRINT), num_args, first(0), first(1)]);
+                spill(words, typed.get(2..).unwrap_or_default());
+            }
+            _ => words.push([tag(opcode), dest, arg(0), arg(1)]),
+        }
+        Ok(())
+    }
+}
+
+// Packs the fields which don't fit in the first word of an instruction into the words after it
+fn spill(words: &mut Vec<Word>, fields: &[u16]) {
+    for chunk in fields.chunks(4) {
+        let mut w = [0; 4];
+        w[..chunk.len()].copy_from_slice(chunk);
+        words.push(w);
+    }
+}
+
+const fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], BytecodeError> {
+    if bytes.len() < n {
+        return Err(BytecodeError::Truncated);
+    }
+    let (taken, rest) = bytes.split_at(n);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn read_u16(bytes: &mut &[u8]) -> Result<u16, BytecodeError> {
+    let b = take(bytes, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+// A `size_t` count
+fn read_len(bytes: &mut &[u8]) -> Result<usize, BytecodeError> {
+    let n = u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap());
+    usize::try_from(n).map_err(|e| BytecodeError::Malformed(e.to_string()))
+}
+
+// A function as it is laid out in the bytecode, before its instructions are decoded
+struct RawFunction {
+    name: String,
+    arg_types: Vec<u16>,
+    ret_type: u16,
+    words: Vec<Word>,
+}
+
+// An instruction with its fields pulled out of the words it spans
+struct Decoded {
+    index: usize,
+    opcode: u16,
+    dest: Option<u16>,
+    args: Vec<u16>,
+    // Indices of instructions
+    labels: Vec<usize>,
+    func: Option<usize>,
+    // The types fastbril records: of an `lconst` or `id`, or of each argument of a `print`
+    types: Vec<u16>,
+    // The bits of a constant as an `int64_t` or `double`
+    value: u64,
+}
+
+impl RawFunction {
+    fn read(bytes: &mut &[u8]) -> Result<Self, BytecodeError> {
+        let end = bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or(BytecodeError::Truncated)?;
+        let name = std::str::from_utf8(take(bytes, end)?)
+            .map_err(|e| BytecodeError::Malformed(e.to_string()))?
+            .to_string();
+        take(bytes, 1)?;
+        let num_args = read_len(bytes)?;
+        let arg_types = (0..num_args)
+            .map(|_| read_u16(bytes))
+            .collect::<Result<_, _>>()?;
+        let ret_type = read_u16(bytes)?;
+        let num_insns = read_len(bytes)?;
+        // The number of variables is only used by fastbril to size its registers
+        read_len(bytes)?;
+        if bytes.len() / 8 < num_insns {
+            return Err(BytecodeError::Truncated);
+        }
+        let words = (0..num_insns)
+            .map(|_| {
+                Ok([
+                    read_u16(bytes)?,
+                    read_u16(bytes)?,
+                    read_u16(bytes)?,
+                    read_u16(bytes)?,
+                ])
+            })
+            .collect::<Result<_, BytecodeError>>()?;
+        Ok(Self {
+            name,
+            arg_types,
+            ret_type,
+            words,
+        })
+    }
+
+    // Pulls out the instruction starting at `index` and returns the index of the next one
+    fn decode_instruction(
+        &self,
+        index: usize,
+        funcs: &[Self],
+    ) -> Result<(Decoded, usize), BytecodeError> {
+        let word = self.words[index];
+        let opcode = word[0] & !LABELLED;
+        let dest = (word[1] != NONE).then_some(word[1]);
+        let mut decoded = Decoded {
+            index,
+            opcode,
+            dest,
+            args: Vec::new(),
+            labels: Vec::new(),
+            func: None,
+            types: Vec::new(),
+            value: 0,
+        };
+        // The words following this one, which some instructions spill into
+        let extra = |n: usize| {
+            self.words
+                .get(index + 1..index + 1 + n)
+                .ok_or(BytecodeError::Truncated)
+        };
+        let mut next = index + 1;
+        match opcode {
+            CONST => {
+                let value = (u32::from(word[3]) << 16 | u32::from(word[2])).cast_signed();
+                decoded.value = i64::from(value).cast_unsigned();
+            }
+            LCONST => {
+                decoded.types.push(word[2]);
+                decoded.value = bits_from_word(extra(1)?[0]);
+                next += 1;
+            }
+            JMP => {
+                decoded.dest = None;
+                decoded.labels.push(usize::from(word[1]));
+            }
+            BR => {
+                decoded.dest = None;
+                decoded.args.push(word[1]);
+                decoded.labels = vec![usize::from(word[2]), usize::from(word[3])];
+            }
+            CALL => {
+                let num_args = usize::from(word[2]);
+                let target = usize::from(word[3]);
+                if target >= funcs.len() {
+                    return Err(BytecodeError::UndefinedFunction(format!("number {target}")));
+                }
+                decoded.func = Some(target);
+                let n = num_args.div_ceil(4);
+                decoded.args = extra(n)?.iter().flatten().take(num_args).copied().collect();
+                next += n;
+            }
+            PHI => {
+                let num_choices = usize::from(word[2]);
+                let n = num_choices.div_ceil(2);
+                for [l, a] in extra(n)?
+                    .iter()
+                    .flat_map(|w| [[w[0], w[1]], [w[2], w[3]]])
+                    .take(num_choices)
+                {
+                    decoded.labels.push(usize::from(l));
+                    decoded.args.push(a);
+                }
+                next += n;
+            }
+            PRINT => {
+                decoded.dest = None;
+                let num_prints = usize::from(word[1]);
+                let n = num_prints / 2;
+                let rest = extra(n)?.iter().flat_map(|w| [[w[0], w[1]], [w[2], w[3]]]);
+                for [ty, a] in std::iter::once([word[2], word[3]])
+                    .chain(rest)
+                    .take(num_prints)
+                {
+                    decoded.types.push(ty);
+                    decoded.args.push(a);
+                }
+                next += n;
+            }
+            ID => {
+                decoded.args.push(word[2]);
+                decoded.types.push(word[3]);
+            }
+            RET | FREE | NOT | ALLOC | LOAD => {
+                decoded.args.extend((word[2] != NONE).then_some(word[2]));
+            }
+            NOP => {}
+            _ if OPCODES.iter().any(|(_, o)| *o == opcode) => {
+                decoded.args = vec![word[2], word[3]];
+            }
+            _ => {
+                return Err(BytecodeError::Malformed(format!(
+                    "invalid opcode {opcode} in @{}",
+                    self.name
+                )))
+            }
+        }
+        if matches!(opcode, RET | FREE | STORE | NOP) {
+            decoded.dest = None;
+        }
+        Ok((decoded, next))
+    }
+
+    fn decode(&self, funcs: &[Self]) -> Result<Function, BytecodeError> {
+        let mut instrs = Vec::new();
+        let mut index = 0;
+        while index < self.words.len() {
+            let (decoded, next) = self.decode_instruction(index, funcs)?;
+            instrs.push(decoded);
+            index = next;
+        }
+
+        // Labels mark the targets of jumps, and wherever fastbril recorded that there was one
+        let mut labels: BTreeSet<usize> = instrs
+            .iter()
+            .filter(|d| self.words[d.index][0] & LABELLED != 0)
+            .map(|d| d.index)
+            .collect();
+        for d in &instrs {
+            for l in &d.labels {
+                if *l != self.words.len() && instrs.binary_search_by_key(l, |d| d.index).is_err() {
+                    return Err(BytecodeError::Malformed(format!(
+                        "jump into the middle of an instruction in @{}",
+                        self.name
+                    )));
+                }
+                labels.insert(*l);
+            }
+        }
+
+        let types = self.infer_types(&instrs, funcs);
+        let name = |t: u16| format!("t{t}");
+        // A type recorded with the instruction wins, since a variable can be redefined with a different type
+        let ty = |d: &Decoded, t: u16| -> Result<Type, BytecodeError> {
+            let recorded = matches!(d.opcode, LCONST | ID)
+                .then(|| d.types.first().copied())
+                .flatten();
+            decode_type(
+                recorded
+                    .or_else(|| types.get(&t).copied())
+                    .unwrap_or(TYPE_INT),
+            )?
+            .ok_or_else(|| BytecodeError::Malformed(format!("t{t} has no type")))
+        };
+
+        let mut code = Vec::new();
+        for d in instrs {
+            if labels.remove(&d.index) {
+                code.push(label_code(d.index));
+            }
+            let args: Vec<String> = d.args.iter().map(|a| name(*a)).collect();
+            let targets: Vec<String> = d.labels.iter().map(|l| format!("L{l}")).collect();
+            let funcs: Vec<String> = d.func.map(|f| funcs[f].name.clone()).into_iter().collect();
+            let instr = match (d.opcode, d.dest) {
+                (CONST | LCONST, Some(dest)) => {
+                    let const_type = ty(&d, dest)?;
+                    let value = literal(encode_type(Some(&const_type))?, d.value)?;
+                    Instruction::Constant {
+                        dest: name(dest),
+                        op: ConstOps::Const,
+                        #[cfg(feature = "position")]
+                        pos: None,
+                        const_type,
+                        value,
+                    }
+                }
+                (opcode, Some(dest)) => Instruction::Value {
+                    args,
+                    dest: name(dest),
+                    funcs,
+                    labels: targets,
+                    op: op_from_opcode(opcode)?,
+                    #[cfg(feature = "position")]
+                    pos: None,
+                    op_type: ty(&d, dest)?,
+                },
+                (opcode, None) => Instruction::Effect {
+                    args,
+                    funcs,
+                    labels: targets,
+                    op: op_from_opcode(opcode)?,
+                    #[cfg(feature = "position")]
+                    pos: None,
+                },
+            };
+            code.push(Code::Instruction(instr));
+        }
+        // Any label left marks the end of the function
+        code.extend(labels.into_iter().map(label_code));
+
+        let args = self
+            .arg_types
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                Ok(Argument {
+                    name: format!("t{i}"),
+                    arg_type: decode_type(*t)?.ok_or_else(|| {
+                        BytecodeError::Malformed(format!("argument {i} of @{} is void", self.name))
+                    })?,
+                })
+            })
+            .collect::<Result<_, BytecodeError>>()?;
+        Ok(Function {
+            args,
+            instrs: code,
+            name: self.name.clone(),
+            #[cfg(feature = "position")]
+            pos: None,
+            return_type: decode_type(self.ret_type)?,
+        })
+    }
+
+    // The type of each variable in the encoding of types, from what is recorded in the bytecode and how the variable is used
+    fn infer_types(&self, instrs: &[Decoded], funcs: &[Self]) -> HashMap<u16, u16> {
+        let mut types = HashMap::new();
+        for (i, t) in self.arg_types.iter().enumerate() {
+            if let Ok(i) = u16::try_from(i) {
+                types.insert(i, *t);
+            }
+        }
+        loop {
+            while infer_step(&mut types, instrs, funcs, self.ret_type) {}
+            // Constants and allocations are the only definitions which can have unconstrained types
+            let Some((dest, default)) = instrs.iter().find_map(|d| {
+                let dest = d.dest.filter(|t| !types.contains_key(t))?;
+                match d.opcode {
+                    CONST => Some((dest, TYPE_INT)),
+                    ALLOC => Some((dest, TYPE_INT + POINTER)),
+                    _ => None,
+                }
+            }) else {
+                return types;
+            };
+            types.insert(dest, default);
+        }
+    }
+}
+
+// Propagates types once through every instruction, and returns whether any were found
+fn infer_step(
+    types: &mut HashMap<u16, u16>,
+    instrs: &[Decoded],
+    funcs: &[RawFunction],
+    ret_type: u16,
+) -> bool {
+    let mut changed = false;
+    let mut set = |types: &mut HashMap<u16, u16>, t: u16, ty: Option<u16>| {
+        if let Some(ty) = ty.filter(|ty| *ty != TYPE_VOID) {
+            if let Entry::Vacant(e) = types.entry(t) {
+                e.insert(ty);
+                changed = true;
+            }
+        }
+    };
+    for d in instrs {
+        let (arg_type, dest_type) = match d.opcode {
+            ADD | MUL | SUB | DIV => (Some(TYPE_INT), Some(TYPE_INT)),
+            EQ | LT | GT | LE | GE => (Some(TYPE_INT), Some(TYPE_BOOL)),
+            NOT | AND | OR | BR => (Some(TYPE_BOOL), Some(TYPE_BOOL)),
+            FADD | FMUL | FSUB | FDIV => (Some(TYPE_FLOAT), Some(TYPE_FLOAT)),
+            FEQ | FLT | FLE | FGT | FGE => (Some(TYPE_FLOAT), Some(TYPE_BOOL)),
+            ALLOC => (Some(TYPE_INT), None),
+            RET => (Some(ret_type), None),
+            LCONST | ID => (d.types.first().copied(), d.types.first().copied()),
+            _ => (None, None),
+        };
+        for a in &d.args {
+            set(types, *a, arg_type);
+        }
+        if let Some(dest) = d.dest {
+            set(types, dest, dest_type);
+        }
+
+        let known =
+            |types: &HashMap<u16, u16>, t: Option<&u16>| t.and_then(|t| types.get(t).copied());
+        match d.opcode {
+            PRINT => {
+                for (a, ty) in d.args.iter().zip(&d.types) {
+                    set(types, *a, Some(*ty));
+                }
+            }
+            CALL => {
+                if let Some(target) = d.func.map(|f| &funcs[f]) {
+                    for (a, ty) in d.args.iter().zip(&target.arg_types) {
+                        set(types, *a, Some(*ty));
+                    }
+                    if let Some(dest) = d.dest {
+                        set(types, dest, Some(target.ret_type));
+                    }
+                }
+            }
+            // Every argument and the destination have the same type
+            PHI => {
+                let all = d.args.iter().chain(&d.dest);
+                if let Some(ty) = all.clone().find_map(|t| types.get(t).copied()) {
+                    for t in all {
+                        set(types, *t, Some(ty));
+                    }
+                }
+            }
+            PTRADD => {
+                if let Some(dest) = d.dest {
+                    set(types, dest, known(types, d.args.first()));
+                    set(types, d.args[0], types.get(&dest).copied());
+                }
+                set(types, d.args[1], Some(TYPE_INT));
+            }
+            LOAD => {
+                if let (Some(dest), Some(ptr)) = (d.dest, d.args.first()) {
+                    let loaded = known(types, Some(ptr)).and_then(|t| t.checked_sub(POINTER));
+                    set(types, dest, loaded);
+                    let ptr_type = types.get(&dest).and_then(|t| t.checked_add(POINTER));
+                    set(types, *ptr, ptr_type);
+                }
+            }
+            STORE => {
+                let stored = known(types, d.args.first()).and_then(|t| t.checked_sub(POINTER));
+                set(types, d.args[1], stored);
+                let ptr_type = known(types, d.args.get(1)).and_then(|t| t.checked_add(POINTER));
+                set(types, d.args[0], ptr_type);
+            }
+            _ => {}
+        }
+    }
+    changed
+}
+
+fn label_code(index: usize) -> Code {
+    Code::Label {
+        label: format!("L{index}"),
+        #[cfg(feature = "position")]
+        pos: None,
+    }
+}
+
+fn literal(ty: u16, bits: u64) -> Result<Literal, BytecodeError> {
+    match ty {
+        TYPE_INT => Ok(Literal::Int(bits.cast_signed())),
+        TYPE_BOOL => Ok(Literal::Bool(bits != 0)),
+        #[cfg(feature = "float")]
+        TYPE_FLOAT => Ok(Literal::Float(f64::from_bits(bits))),
+        _ => Err(BytecodeError::Malformed(format!("constant of type {ty}"))),
+    }
+}
+
+fn op_from_opcode<T: std::str::FromStr>(opcode: u16) -> Result<T, BytecodeError> {
+    let (name, _) = OPCODES
+        .iter()
+        .find(|(_, o)| *o == opcode)
+        .ok_or_else(|| BytecodeError::Malformed(format!("invalid opcode {opcode}")))?;
+    name.parse()
+        .map_err(|_| BytecodeError::Unsupported(format!("the `{name}` operation")))
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use crate::{AbstractCode, AbstractInstruction, AbstractProgram, Code, Program};
+
+/// The call graph of a program, with an edge from each function to every function that it calls with `call`
+///
+/// Functions are referred to by their index in the `functions` of the program that the graph was built from.
+/// Calls to functions which are not defined in the program, like those which have not been linked in yet, are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraph {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    callees: Vec<Vec<usize>>,
+    callers: Vec<Vec<usize>>,
+    // The strongly connected components in reverse topological order, along with the component of each function
+    sccs: Vec<Vec<usize>>,
+    scc_of: Vec<usize>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of `program`
+    #[must_use]
+    pub fn new(program: &Program) -> Self {
+        Self::from_calls(program.functions.iter().map(|f| {
+            let calls = f.instrs.iter().flat_map(|code| match code {
+                Code::Label { .. } => [].as_slice(),
+                Code::Instruction(i) => i.funcs(),
+            });
+            (f.name.as_str(), calls.map(String::as_str).collect())
+        }))
+    }
+
+    /// Builds the call graph of `program`, which does not need to be fully typed, such as the output of `brild`
+    #[must_use]
+    pub fn from_abstract(program: &AbstractProgram) -> Self {
+        Self::from_calls(program.functions.iter().map(|f| {
+            let calls = f.instrs.iter().flat_map(|code| match code {
+                AbstractCode::Label { .. }
+                | AbstractCode::Instruction(AbstractInstruction::Constant { .. }) => [].as_slice(),
+                AbstractCode::Instruction(
+                    AbstractInstruction::Value { funcs, .. }
+                    | AbstractInstruction::Effect { funcs, .. },
+                ) => funcs.as_slice(),
+            });
+            (f.name.as_str(), calls.map(String::as_str).collect())
+        }))
+    }
+
+    fn from_calls<'a>(functions: impl Iterator<Item = (&'a str, Vec<&'a str>)>) -> Self {
+        let (names, calls): (Vec<String>, Vec<Vec<&str>>) = functions
+            .map(|(name, calls)| (name.to_string(), calls))
+            .unzip();
+        let mut index = HashMap::new();
+        for (i, name) in names.iter().enumerate() {
+            index.entry(name.clone()).or_insert(i);
+        }
+
+        let mut edges = vec![Vec::new(); names.len()];
+        let mut reverse_edges = vec![Vec::new(); names.len()];
+        for (caller, calls) in calls.into_iter().enumerate() {
+            for callee in calls.into_iter().filter_map(|c| index.get(c).copied()) {
+                if !edges[caller].contains(&callee) {
+                    edges[caller].push(callee);
+                    reverse_edges[callee].push(caller);
+                }
+            }
+        }
+
+        let sccs = tarjan(&edges);
+        let mut scc_of = vec![0; names.len()];
+        for (i, scc) in sccs.iter().enumerate() {
+            for f in scc {
+                scc_of[*f] = i;
+            }
+        }
+
+        Self {
+            names,
+            index,
+            callees: edges,
+            callers: reverse_edges,
+            sccs,
+            scc_of,
+        }
+    }
+
+    /// The number of functions in the graph
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether the program has no functions
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The name of `function`
+    #[must_use]
+    pub fn name(&self, function: usize) -> &str {
+        &self.names[function]
+    }
+
+    /// The index of the function called `name`, which is the first one if there are several
+    #[must_use]
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    /// The functions which `function` calls, in the order that they are first called
+    #[must_use]
+    pub fn callees(&self, function: usize) -> &[usize] {
+        &self.callees[function]
+    }
+
+    /// The functions which call `function`
+    #[must_use]
+    pub fn callers(&self, function: usize) -> &[usize] {
+        &self.callers[function]
+    }
+
+    /// The strongly connected components of the graph in reverse topological order, so that each function comes after every function it calls unless they are in the same component
+    ///
+    /// This is the order to visit functions in for bottom-up passes like inlining.
+    #[must_use]
+    pub fn sccs(&self) -> &[Vec<usize>] {
+        &self.sccs
+    }
+
+    /// Whether `function` can call itself, either directly or through other functions
+    #[must_use]
+    pub fn is_recursive(&self, function: usize) -> bool {
+        self.sccs[self.scc_of[function]].len() > 1 || self.callees[function].contains(&function)
+    }
+
+    /// Whether `a` and `b` can both call each other, directly or through other functions. Every function is mutually recursive with itself
+    #[must_use]
+    pub fn same_scc(&self, a: usize, b: usize) -> bool {
+        self.scc_of[a] == self.scc_of[b]
+    }
+
+    /// Which functions can be reached by calls starting from `roots`, including the roots themselves, indexed by function
+    #[must_use]
+    pub fn reachable_from(&self, roots: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut reachable = vec![false; self.len()];
+        let mut worklist: Vec<usize> = roots.into_iter().collect();
+        while let Some(f) = worklist.pop() {
+            if !reachable[f] {
+                reachable[f] = true;
+                worklist.extend(&self.callees[f]);
+            }
+        }
+        reachable
+    }
+
+    /// Which functions can be reached by calls starting from `main`, indexed by function
+    ///
+    /// Every function is reachable when there is no `main`, as is the case for libraries.
+    #[must_use]
+    pub fn reachable_from_main(&self) -> Vec<bool> {
+        self.index("main").map_or_else(
+            || vec![true; self.len()],
+            |main| self.reachable_from([main]),
+        )
+    }
+
+    /// The graph in the DOT format of `GraphViz`, like `examples/cfg_dot.py`, with recursive functions drawn with a double border
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let nodes = self.names.iter().enumerate().map(|(f, name)| {
+            if self.is_recursive(f) {
+                format!("  {name:?} [peripheries=2];\n")
+            } else {
+                format!("  {name:?};\n")
+            }
+        });
+        let edges = self.callees.iter().enumerate().flat_map(|(f, callees)| {
+            callees
+                .iter()
+                .map(move |c| format!("  {:?} -> {:?};\n", self.names[f], self.names[*c]))
+        });
+        format!(
+            "digraph calls {{\n{}}}\n",
+            nodes.chain(edges).collect::<String>()
+        )
+    }
+}
+
+// Tarjan's algorithm with an explicit stack of (function, index of the next callee to visit), which finds components in reverse topological order
+fn tarjan(callees: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = callees.len();
+    let mut order = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut sccs = Vec::new();
+    let mut counter = 0;
+    for root in 0..n {
+        if order[root] != usize::MAX {
+            continue;
+        }
+        let mut calls = vec![(root, 0)];
+        order[root] = counter;
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some(&mut (f, ref mut next)) = calls.last_mut() {
+            if let Some(&c) = callees[f].get(*next) {
+                *next += 1;
+                if order[c] == usize::MAX {
+                    order[c] = counter;
+                    low[c] = counter;
+                    counter += 1;
+                    stack.push(c);
+                    on_stack[c] = true;
+                    calls.push((c, 0));
+                } else if on_stack[c] {
+                    low[f] = low[f].min(order[c]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(caller, _)) = calls.last() {
+                low[caller] = low[caller].min(low[f]);
+            }
+            if low[f] == order[f] {
+                let mut scc = Vec::new();
+                while let Some(g) = stack.pop() {
+                    on_stack[g] = false;
+                    scc.push(g);
+                    if g == f {
+                        break;
+                    }
+                }
+                scc.sort_unstable();
+                sccs.push(scc);
+            }
+        }
+    }
+    sccs
+}
+
+impl Program {
+    /// Removes every function which can't be reached by calls from `main`, which keeps every function if there is no `main`
+    pub fn remove_unreachable_functions(&mut self) {
+        let reachable = CallGraph::new(self).reachable_from_main();
+        let mut keep = reachable.into_iter();
+        self.functions.retain(|_| keep.next().unwrap_or(true));
+    }
+}
+
+impl AbstractProgram {
+    /// Removes every function which can't be reached by calls from `main`, like [`Program::remove_unreachable_functions`]
+    pub fn remove_unreachable_functions(&mut self) {
+        let reachable = CallGraph::from_abstract(self).reachable_from_main();
+        let mut keep = reachable.into_iter();
+        self.functions.retain(|_| keep.next().unwrap_or(true));
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::HashMap, fmt::Display};
+
+use thiserror::Error;
+
+#[cfg(feature = "position")]
+use crate::Instruction;
+use crate::{
+    cfg::{Cfg, CfgError},
+    Code, Function, Position, Program,
+};
+
+/// Renames the arguments, variables, and labels of `function` to `v0`, `v1`, ... and `b0`, `b1`, ... in the order that they first appear, so that functions which only differ in their names become equal
+///
+/// Blocks are visited in reverse post-order from the entry, followed by any unreachable blocks in program order, so the names don't depend on how the blocks are laid out.
+/// The instructions themselves and the name of the function are left alone, so each instruction stays at the same index in `function.instrs`.
+/// # Errors
+/// Returns an error if the labels of `function` don't resolve, in which case `function` is unchanged
+pub fn canonicalize(function: &mut Function) -> Result<(), CfgError> {
+    let mut cfg = Cfg::try_from(function.clone())?;
+    canonicalize_cfg(&mut cfg);
+    *function = cfg.into();
+    Ok(())
+}
+
+/// Renames the arguments, variables, and blocks of `cfg` like [`canonicalize`]
+pub fn canonicalize_cfg(cfg: &mut Cfg) {
+    let mut order = cfg.reverse_post_order();
+    let mut reachable = vec![false; cfg.blocks.len()];
+    for b in &order {
+        reachable[*b] = true;
+    }
+    order.extend((0..cfg.blocks.len()).filter(|b| !reachable[*b]));
+
+    let blocks: HashMap<String, String> = order
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (cfg.blocks[*b].name.clone(), format!("b{i}")))
+        .collect();
+    for block in &mut cfg.blocks {
+        block.name.clone_from(&blocks[&block.name]);
+    }
+
+    let mut vars = Renaming::default();
+    for a in &mut cfg.args {
+        vars.rename(&mut a.name);
+    }
+    for b in order {
+        for instr in &mut cfg.blocks[b].instrs {
+            for a in instr.args_mut() {
+                vars.rename(a);
+            }
+            if let Some(dest) = instr.dest_mut() {
+                vars.rename(dest);
+            }
+            // The labels of `phi` are not checked when building a `Cfg`, so they might not be blocks
+            for l in instr.labels_mut() {
+                if let Some(name) = blocks.get(l.as_str()) {
+                    l.clone_from(name);
+                }
+            }
+        }
+    }
+}
+
+// Gives each distinct variable the next name in order
+#[derive(Default)]
+struct Renaming(HashMap<String, String>);
+
+impl Renaming {
+    fn rename(&mut self, var: &mut String) {
+        let next = self.0.len();
+        let name = self
+            .0
+            .entry(std::mem::take(var))
+            .or_insert_with(|| format!("v{next}"));
+        var.clone_from(name);
+    }
+}
+
+/// The first difference between two programs found by [`alpha_eq`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// A description of the difference, which quotes the original code of both programs
+    pub message: String,
+    /// Where the difference is in the first program, if that is known
+    pub left: Option<Position>,
+    /// Where the difference is in the second program, if that is known
+    pub right: Option<Position>,
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        match (&self.left, &self.right) {
+            (Some(l), Some(r)) => write!(
+                f,
+                " (line {}, column {} of the first program and line {}, column {} of the second)",
+                l.pos.row, l.pos.col, r.pos.row, r.pos.col
+            ),
+            (Some(p), None) => write!(
+                f,
+                " (line {}, column {} of the first program)",
+                p.pos.row, p.pos.col
+            ),
+            (None, Some(p)) => write!(
+                f,
+                " (line {}, column {} of the second program)",
+                p.pos.row, p.pos.col
+            ),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl Difference {
+    // Boxed since positions make the difference too large to return by value
+    fn new(message: String, left: Option<Position>, right: Option<Position>) -> Box<Self> {
+        Box::new(Self {
+            message,
+            left,
+            right,
+        })
+    }
+}
+
+/// Checks whether two programs are the same up to the names of variables and labels, by comparing each pair of functions with the same name after [`canonicalize`]
+///
+/// Source positions and the order of the functions are ignored.
+/// # Errors
+/// Returns the first difference which was found, with its position in each program when that is available
+pub fn alpha_eq(left: &Program, right: &Program) -> Result<(), Box<Difference>> {
+    #[cfg(feature = "import")]
+    if left.imports != right.imports {
+        return Err(Difference::new(
+            "The programs have different imports".to_string(),
+            None,
+            None,
+        ));
+    }
+    for l in &left.functions {
+        let Some(r) = right.functions.iter().find(|r| r.name == l.name) else {
+            return Err(Difference::new(
+                format!("@{} is only defined in the first program", l.name),
+                function_pos(l),
+                None,
+            ));
+        };
+        function_eq(l, r)?;
+    }
+    if let Some(r) = right
+        .functions
+        .iter()
+        .find(|r| left.functions.iter().all(|l| l.name != r.name))
+    {
+        return Err(Difference::new(
+            format!("@{} is only defined in the second program", r.name),
+            None,
+            function_pos(r),
+        ));
+    }
+    Ok(())
+}
+
+fn function_eq(left: &Function, right: &Function) -> Result<(), Box<Difference>> {
+    let name = &left.name;
+    let signature = |f: &Function| {
+        let args: Vec<String> = f.args.iter().map(|a| a.arg_type.to_string()).collect();
+        let ret = f
+            .return_type
+            .as_ref()
+            .map_or_else(String::new, |t| format!(": {t}"));
+        format!("({}){ret}", args.join(", "))
+    };
+    if left.args.len() != right.args.len()
+        || left
+            .args
+            .iter()
+            .zip(&right.args)
+            .any(|(l, r)| l.arg_type != r.arg_type)
+        || left.return_type != right.return_type
+    {
+        return Err(Difference::new(
+            format!(
+                "@{name} has the signature {} in the first program and {} in the second",
+                signature(left),
+                signature(right)
+            ),
+            function_pos(left),
+            function_pos(right),
+        ));
+    }
+
+    let (mut canonical_left, mut canonical_right) = (left.clone(), right.clone());
+    canonicalize(&mut canonical_left).map_err(|e| {
+        Difference::new(
+            format!("@{name} in the first program: {e}"),
+            e.pos().cloned(),
+            None,
+        )
+    })?;
+    canonicalize(&mut canonical_right).map_err(|e| {
+        Difference::new(
+            format!("@{name} in the second program: {e}"),
+            None,
+            e.pos().cloned(),
+        )
+    })?;
+
+    let quote = |code: &Code| code.to_string().trim().to_string();
+    for (i, (l, r)) in canonical_left
+        .instrs
+        .iter()
+        .zip(&canonical_right.instrs)
+        .enumerate()
+    {
+        if without_pos(l) != without_pos(r) {
+            return Err(Difference::new(
+                format!(
+                    "@{name} has `{}` in the first program where the second has `{}`",
+                    quote(&left.instrs[i]),
+                    quote(&right.instrs[i])
+                ),
+                code_pos(l),
+                code_pos(r),
+            ));
+        }
+    }
+    let common = left.instrs.len().min(right.instrs.len());
+    if let Some(l) = left.instrs.get(common) {
+        return Err(Difference::new(
+            format!(
+                "@{name} continues with `{}` in the first program where the second ends",
+                quote(l)
+            ),
+            code_pos(l),
+            None,
+        ));
+    }
+    if let Some(r) = right.instrs.get(common) {
+        return Err(Difference::new(
+            format!(
+                "@{name} continues with `{}` in the second program where the first ends",
+                quote(r)
+            ),
+            None,
+            code_pos(r),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "position")]
+fn without_pos(code: &Code) -> Code {
+    let mut code = code.clone();
+    match &mut code {
+        Code::Label { pos, .. }
+        | Code::Instruction(
+            Instruction::Constant { pos, .. }
+            | Instruction::Value { pos, .. }
+            | Instruction::Effect { pos, .. },
+        ) => *pos = None,
+    }
+    code
+}
+
+#[cfg(not(feature = "position"))]
+fn without_pos(code: &Code) -> Code {
+    code.clone()
+}
+
+#[cfg(feature = "position")]
+fn function_pos(function: &Function) -> Option<Position> {
+    function.pos.clone()
+}
+
+#[cfg(not(feature = "position"))]
+const fn function_pos(_: &Function) -> Option<Position> {
+    None
+}
+
+#[cfg(feature = "position")]
+fn code_pos(code: &Code) -> Option<Position> {
+    match code {
+        Code::Label { pos, .. } => pos.clone(),
+        Code::Instruction(i) => i.get_pos(),
+    }
+}
+
+#[cfg(not(feature = "position"))]
+const fn code_pos(_: &Code) -> Option<Position> {
+    None
+}
@@ -0,0 +1,410 @@
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::{Argument, Code, EffectOps, Function, Instruction, Position, Type};
+
+#[cfg(not(feature = "position"))]
+#[expect(
+    non_upper_case_globals,
+    reason = "This is a nifty trick to supply a global value for pos when it is not defined"
+)]
+const pos: Option<Position> = None;
+
+/// Errors from building a [`Cfg`] out of a [`Function`]
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum CfgError {
+    /// An instruction refers to a label which is not defined in the function
+    #[error("Could not find label: {0}")]
+    MissingLabel(String, Option<Position>),
+    /// A label is defined more than once in the function
+    #[error("duplicate label `{0}` found")]
+    DuplicateLabel(String, Option<Position>),
+}
+
+impl CfgError {
+    /// The source position of the offending label or instruction if it is available
+    #[must_use]
+    pub const fn pos(&self) -> Option<&Position> {
+        match self {
+            Self::MissingLabel(_, p) | Self::DuplicateLabel(_, p) => p.as_ref(),
+        }
+    }
+}
+
+/// A maximal sequence of instructions which is only entered at the top and only exited at the bottom
+#[cfg_attr(not(feature = "float"), derive(Eq))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    /// The name of the block. This is its label if it has one, otherwise a fresh name which does not clash with any label in the function
+    pub name: String,
+    /// Whether `name` is a label in the function. Only labeled blocks emit a [`Code::Label`] when converted back into a [`Function`]
+    pub labeled: bool,
+    /// Where the label of this block is located in source code
+    #[cfg(feature = "position")]
+    pub label_pos: Option<Position>,
+    /// The instructions of this block, ending with the terminator if there is one
+    pub instrs: Vec<Instruction>,
+    /// Indices of the blocks which can transfer control to this block
+    pub predecessors: Vec<usize>,
+    /// Indices of the blocks which this block can transfer control to
+    pub successors: Vec<usize>,
+}
+
+impl BasicBlock {
+    /// Creates an empty block with no edges. Call [`Cfg::recompute_edges`] after adding it to a [`Cfg`]
+    #[must_use]
+    pub const fn new(name: String, labeled: bool) -> Self {
+        Self {
+            name,
+            labeled,
+            #[cfg(feature = "position")]
+            label_pos: None,
+            instrs: Vec::new(),
+            predecessors: Vec::new(),
+            successors: Vec::new(),
+        }
+    }
+
+    /// The last instruction of the block if it is a `jmp`, `br`, or `ret`
+    #[must_use]
+    pub fn terminator(&self) -> Option<&Instruction> {
+        self.instrs.last().filter(|i| is_terminator(i))
+    }
+}
+
+/// A [`Function`] represented as a control-flow graph of [`BasicBlock`]s
+///
+/// The block at index 0 is the entry block and has no predecessors. If the first block of the function can be jumped to, an empty unlabeled entry block is inserted before it.
+/// Blocks are kept in the order that they appear in the function so that fall-through is preserved when converting back into a [`Function`].
+#[cfg_attr(not(feature = "float"), derive(Eq))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cfg {
+    /// Any arguments the function accepts
+    pub args: Vec<Argument>,
+    /// The basic blocks of the function in program order
+    pub blocks: Vec<BasicBlock>,
+    /// The name of the function
+    pub name: String,
+    /// The position of this function in the original source code
+    #[cfg(feature = "position")]
+    pub pos: Option<Position>,
+    /// The possible return type of this function
+    pub return_type: Option<Type>,
+}
+
+/// Whether this instruction ends a basic block
+#[must_use]
+pub const fn is_terminator(i: &Instruction) -> bool {
+    matches!(
+        i,
+        Instruction::Effect {
+            op: EffectOps::Jump | EffectOps::Branch | EffectOps::Return,
+            ..
+        }
+    )
+}
+
+impl Cfg {
+    /// The index of the entry block
+    pub const ENTRY: usize = 0;
+
+    /// A map from the names of each block to their index
+    #[must_use]
+    pub fn block_map(&self) -> HashMap<&str, usize> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.name.as_str(), i))
+            .collect()
+    }
+
+    /// Generates a block name which is not used by any block in the cfg, starting with `prefix`
+    #[must_use]
+    pub fn fresh_block_name(&self, prefix: &str) -> String {
+        let names: HashSet<&str> = self.blocks.iter().map(|b| b.name.as_str()).collect();
+        fresh_name(prefix, |n| names.contains(n))
+    }
+
+    /// Recomputes the predecessors and successors of every block from the terminators of each block.
+    ///
+    /// Call this after modifying the instructions of any block in a way that changes control flow.
+    /// # Errors
+    /// Will error if a jump refers to a block which does not exist.
+    pub fn recompute_edges(&mut self) -> Result<(), CfgError> {
+        let block_map: HashMap<String, usize> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.name.clone(), i))
+            .collect();
+
+        let num_blocks = self.blocks.len();
+        let mut edges = Vec::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            let mut succs: Vec<usize> = Vec::new();
+            let mut push = |s: usize| {
+                if !succs.contains(&s) {
+                    succs.push(s);
+                }
+            };
+
+            #[cfg(feature = "speculate")]
+            for instr in &block.instrs {
+                if let Instruction::Effect {
+                    op: EffectOps::Guard,
+                    labels,
+                    #[cfg(feature = "position")]
+                    pos,
+                    ..
+                } = instr
+                {
+                    for l in labels {
+                        push(lookup(&block_map, l, pos.clone())?);
+                    }
+                }
+            }
+
+            match block.instrs.last() {
+                Some(Instruction::Effect {
+                    op: EffectOps::Jump | EffectOps::Branch,
+                    labels,
+                    #[cfg(feature = "position")]
+                    pos,
+                    ..
+                }) => {
+                    for l in labels {
+                        push(lookup(&block_map, l, pos.clone())?);
+                    }
+                }
+                Some(Instruction::Effect {
+                    op: EffectOps::Return,
+                    ..
+                }) => {}
+                _ => {
+                    if i + 1 < num_blocks {
+                        push(i + 1);
+                    }
+                }
+            }
+            edges.push(succs);
+        }
+
+        for block in &mut self.blocks {
+            block.predecessors.clear();
+        }
+        for (i, succs) in edges.into_iter().enumerate() {
+            for s in &succs {
+                self.blocks[*s].predecessors.push(i);
+            }
+            self.blocks[i].successors = succs;
+        }
+        Ok(())
+    }
+
+    /// Returns the indices of the blocks reachable from the entry block in reverse post-order
+    #[must_use]
+    pub fn reverse_post_order(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut post_order = Vec::with_capacity(self.blocks.len());
+        // An explicit stack of (block, index of the next successor to visit)
+        let mut stack = vec![(Self::ENTRY, 0)];
+        visited[Self::ENTRY] = true;
+        while let Some((b, next)) = stack.last_mut() {
+            if let Some(&s) = self.blocks[*b].successors.get(*next) {
+                *next += 1;
+                if !visited[s] {
+                    visited[s] = true;
+                    stack.push((s, 0));
+                }
+            } else {
+                post_order.push(*b);
+                stack.pop();
+            }
+        }
+        post_order.reverse();
+        post_order
+    }
+}
+
+pub(crate) fn fresh_name(prefix: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    let mut i = 1;
+    loop {
+        let name = format!("{prefix}{i}");
+        if !is_taken(&name) {
+            return name;
+        }
+        i += 1;
+    }
+}
+
+// Applies `f` to each run of instructions in `instrs` which forms a basic block, without needing the labels to resolve like building a `Cfg` does
+pub(crate) fn map_blocks(instrs: &mut Vec<Code>, mut f: impl FnMut(&mut Vec<Instruction>)) {
+    let mut result = Vec::with_capacity(instrs.len());
+    let mut block = Vec::new();
+    let mut flush = |block: &mut Vec<Instruction>, result: &mut Vec<Code>| {
+        f(block);
+        result.extend(std::mem::take(block).into_iter().map(Code::Instruction));
+    };
+    for code in std::mem::take(instrs) {
+        match code {
+            Code::Label { .. } => {
+                flush(&mut block, &mut result);
+                result.push(code);
+            }
+            Code::Instruction(i) => {
+                let terminator = is_terminator(&i);
+                block.push(i);
+                if terminator {
+                    flush(&mut block, &mut result);
+                }
+            }
+        }
+    }
+    flush(&mut block, &mut result);
+    *instrs = result;
+}
+
+fn lookup(
+    block_map: &HashMap<String, usize>,
+    label: &str,
+    pos_var: Option<Position>,
+) -> Result<usize, CfgError> {
+    block_map
+        .get(label)
+        .copied()
+        .ok_or_else(|| CfgError::MissingLabel(label.to_string(), pos_var))
+}
+
+impl TryFrom<Function> for Cfg {
+    type Error = CfgError;
+
+    fn try_from(
+        Function {
+            args,
+            instrs,
+            name,
+            #[cfg(feature = "position")]
+            pos,
+            return_type,
+        }: Function,
+    ) -> Result<Self, Self::Error> {
+        let mut names = HashSet::new();
+        for code in &instrs {
+            if let Code::Label {
+                label,
+                #[cfg(feature = "position")]
+                pos,
+            } = code
+            {
+                if !names.insert(label.clone()) {
+                    return Err(CfgError::DuplicateLabel(label.clone(), pos.clone()));
+                }
+            }
+        }
+
+        // Unlabeled blocks are given fresh names that can't clash with the labels of the function
+        let mut unlabeled_block = |prefix: &str| {
+            let n = fresh_name(prefix, |n| names.contains(n));
+            names.insert(n.clone());
+            BasicBlock::new(n, false)
+        };
+
+        let mut blocks = Vec::new();
+        let mut curr_block: Option<BasicBlock> = None;
+        for code in instrs {
+            match code {
+                Code::Label {
+                    label,
+                    #[cfg(feature = "position")]
+                    pos,
+                } => {
+                    if let Some(b) = curr_block.take() {
+                        blocks.push(b);
+                    }
+                    #[cfg(feature = "position")]
+                    let block = BasicBlock {
+                        label_pos: pos,
+                        ..BasicBlock::new(label, true)
+                    };
+                    #[cfg(not(feature = "position"))]
+                    let block = BasicBlock::new(label, true);
+                    curr_block = Some(block);
+                }
+                Code::Instruction(i) => {
+                    let terminator = is_terminator(&i);
+                    curr_block
+                        .get_or_insert_with(|| unlabeled_block("b"))
+                        .instrs
+                        .push(i);
+                    if terminator {
+                        blocks.push(curr_block.take().unwrap());
+                    }
+                }
+            }
+        }
+        if let Some(b) = curr_block {
+            blocks.push(b);
+        }
+
+        let mut cfg = Self {
+            args,
+            blocks,
+            name,
+            #[cfg(feature = "position")]
+            pos,
+            return_type,
+        };
+        if cfg.blocks.is_empty() {
+            cfg.blocks.push(unlabeled_block("b"));
+        }
+        cfg.recompute_edges()?;
+
+        // The entry block can't have any predecessors
+        if !cfg.blocks[Self::ENTRY].predecessors.is_empty() {
+            cfg.blocks.insert(Self::ENTRY, unlabeled_block("entry"));
+            cfg.recompute_edges()?;
+        }
+        Ok(cfg)
+    }
+}
+
+impl From<Cfg> for Function {
+    fn from(
+        Cfg {
+            args,
+            blocks,
+            name,
+            #[cfg(feature = "position")]
+            pos,
+            return_type,
+        }: Cfg,
+    ) -> Self {
+        let instrs = blocks
+            .into_iter()
+            .flat_map(|b| {
+                let label = b.labeled.then_some(Code::Label {
+                    label: b.name,
+                    #[cfg(feature = "position")]
+                    pos: b.label_pos,
+                });
+                label
+                    .into_iter()
+                    .chain(b.instrs.into_iter().map(Code::Instruction))
+            })
+            .collect();
+        Self {
+            args,
+            instrs,
+            name,
+            #[cfg(feature = "position")]
+            pos,
+            return_type,
+        }
+    }
+}
@@ -14,8 +14,7 @@ use thiserror::Error;
 )]
 const pos: Option<Position> = None;
 
-/// This is the [`std::error::Error`] implementation for `bril_rs`. This crate currently only supports errors from converting between [`AbstractProgram`] and [Program]
-// todo Should this also wrap Serde errors? In this case, maybe change the name from ConversionError
+/// This is the [`std::error::Error`] implementation for errors from converting between [`AbstractProgram`] and [Program]. See [`crate::error::LoadError`] for the error type which also wraps IO and parsing errors.
 // Having the #[error(...)] for all variants derives the Display trait as well
 #[derive(Error, Debug)]
 #[expect(
@@ -252,9 +251,24 @@ impl TryFrom<AbstractType> for Type {
             AbstractType::Parameterized(t, ty) if t == "ptr" => {
                 Ok(Self::Pointer(Box::new((*ty).try_into()?)))
             }
+            #[cfg(feature = "other")]
+            ty @ AbstractType::Parameterized(..) => Ok(Self::Other(ty)),
+            #[cfg(not(feature = "other"))]
             AbstractType::Parameterized(t, ty) => {
                 Err(ConversionError::InvalidParameterized(t, ty.to_string()))
             }
         }
     }
 }
+
+impl From<Type> for AbstractType {
+    fn from(value: Type) -> Self {
+        match value {
+            #[cfg(feature = "memory")]
+            Type::Pointer(ty) => Self::Parameterized("ptr".to_string(), Box::new((*ty).into())),
+            #[cfg(feature = "other")]
+            Type::Other(ty) => ty,
+            ty => Self::Primitive(ty.to_string()),
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+use crate::{cfg::Cfg, fold::fold, Instruction, Literal, ValueOps};
+
+/// The direction that facts flow through a [`Cfg`] in an [`Analysis`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Facts flow from the entry block along control-flow edges
+    Forward,
+    /// Facts flow from the exit blocks against control-flow edges
+    Backward,
+}
+
+/// The location of an instruction in a [`Cfg`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    /// The index of the block in [`Cfg::blocks`]
+    pub block: usize,
+    /// The index of the instruction in [`crate::cfg::BasicBlock::instrs`]
+    pub index: usize,
+}
+
+/// A dataflow analysis which can be run over a [`Cfg`] with [`solve`]
+///
+/// This mirrors the analyses of `examples/df.py`: the lattice is given by [`Analysis::Domain`] and [`Analysis::meet`], and each block is analyzed by applying [`Analysis::transfer`] to its instructions in order(or in reverse for [`Direction::Backward`]).
+pub trait Analysis {
+    /// The facts computed at each program point
+    type Domain: Clone + PartialEq;
+
+    /// Whether this analysis is forward or backward
+    const DIRECTION: Direction;
+
+    /// The value flowing into the cfg: at the start of the entry block for forward analyses or at the end of every block without successors for backward analyses
+    fn boundary(&self, cfg: &Cfg) -> Self::Domain;
+
+    /// The value every other program point starts with. This should be the identity of [`Analysis::meet`]
+    fn init(&self, cfg: &Cfg) -> Self::Domain;
+
+    /// Combines `other` into `value` where control-flow merges
+    fn meet(&self, value: &mut Self::Domain, other: &Self::Domain);
+
+    /// Updates `value` to reflect executing `instr`, which is found at `loc`
+    fn transfer(&self, value: &mut Self::Domain, instr: &Instruction, loc: Location);
+}
+
+/// The solution to an [`Analysis`] over a [`Cfg`]
+///
+/// Regardless of the direction of the analysis, `ins` holds the facts at the start of each block and `outs` holds the facts at the end of each block in program order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataflowResult<D> {
+    /// The facts at the start of each block, indexed like [`Cfg::blocks`]
+    pub ins: Vec<D>,
+    /// The facts at the end of each block, indexed like [`Cfg::blocks`]
+    pub outs: Vec<D>,
+}
+
+impl<D: Clone + PartialEq> DataflowResult<D> {
+    /// The facts at every program point of `block`, in program order
+    ///
+    /// The result has one more element than the block has instructions: element `i` holds the facts right before instruction `i` and the last element holds the facts at the end of the block.
+    #[must_use]
+    pub fn points<A: Analysis<Domain = D>>(&self, analysis: &A, cfg: &Cfg, block: usize) -> Vec<D> {
+        let instrs = &cfg.blocks[block].instrs;
+        match A::DIRECTION {
+            Direction::Forward => {
+                let mut value = self.ins[block].clone();
+                let mut points = vec![value.clone()];
+                for (index, instr) in instrs.iter().enumerate() {
+                    analysis.transfer(&mut value, instr, Location { block, index });
+                    points.push(value.clone());
+                }
+                points
+            }
+            Direction::Backward => {
+                let mut value = self.outs[block].clone();
+                let mut points = vec![value.clone()];
+                for (index, instr) in instrs.iter().enumerate().rev() {
+                    analysis.transfer(&mut value, instr, Location { block, index });
+                    points.push(value.clone());
+                }
+                points.reverse();
+                points
+            }
+        }
+    }
+}
+
+/// Solves `analysis` over `cfg` using a worklist until a fixed point is reached
+///
+/// Blocks are visited in reverse post-order(or its reverse for backward analyses) so that most analyses converge in a few passes.
+/// Blocks which are unreachable are still analyzed.
+#[must_use]
+pub fn solve<A: Analysis>(analysis: &A, cfg: &Cfg) -> DataflowResult<A::Domain> {
+    let num_blocks = cfg.blocks.len();
+    let forward = A::DIRECTION == Direction::Forward;
+
+    let mut order = cfg.reverse_post_order();
+    let mut in_worklist = vec![false; num_blocks];
+    for &b in &order {
+        in_worklist[b] = true;
+    }
+    order.extend((0..num_blocks).filter(|b| !in_worklist[*b]));
+    if !forward {
+        order.reverse();
+    }
+    in_worklist.fill(true);
+    let mut worklist: VecDeque<usize> = order.into();
+
+    // `inputs` are the facts flowing into a block and `outputs` the facts flowing out of it, following the direction of the analysis
+    let init = analysis.init(cfg);
+    let mut inputs = vec![init.clone(); num_blocks];
+    let mut outputs = vec![init; num_blocks];
+
+    while let Some(b) = worklist.pop_front() {
+        in_worklist[b] = false;
+        let block = &cfg.blocks[b];
+        let (sources, sinks) = if forward {
+            (&block.predecessors, &block.successors)
+        } else {
+            (&block.successors, &block.predecessors)
+        };
+
+        let mut value = if (forward && b == Cfg::ENTRY) || (!forward && sources.is_empty()) {
+            analysis.boundary(cfg)
+        } else if let Some((first, rest)) = sources.split_first() {
+            let mut value = outputs[*first].clone();
+            for s in rest {
+                analysis.meet(&mut value, &outputs[*s]);
+            }
+            value
+        } else {
+            analysis.init(cfg)
+        };
+        inputs[b] = value.clone();
+
+        let transfer = |(index, instr)| {
+            analysis.transfer(&mut value, instr, Location { block: b, index });
+        };
+        if forward {
+            block.instrs.iter().enumerate().for_each(transfer);
+        } else {
+            block.instrs.iter().enumerate().rev().for_each(transfer);
+        }
+
+        if value != outputs[b] {
+            outputs[b] = value;
+            for &s in sinks {
+                if !in_worklist[s] {
+                    in_worklist[s] = true;
+                    worklist.push_back(s);
+                }
+            }
+        }
+    }
+
+    if forward {
+        DataflowResult {
+            ins: inputs,
+            outs: outputs,
+        }
+    } else {
+        DataflowResult {
+            ins: outputs,
+            outs: inputs,
+        }
+    }
+}
+
+/// A definition of a variable, either by an instruction or as an argument of the function
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Definition {
+    /// The variable being defined
+    pub var: String,
+    /// The instruction defining `var`, or [`None`] if `var` is an argument of the function
+    pub site: Option<Location>,
+}
+
+/// Reaching definitions: the set of [`Definition`]s which may reach each program point without being overwritten
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReachingDefinitions;
+
+impl Analysis for ReachingDefinitions {
+    type Domain = BTreeSet<Definition>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, cfg: &Cfg) -> Self::Domain {
+        cfg.args
+            .iter()
+            .map(|a| Definition {
+                var: a.name.clone(),
+                site: None,
+            })
+            .collect()
+    }
+
+    fn init(&self, _cfg: &Cfg) -> Self::Domain {
+        BTreeSet::new()
+    }
+
+    fn meet(&self, value: &mut Self::Domain, other: &Self::Domain) {
+        value.extend(other.iter().cloned());
+    }
+
+    fn transfer(&self, value: &mut Self::Domain, instr: &Instruction, loc: Location) {
+        if let Some(dest) = instr.dest() {
+            value.retain(|d| d.var != dest);
+            value.insert(Definition {
+                var: dest.to_string(),
+                site: Some(loc),
+            });
+        }
+    }
+}
+
+/// Live variables: the set of variables which may be read at some later point before being overwritten
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LiveVariables;
+
+impl Analysis for LiveVariables {
+    type Domain = BTreeSet<String>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn boundary(&self, _cfg: &Cfg) -> Self::Domain {
+        BTreeSet::new()
+    }
+
+    fn init(&self, _cfg: &Cfg) -> Self::Domain {
+        BTreeSet::new()
+    }
+
+    fn meet(&self, value: &mut Self::Domain, other: &Self::Domain) {
+        value.extend(other.iter().cloned());
+    }
+
+    fn transfer(&self, value: &mut Self::Domain, instr: &Instruction, _loc: Location) {
+        if let Some(dest) = instr.dest() {
+            value.remove(dest);
+        }
+        value.extend(instr.uses().iter().cloned());
+    }
+}
+
+/// A pure computation which can be reused if its arguments have not been overwritten
+///
+/// The arguments of commutative operations are sorted so that `add a b` and `add b a` are the same expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Expression {
+    /// The operation being computed
+    pub op: ValueOps,
+    /// The variables the operation is applied to
+    pub args: Vec<String>,
+}
+
+impl Expression {
+    /// The expression computed by `instr` if it is a pure value operation
+    ///
+    /// Operations with side effects or whose results can differ between executions, like `call`, `alloc`, `load`, and `get`, are not expressions.
+    #[must_use]
+    pub fn from_instruction(instr: &Instruction) -> Option<Self> {
+        let Instruction::Value { op, args, .. } = instr else {
+            return None;
+        };
+        match op {
+            ValueOps::Call => return None,
+            #[cfg(feature = "memory")]
+            ValueOps::Alloc | ValueOps::Load => return None,
+            #[cfg(feature = "ssa")]
+            ValueOps::Phi => return None,
+            #[cfg(feature = "ssa2")]
+            ValueOps::Get | ValueOps::Undef => return None,
+            #[cfg(feature = "other")]
+            ValueOps::Other(_) => return None,
+            _ => {}
+        }
+        let mut args = args.clone();
+        if is_commutative(op) {
+            args.sort();
+        }
+        Some(Self {
+            op: ValueOps::clone(op),
+            args,
+        })
+    }
+}
+
+/// Whether the arguments of `op` can be swapped without changing its result
+#[must_use]
+pub const fn is_commutative(op: &ValueOps) -> bool {
+    match op {
+        ValueOps::Add | ValueOps::Mul | ValueOps::Eq | ValueOps::And | ValueOps::Or => true,
+        #[cfg(feature = "float")]
+        ValueOps::Fadd | ValueOps::Fmul | ValueOps::Feq => true,
+        #[cfg(feature = "char")]
+        ValueOps::Ceq => true,
+        _ => false,
+    }
+}
+
+/// Available expressions: the set of [`Expression`]s which have been computed along every path to a program point and whose arguments have not been overwritten since
+///
+/// [`None`] stands for the set of every expression, which is the starting point of this analysis.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AvailableExpressions;
+
+impl Analysis for AvailableExpressions {
+    type Domain = Option<HashSet<Expression>>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, _cfg: &Cfg) -> Self::Domain {
+        Some(HashSet::new())
+    }
+
+    fn init(&self, _cfg: &Cfg) -> Self::Domain {
+        None
+    }
+
+    fn meet(&self, value: &mut Self::Domain, other: &Self::Domain) {
+        match (value.as_mut(), other) {
+            (_, None) => {}
+            (None, Some(_)) => value.clone_from(other),
+            (Some(v), Some(o)) => v.retain(|e| o.contains(e)),
+        }
+    }
+
+    fn transfer(&self, value: &mut Self::Domain, instr: &Instruction, _loc: Location) {
+        let Some(exprs) = value else {
+            return;
+        };
+        if let Some(dest) = instr.dest() {
+            exprs.retain(|e| !e.args.iter().any(|a| a == dest));
+            if let Some(e) = Expression::from_instruction(instr) {
+                if !e.args.iter().any(|a| a == dest) {
+                    exprs.insert(e);
+                }
+            }
+        }
+    }
+}
+
+/// What is known about the value of a variable in [`ConstantPropagation`]
+#[derive(Debug, Clone)]
+pub enum ConstValue {
+    /// The variable always holds this value
+    Known(Literal),
+    /// The variable may hold different values
+    Unknown,
+}
+
+// Floats are compared by their bits so that `NaN` constants don't stop the analysis from reaching a fixed point
+impl PartialEq for ConstValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            #[cfg(feature = "float")]
+            (Self::Known(Literal::Float(a)), Self::Known(Literal::Float(b))) => {
+                a.to_bits() == b.to_bits()
+            }
+            (Self::Known(a), Self::Known(b)) => a == b,
+            (Self::Unknown, Self::Unknown) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ConstValue {}
+
+/// Constant propagation: the value of each defined variable if it is the same along every path to a program point
+///
+/// Value operations are folded when all of their arguments are known. Variables which have not been defined along any path, like the arguments of the function, are not in the map and are treated as [`ConstValue::Unknown`] by [`Analysis::transfer`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstantPropagation;
+
+impl Analysis for ConstantPropagation {
+    type Domain = BTreeMap<String, ConstValue>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, _cfg: &Cfg) -> Self::Domain {
+        BTreeMap::new()
+    }
+
+    fn init(&self, _cfg: &Cfg) -> Self::Domain {
+        BTreeMap::new()
+    }
+
+    fn meet(&self, value: &mut Self::Domain, other: &Self::Domain) {
+        for (var, c) in other {
+            value
+                .entry(var.clone())
+                .and_modify(|v| {
+                    if v != c {
+                        *v = ConstValue::Unknown;
+                    }
+                })
+                .or_insert_with(|| c.clone());
+        }
+    }
+
+    fn transfer(&self, value: &mut Self::Domain, instr: &Instruction, _loc: Location) {
+        let result = match instr {
+            Instruction::Constant {
+                dest, value: lit, ..
+            } => Some((dest, ConstValue::Known(lit.clone()))),
+            Instruction::Value {
+                dest,
+                op,
+                args,
+                op_type,
+                ..
+            } => {
+                let lits: Option<Vec<&Literal>> = args
+                    .iter()
+                    .map(|a| match value.get(a) {
+                        Some(ConstValue::Known(l)) => Some(l),
+                        _ => None,
+                    })
+                    .collect();
+                let folded = lits.and_then(|lits| fold(op, op_type, &lits));
+                Some((dest, folded.map_or(ConstValue::Unknown, ConstValue::Known)))
+            }
+            Instruction::Effect { .. } => None,
+        };
+        if let Some((dest, c)) = result {
+            value.insert(dest.clone(), c);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[cfg(feature = "speculate")]
+use crate::EffectOps;
+use crate::{
+    cfg::{map_blocks, Cfg},
+    dataflow::{solve, Analysis, LiveVariables, Location},
+    Code, Function, Instruction, ValueOps,
+};
+
+/// Whether `instr` does something besides assigning its destination, so that it can't be removed even when its result is unused
+///
+/// This includes every effect operation, `call`, `alloc`, and any value operation from another extension.
+#[must_use]
+pub const fn has_side_effects(instr: &Instruction) -> bool {
+    match instr {
+        Instruction::Constant { .. } => false,
+        Instruction::Value { op, .. } => match op {
+            ValueOps::Call => true,
+            #[cfg(feature = "memory")]
+            ValueOps::Alloc => true,
+            #[cfg(feature = "other")]
+            ValueOps::Other(_) => true,
+            _ => false,
+        },
+        Instruction::Effect { .. } => true,
+    }
+}
+
+// Whether `instr` starts a speculative region, which a failed `guard` can roll back to
+const fn is_speculate(instr: &Instruction) -> bool {
+    match instr {
+        #[cfg(feature = "speculate")]
+        Instruction::Effect {
+            op: EffectOps::Speculate,
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
+/// Removes pure instructions whose destinations are never used as an argument anywhere in `function`, like `tdce.py tdcep`
+///
+/// Returns whether anything was removed.
+pub fn trivial_dce_pass(function: &mut Function) -> bool {
+    let used: HashSet<String> = function
+        .instrs
+        .iter()
+        .filter_map(|c| match c {
+            Code::Instruction(i) => Some(i.args()),
+            Code::Label { .. } => None,
+        })
+        .flatten()
+        .cloned()
+        .collect();
+    let before = function.instrs.len();
+    function.instrs.retain(|c| match c {
+        Code::Instruction(i) => has_side_effects(i) || i.dest().is_none_or(|d| used.contains(d)),
+        Code::Label { .. } => true,
+    });
+    function.instrs.len() != before
+}
+
+/// Repeats [`trivial_dce_pass`] until nothing more can be removed, like `tdce.py tdce`
+pub fn trivial_dce(function: &mut Function) {
+    while trivial_dce_pass(function) {}
+}
+
+/// Removes pure instructions whose destinations are reassigned later in the same basic block before being used, like `tdce.py dkp`
+///
+/// Definitions from before a `speculate` are kept, since a failed `guard` restores them.
+/// Returns whether anything was removed.
+pub fn drop_killed_pass(function: &mut Function) -> bool {
+    let mut changed = false;
+    map_blocks(&mut function.instrs, |block| {
+        // The last pure definition of each variable which hasn't been used yet
+        let mut last_def: HashMap<&str, usize> = HashMap::new();
+        let mut dead = HashSet::new();
+        for (i, instr) in block.iter().enumerate() {
+            // A failed `guard` restores the values variables had here
+            if is_speculate(instr) {
+                last_def.clear();
+            }
+            for a in instr.args() {
+                last_def.remove(a.as_str());
+            }
+            if let Some(dest) = instr.dest() {
+                if let Some(killed) = last_def.remove(dest) {
+                    dead.insert(killed);
+                }
+                if !has_side_effects(instr) {
+                    last_def.insert(dest, i);
+                }
+            }
+        }
+        changed |= !dead.is_empty();
+        let mut i = 0;
+        block.retain(|_| {
+            i += 1;
+            !dead.contains(&(i - 1))
+        });
+    });
+    changed
+}
+
+/// Alternates [`trivial_dce_pass`] and [`drop_killed_pass`] until neither removes anything, like `tdce.py tdce+`
+pub fn trivial_dce_plus(function: &mut Function) {
+    while trivial_dce_pass(function) || drop_killed_pass(function) {}
+}
+
+/// Removes pure instructions whose destinations are not live immediately afterwards, using [`LiveVariables`] over the whole `cfg`
+///
+/// This finds definitions which are dead along every path, even when the variable is used elsewhere in the function, and repeats until nothing more can be removed.
+/// In functions using speculation, variables which are used anywhere are conservatively kept live everywhere since a failed `guard` can restore their old values.
+/// Returns whether anything was removed.
+pub fn global_dce(cfg: &mut Cfg) -> bool {
+    let instrs = || cfg.blocks.iter().flat_map(|b| &b.instrs);
+    let pinned: BTreeSet<String> = if instrs().any(is_speculate) {
+        instrs().flat_map(|i| i.args().iter().cloned()).collect()
+    } else {
+        BTreeSet::new()
+    };
+
+    let mut changed = false;
+    loop {
+        let live = solve(&LiveVariables, cfg);
+        let mut removed = false;
+        for (b, block) in cfg.blocks.iter_mut().enumerate() {
+            let mut live_now = live.outs[b].clone();
+            let mut keep = vec![true; block.instrs.len()];
+            for (index, instr) in block.instrs.iter().enumerate().rev() {
+                if let Some(dest) = instr.dest() {
+                    if !has_side_effects(instr)
+                        && !live_now.contains(dest)
+                        && !pinned.contains(dest)
+                    {
+                        keep[index] = false;
+                        continue;
+                    }
+                }
+                LiveVariables.transfer(&mut live_now, instr, Location { block: b, index });
+            }
+            if keep.contains(&false) {
+                removed = true;
+                let mut keep = keep.into_iter();
+                block.instrs.retain(|_| keep.next().unwrap_or(true));
+            }
+        }
+        if !removed {
+            return changed;
+        }
+        changed = true;
+    }
+}
@@ -0,0 +1,182 @@
+use crate::cfg::Cfg;
+
+/// The dominance relation of a [`Cfg`]
+///
+/// Block `a` dominates block `b` if every path from the entry block to `b` goes through `a`.
+/// Immediate dominators are computed with the iterative algorithm from "A Simple, Fast Dominance Algorithm" by Cooper, Harvey, and Kennedy.
+/// All blocks are referred to by their index in [`Cfg::blocks`].
+///
+/// Blocks which are unreachable from the entry block have no immediate dominator, are only dominated by themselves, and do not dominate any other block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dominators {
+    idoms: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    frontiers: Vec<Vec<usize>>,
+    // Pre and post order numbers of each block in the dominator tree
+    // These make checking dominance a constant time operation
+    pre: Vec<usize>,
+    post: Vec<usize>,
+}
+
+impl Dominators {
+    /// Computes the dominance relation for `cfg`
+    #[must_use]
+    pub fn new(cfg: &Cfg) -> Self {
+        let num_blocks = cfg.blocks.len();
+        let rpo = cfg.reverse_post_order();
+        let mut rpo_index = vec![usize::MAX; num_blocks];
+        for (i, b) in rpo.iter().enumerate() {
+            rpo_index[*b] = i;
+        }
+
+        let mut idoms: Vec<Option<usize>> = vec![None; num_blocks];
+        idoms[Cfg::ENTRY] = Some(Cfg::ENTRY);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &b in rpo.iter().skip(1) {
+                let new_idom = cfg.blocks[b]
+                    .predecessors
+                    .iter()
+                    .copied()
+                    .filter(|p| idoms[*p].is_some())
+                    .reduce(|a, p| intersect(&idoms, &rpo_index, a, p));
+                if new_idom.is_some() && idoms[b] != new_idom {
+                    idoms[b] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        // The entry block is the root of the tree
+        idoms[Cfg::ENTRY] = None;
+
+        let mut children = vec![Vec::new(); num_blocks];
+        for &b in &rpo {
+            if let Some(d) = idoms[b] {
+                children[d].push(b);
+            }
+        }
+
+        // https://www.cs.rice.edu/~keith/EMBED/dom.pdf, Figure 5
+        let mut frontiers: Vec<Vec<usize>> = vec![Vec::new(); num_blocks];
+        for &b in &rpo {
+            let preds: Vec<usize> = cfg.blocks[b]
+                .predecessors
+                .iter()
+                .copied()
+                .filter(|p| rpo_index[*p] != usize::MAX)
+                .collect();
+            if preds.len() < 2 {
+                continue;
+            }
+            for p in preds {
+                let mut runner = Some(p);
+                while let Some(r) = runner {
+                    if Some(r) == idoms[b] {
+                        break;
+                    }
+                    if !frontiers[r].contains(&b) {
+                        frontiers[r].push(b);
+                    }
+                    runner = idoms[r];
+                }
+            }
+        }
+
+        let mut pre = vec![usize::MAX; num_blocks];
+        let mut post = vec![usize::MAX; num_blocks];
+        let mut counter = 0;
+        let mut stack = vec![(Cfg::ENTRY, 0)];
+        pre[Cfg::ENTRY] = counter;
+        while let Some((b, next)) = stack.last_mut() {
+            if let Some(&c) = children[*b].get(*next) {
+                *next += 1;
+                counter += 1;
+                pre[c] = counter;
+                stack.push((c, 0));
+            } else {
+                counter += 1;
+                post[*b] = counter;
+                stack.pop();
+            }
+        }
+
+        Self {
+            idoms,
+            children,
+            frontiers,
+            pre,
+            post,
+        }
+    }
+
+    /// The immediate dominator of `block`, which is [`None`] for the entry block and unreachable blocks
+    #[must_use]
+    pub fn immediate_dominator(&self, block: usize) -> Option<usize> {
+        self.idoms[block]
+    }
+
+    /// Whether `a` dominates `b`. Every block dominates itself
+    #[must_use]
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        a == b
+            || (self.is_reachable(a)
+                && self.is_reachable(b)
+                && self.pre[a] <= self.pre[b]
+                && self.post[b] <= self.post[a])
+    }
+
+    /// Whether `a` dominates `b` and `a` is not `b`
+    #[must_use]
+    pub fn strictly_dominates(&self, a: usize, b: usize) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// All of the dominators of `block`, starting with `block` itself and walking up the dominator tree to the entry block
+    #[must_use]
+    pub fn dominators(&self, block: usize) -> Vec<usize> {
+        std::iter::successors(Some(block), |b| self.idoms[*b]).collect()
+    }
+
+    /// The children of `block` in the dominator tree, i.e. the blocks which `block` immediately dominates
+    #[must_use]
+    pub fn children(&self, block: usize) -> &[usize] {
+        &self.children[block]
+    }
+
+    /// The dominance frontier of `block`: the blocks which `block` does not strictly dominate but which have a predecessor that `block` dominates
+    #[must_use]
+    pub fn frontier(&self, block: usize) -> &[usize] {
+        &self.frontiers[block]
+    }
+
+    /// Whether `block` is reachable from the entry block
+    #[must_use]
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.pre[block] != usize::MAX
+    }
+
+    /// The reachable blocks in a pre-order traversal of the dominator tree starting from the entry block
+    #[must_use]
+    pub fn tree_pre_order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.idoms.len());
+        let mut stack = vec![Cfg::ENTRY];
+        while let Some(b) = stack.pop() {
+            order.push(b);
+            stack.extend(self.children[b].iter().rev());
+        }
+        order
+    }
+}
+
+fn intersect(idoms: &[Option<usize>], rpo_index: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while rpo_index[a] > rpo_index[b] {
+            a = idoms[a].unwrap();
+        }
+        while rpo_index[b] > rpo_index[a] {
+            b = idoms[b].unwrap();
+        }
+    }
+    a
+}
@@ -0,0 +1,149 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{conversion::PositionalConversionError, ColRow, Position};
+
+/// The unified error type for loading Bril programs from JSON, binary, bytecode, or text.
+///
+/// This wraps failures from reading the input, deserializing JSON, the binary encoding, or `fastbril` bytecode, parsing the text format, and converting an [`crate::AbstractProgram`] into a [`crate::Program`] so that tools can report them without panicking.
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum LoadError {
+    /// There has been an io error while reading the input
+    #[error("There has been an io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The input was not well-formed Bril JSON
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The input was not a well-formed binary Bril program
+    #[error("{0}")]
+    Binary(#[from] crate::binary::BinaryError),
+
+    /// The input was not well-formed `fastbril` bytecode
+    #[error("{0}")]
+    Bytecode(#[from] crate::bytecode::BytecodeError),
+
+    /// The input was not well-formed Bril text
+    #[error("{}{}", pos_prefix(.0.pos.as_ref()), .0)]
+    Parse(Box<ParseError>),
+
+    /// The program could not be converted into a structured representation
+    #[error("{0}")]
+    Conversion(Box<PositionalConversionError>),
+}
+
+fn pos_prefix(pos: Option<&Position>) -> String {
+    pos.map_or_else(String::new, |p| {
+        format!("Line {}, Column {}: ", p.pos.row, p.pos.col)
+    })
+}
+
+// Writes `e` prefixed by where it happened, in the same format as the errors of `brilirs`
+pub(crate) fn fmt_positional(
+    f: &mut std::fmt::Formatter<'_>,
+    e: &impl Display,
+    pos: Option<&Position>,
+) -> std::fmt::Result {
+    match pos {
+        Some(Position {
+            pos,
+            pos_end: Some(end),
+            src: Some(s),
+        }) => write!(
+            f,
+            "{s}:{}:{} to {s}:{}:{} \n\t {e}",
+            pos.row, pos.col, end.row, end.col
+        ),
+        Some(Position {
+            pos,
+            pos_end: None,
+            src: Some(s),
+        }) => write!(f, "{s}:{}:{} \n\t {e}", pos.row, pos.col),
+        Some(Position {
+            pos,
+            pos_end: Some(end),
+            src: None,
+        }) => write!(
+            f,
+            "Line {}, Column {} to Line {}, Column {}: {e}",
+            pos.row, pos.col, end.row, end.col
+        ),
+        Some(Position {
+            pos,
+            pos_end: None,
+            src: None,
+        }) => write!(f, "Line {}, Column {}: {e}", pos.row, pos.col),
+        None => write!(f, "{e}"),
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(Box::new(e))
+    }
+}
+
+impl From<PositionalConversionError> for LoadError {
+    fn from(e: PositionalConversionError) -> Self {
+        Self::Conversion(Box::new(e))
+    }
+}
+
+impl LoadError {
+    /// The source position of the error if one is known.
+    ///
+    /// For JSON errors, this is the line and column of the input reported by `serde_json`.
+    #[must_use]
+    pub fn pos(&self) -> Option<Position> {
+        match self {
+            Self::Io(_) | Self::Binary(_) | Self::Bytecode(_) => None,
+            Self::Json(e) if e.line() == 0 => None,
+            Self::Json(e) => Some(Position {
+                pos: ColRow {
+                    col: e.column() as u64,
+                    row: e.line() as u64,
+                },
+                pos_end: None,
+                src: None,
+            }),
+            Self::Parse(e) => e.pos.clone(),
+            Self::Conversion(e) => e.pos.clone(),
+        }
+    }
+}
+
+/// An error from parsing the Bril text format.
+///
+/// `bril_rs` does not depend on a particular parser so the parser's error is flattened into the offending token(if there is one), a message, and its position.
+/// The position is not included when this is displayed on its own.
+#[derive(Error, Debug)]
+pub struct ParseError {
+    /// A description of what went wrong
+    pub message: String,
+    /// The text of the offending token, if the error was caused by one
+    pub token: Option<String>,
+    /// The tokens which would have been accepted instead
+    pub expected: Vec<String>,
+    /// Where the error occurred in the source text
+    pub pos: Option<Position>,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(token) = &self.token {
+            write!(f, " `{token}`")?;
+        }
+        if !self.expected.is_empty() {
+            write!(f, ", expected one of {}", self.expected.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use thiserror::Error;
+
+use crate::{
+    AbstractCode, AbstractFunction, AbstractInstruction, AbstractProgram, AbstractType, Code,
+    Function, Instruction, Position, Program,
+};
+
+#[cfg(not(feature = "position"))]
+#[expect(
+    non_upper_case_globals,
+    reason = "This is a nifty trick to supply a global value for pos when it is not defined"
+)]
+const pos: Option<Position> = None;
+
+/// An extension of the core Bril language, named as in the documentation and in the feature flags of this crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Extension {
+    /// <https://capra.cs.cornell.edu/bril/lang/float.html>
+    Float,
+    /// <https://capra.cs.cornell.edu/bril/lang/char.html>
+    Char,
+    /// <https://capra.cs.cornell.edu/bril/lang/memory.html>
+    Memory,
+    /// <https://capra.cs.cornell.edu/bril/lang/ssa.html>, which covers `phi` along with `get`, `set`, and `undef`
+    Ssa,
+    /// <https://capra.cs.cornell.edu/bril/lang/spec.html>
+    Speculate,
+    /// <https://capra.cs.cornell.edu/bril/lang/import.html>
+    Import,
+    /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#source-positions>
+    Position,
+}
+
+impl Display for Extension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Float => write!(f, "float"),
+            Self::Char => write!(f, "char"),
+            Self::Memory => write!(f, "memory"),
+            Self::Ssa => write!(f, "ssa"),
+            Self::Speculate => write!(f, "speculate"),
+            Self::Import => write!(f, "import"),
+            Self::Position => write!(f, "position"),
+        }
+    }
+}
+
+/// The set of extensions used by a program, found with [`Program::extensions`] or [`AbstractProgram::extensions`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extensions {
+    // The position of the first use of each extension, when it has one
+    used: BTreeMap<Extension, Option<Position>>,
+}
+
+impl Extensions {
+    /// Whether `extension` is used anywhere in the program
+    #[must_use]
+    pub fn contains(&self, extension: Extension) -> bool {
+        self.used.contains_key(&extension)
+    }
+
+    /// The source position of the first use of `extension`, if it is used and positions are available
+    #[must_use]
+    pub fn first_use(&self, extension: Extension) -> Option<&Position> {
+        self.used.get(&extension).and_then(Option::as_ref)
+    }
+
+    /// The extensions which are used, in the order they are declared in [Extension]
+    pub fn iter(&self) -> impl Iterator<Item = Extension> + '_ {
+        self.used.keys().copied()
+    }
+
+    /// Whether the program only uses the core language
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+
+    /// Checks that every extension which is used is one of `supported`, so that tools can reject a program up front
+    /// # Errors
+    /// Returns the first extension which is used but not supported, along with where it is first used
+    pub fn check(&self, supported: &[Extension]) -> Result<(), UnsupportedExtension> {
+        match self.used.iter().find(|(e, _)| !supported.contains(e)) {
+            Some((extension, at)) => Err(UnsupportedExtension {
+                extension: *extension,
+                pos: at.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn instruction(&mut self, instr: &Instruction) {
+        match instr {
+            Instruction::Constant {
+                const_type,
+                #[cfg(feature = "position")]
+                pos,
+                ..
+            } => {
+                self.position(pos.as_ref());
+                self.ty(&const_type.clone().into(), pos.as_ref());
+            }
+            Instruction::Value {
+                op,
+                op_type,
+                #[cfg(feature = "position")]
+                pos,
+                ..
+            } => {
+                self.position(pos.as_ref());
+                self.ty(&op_type.clone().into(), pos.as_ref());
+                self.op(&op.to_string(), pos.as_ref());
+            }
+            Instruction::Effect {
+                op,
+                #[cfg(feature = "position")]
+                pos,
+                ..
+            } => {
+                self.position(pos.as_ref());
+                self.op(&op.to_string(), pos.as_ref());
+            }
+        }
+    }
+
+    fn abstract_instruction(&mut self, instr: &AbstractInstruction) {
+        match instr {
+            AbstractInstruction::Constant {
+                const_type,
+                value,
+                #[cfg(feature = "position")]
+                pos,
+                ..
+            } => {
+                self.position(pos.as_ref());
+                if let Some(t) = const_type {
+                    self.ty(t, pos.as_ref());
+                }
+                self.ty(&value.get_type().into(), pos.as_ref());
+            }
+            AbstractInstruction::Value {
+                op,
+                op_type,
+                #[cfg(feature = "position")]
+                pos,
+                ..
+            } => {
+                self.position(pos.as_ref());
+                if let Some(t) = op_type {
+                    self.ty(t, pos.as_ref());
+                }
+                self.op(op, pos.as_ref());
+            }
+            AbstractInstruction::Effect {
+                op,
+                #[cfg(feature = "position")]
+                pos,
+                ..
+            } => {
+                self.position(pos.as_ref());
+                self.op(op, pos.as_ref());
+            }
+        }
+    }
+
+    fn add(&mut self, extension: Extension, at: Option<&Position>) {
+        self.used.entry(extension).or_insert_with(|| at.cloned());
+    }
+
+    fn position(&mut self, at: Option<&Position>) {
+        if at.is_some() {
+            self.add(Extension::Position, at);
+        }
+    }
+
+    // Operations and types are matched by name so that programs can be checked for extensions whose feature flags are not enabled
+    fn op(&mut self, op: &str, at: Option<&Position>) {
+        let extension = match op {
+            "fadd" | "fsub" | "fmul" | "fdiv" | "feq" | "flt" | "fgt" | "fle" | "fge" => {
+                Extension::Float
+            }
+            "ceq" | "clt" | "cgt" | "cle" | "cge" | "char2int" | "int2char" => Extension::Char,
+            "alloc" | "free" | "store" | "load" | "ptradd" => Extension::Memory,
+            "phi" | "get" | "set" | "undef" => Extension::Ssa,
+            "speculate" | "commit" | "guard" => Extension::Speculate,
+            _ => return,
+        };
+        self.add(extension, at);
+    }
+
+    fn ty(&mut self, ty: &AbstractType, at: Option<&Position>) {
+        match ty {
+            AbstractType::Primitive(t) if t == "float" => self.add(Extension::Float, at),
+            AbstractType::Primitive(t) if t == "char" => self.add(Extension::Char, at),
+            AbstractType::Parameterized(t, ty) if t == "ptr" => {
+                self.add(Extension::Memory, at);
+                self.ty(ty, at);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The error from [`Extensions::check`] for an extension which is used by a program but not supported by a tool
+#[derive(Error, Debug)]
+pub struct UnsupportedExtension {
+    /// The unsupported extension
+    pub extension: Extension,
+    /// Where the extension is first used, if that is known
+    pub pos: Option<Position>,
+}
+
+impl Display for UnsupportedExtension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(p) = &self.pos {
+            write!(f, "Line {}, Column {}: ", p.pos.row, p.pos.col)?;
+        }
+        write!(f, "The {} extension is not supported", self.extension)
+    }
+}
+
+impl Program {
+    /// The extensions of Bril which this program uses, with the position of the first use of each
+    #[must_use]
+    pub fn extensions(&self) -> Extensions {
+        let mut extensions = Extensions::default();
+        #[cfg(feature = "import")]
+        if !self.imports.is_empty() {
+            extensions.add(Extension::Import, None);
+        }
+        for Function {
+            args,
+            instrs,
+            return_type,
+            #[cfg(feature = "position")]
+            pos,
+            ..
+        } in &self.functions
+        {
+            extensions.position(pos.as_ref());
+            for a in args {
+                extensions.ty(&a.arg_type.clone().into(), pos.as_ref());
+            }
+            if let Some(t) = return_type {
+                extensions.ty(&t.clone().into(), pos.as_ref());
+            }
+            for code in instrs {
+                match code {
+                    Code::Label {
+                        #[cfg(feature = "position")]
+                        pos,
+                        ..
+                    } => extensions.position(pos.as_ref()),
+                    Code::Instruction(i) => extensions.instruction(i),
+                }
+            }
+        }
+        extensions
+    }
+}
+
+impl AbstractProgram {
+    /// The extensions of Bril which this program uses, with the position of the first use of each
+    ///
+    /// Unlike [`Program::extensions`], this also finds the operations and types of extensions whose feature flags are not enabled.
+    #[must_use]
+    pub fn extensions(&self) -> Extensions {
+        let mut extensions = Extensions::default();
+        #[cfg(feature = "import")]
+        if !self.imports.is_empty() {
+            extensions.add(Extension::Import, None);
+        }
+        for AbstractFunction {
+            args,
+            instrs,
+            return_type,
+            #[cfg(feature = "position")]
+            pos,
+            ..
+        } in &self.functions
+        {
+            extensions.position(pos.as_ref());
+            for a in args {
+                extensions.ty(&a.arg_type, pos.as_ref());
+            }
+            if let Some(t) = return_type {
+                extensions.ty(t, pos.as_ref());
+            }
+            for code in instrs {
+                match code {
+                    AbstractCode::Label {
+                        #[cfg(feature = "position")]
+                        pos,
+                        ..
+                    } => extensions.position(pos.as_ref()),
+                    AbstractCode::Instruction(i) => extensions.abstract_instruction(i),
+                }
+            }
+        }
+        extensions
+    }
+}
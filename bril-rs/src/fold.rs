@@ -0,0 +1,109 @@
+use crate::{Literal, Type, ValueOps};
+
+/// Evaluates the value operation `op` on constant `args`, producing a value of type `op_type`
+///
+/// This follows the semantics of the reference interpreter: integer arithmetic wraps on overflow and integer constants are promoted to floats when `op_type` is float.
+/// Returns [`None`] if `op` can't be evaluated at compile time(like `call` or `load`), if the arguments are not constants of the expected types, or if evaluating `op` would be a runtime error like dividing by zero.
+#[must_use]
+pub fn fold(op: &ValueOps, op_type: &Type, args: &[&Literal]) -> Option<Literal> {
+    use Literal::{Bool, Int};
+    Some(match (op, args) {
+        (ValueOps::Add, [Int(a), Int(b)]) => Int(a.wrapping_add(*b)),
+        (ValueOps::Sub, [Int(a), Int(b)]) => Int(a.wrapping_sub(*b)),
+        (ValueOps::Mul, [Int(a), Int(b)]) => Int(a.wrapping_mul(*b)),
+        (ValueOps::Div, [Int(_), Int(0)]) => return None,
+        (ValueOps::Div, [Int(a), Int(b)]) => Int(a.wrapping_div(*b)),
+        (ValueOps::Eq, [Int(a), Int(b)]) => Bool(a == b),
+        (ValueOps::Lt, [Int(a), Int(b)]) => Bool(a < b),
+        (ValueOps::Gt, [Int(a), Int(b)]) => Bool(a > b),
+        (ValueOps::Le, [Int(a), Int(b)]) => Bool(a <= b),
+        (ValueOps::Ge, [Int(a), Int(b)]) => Bool(a >= b),
+        (ValueOps::Not, [Bool(a)]) => Bool(!a),
+        (ValueOps::And, [Bool(a), Bool(b)]) => Bool(*a && *b),
+        (ValueOps::Or, [Bool(a), Bool(b)]) => Bool(*a || *b),
+        (ValueOps::Id, [a]) => return cast(a, op_type),
+        #[cfg(feature = "float")]
+        (
+            ValueOps::Fadd
+            | ValueOps::Fsub
+            | ValueOps::Fmul
+            | ValueOps::Fdiv
+            | ValueOps::Feq
+            | ValueOps::Flt
+            | ValueOps::Fgt
+            | ValueOps::Fle
+            | ValueOps::Fge,
+            [a, b],
+        ) => return fold_float(op, as_float(a)?, as_float(b)?),
+        #[cfg(feature = "char")]
+        (ValueOps::Ceq, [Literal::Char(a), Literal::Char(b)]) => Bool(a == b),
+        #[cfg(feature = "char")]
+        (ValueOps::Clt, [Literal::Char(a), Literal::Char(b)]) => Bool(a < b),
+        #[cfg(feature = "char")]
+        (ValueOps::Cgt, [Literal::Char(a), Literal::Char(b)]) => Bool(a > b),
+        #[cfg(feature = "char")]
+        (ValueOps::Cle, [Literal::Char(a), Literal::Char(b)]) => Bool(a <= b),
+        #[cfg(feature = "char")]
+        (ValueOps::Cge, [Literal::Char(a), Literal::Char(b)]) => Bool(a >= b),
+        #[cfg(feature = "char")]
+        (ValueOps::Char2int, [Literal::Char(c)]) => Int(u32::from(*c).into()),
+        #[cfg(feature = "char")]
+        (ValueOps::Int2char, [Int(i)]) => {
+            Literal::Char(u32::try_from(*i).ok().and_then(char::from_u32)?)
+        }
+        _ => return None,
+    })
+}
+
+/// Converts a literal to the representation it would have at runtime as a value of type `t`
+///
+/// The only conversion allowed is from an integer literal to a float. Returns [`None`] if `lit` is not a valid literal for `t`.
+#[must_use]
+pub fn cast(lit: &Literal, t: &Type) -> Option<Literal> {
+    match (lit, t) {
+        (Literal::Int(_), Type::Int) | (Literal::Bool(_), Type::Bool) => Some(lit.clone()),
+        #[cfg(feature = "float")]
+        (Literal::Float(_), Type::Float) => Some(lit.clone()),
+        #[cfg(feature = "float")]
+        #[expect(
+            clippy::cast_precision_loss,
+            reason = "This mirrors how the interpreters promote integer literals to floats"
+        )]
+        (Literal::Int(i), Type::Float) => Some(Literal::Float(*i as f64)),
+        #[cfg(feature = "char")]
+        (Literal::Char(_), Type::Char) => Some(lit.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "float")]
+const fn as_float(lit: &Literal) -> Option<f64> {
+    match lit {
+        Literal::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "float")]
+#[expect(clippy::float_cmp, reason = "Bril float comparisons are exact")]
+#[cfg_attr(
+    not(feature = "other"),
+    expect(
+        clippy::trivially_copy_pass_by_ref,
+        reason = "Operations are only `Copy` without the `other` feature"
+    )
+)]
+fn fold_float(op: &ValueOps, a: f64, b: f64) -> Option<Literal> {
+    Some(match op {
+        ValueOps::Fadd => Literal::Float(a + b),
+        ValueOps::Fsub => Literal::Float(a - b),
+        ValueOps::Fmul => Literal::Float(a * b),
+        ValueOps::Fdiv => Literal::Float(a / b),
+        ValueOps::Feq => Literal::Bool(a == b),
+        ValueOps::Flt => Literal::Bool(a < b),
+        ValueOps::Fgt => Literal::Bool(a > b),
+        ValueOps::Fle => Literal::Bool(a <= b),
+        ValueOps::Fge => Literal::Bool(a >= b),
+        _ => return None,
+    })
+}
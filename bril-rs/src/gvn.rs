@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::{
+    cfg::{Cfg, CfgError},
+    dataflow::{Expression, Location},
+    dom::Dominators,
+    ssa::is_ssa,
+    Instruction, Program, Type, ValueOps,
+};
+
+/// Errors from running [`gvn`] over a [Program]
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum GvnError {
+    /// A function assigns a variable more than once, so it isn't in SSA form
+    #[error("@{0} is not in SSA form")]
+    NotSsa(String),
+    /// The labels of a function don't resolve
+    #[error("@{0}: {1}")]
+    Cfg(String, CfgError),
+}
+
+/// Runs [`gvn_cfg`] over every function of `program`, which must be in SSA form like the output of [`crate::ssa::to_ssa`]
+/// # Errors
+/// Returns an error if a function is not in SSA form or its labels don't resolve, in which case `program` is unchanged
+pub fn gvn(program: &mut Program) -> Result<(), GvnError> {
+    let mut cfgs = Vec::with_capacity(program.functions.len());
+    for f in &program.functions {
+        if !is_ssa(f) {
+            return Err(GvnError::NotSsa(f.name.clone()));
+        }
+        cfgs.push(Cfg::try_from(f.clone()).map_err(|e| GvnError::Cfg(f.name.clone(), e))?);
+    }
+    for (f, mut cfg) in program.functions.iter_mut().zip(cfgs) {
+        gvn_cfg(&mut cfg);
+        *f = cfg.into();
+    }
+    Ok(())
+}
+
+// What a variable was computed from, in terms of the variables holding the value numbers of its arguments
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    // Constants are keyed by how they are printed, since floats can't be hashed
+    Const(Type, String),
+    Expr(Type, Expression),
+    Phi(Vec<String>, Vec<String>),
+}
+
+// How the value number of a variable is found
+enum Number {
+    // The variable holds the same value as another one
+    Copy(String),
+    // The variable holds the same value as any other variable computed the same way
+    Computed(Key),
+}
+
+/// Dominator-based global value numbering, from "Value Numbering" by Briggs, Cooper, and Simpson
+///
+/// Blocks are visited in a pre-order walk of the dominator tree, so a computation is replaced by the earlier variable holding the same value whenever that variable's definition dominates it.
+/// Every argument is rewritten to the variable holding its value number, the arguments of commutative operations are sorted, and a `phi` whose arguments are all the same, or which repeats another `phi` of its block, takes the value of its arguments.
+/// Instructions whose values are held by an earlier variable are then removed, since none of their uses are left.
+/// `call`, `alloc`, `load`, and other operations which can give different results each time they run are never merged, as described by [`Expression::from_instruction`].
+/// `cfg` must be in SSA form, since each variable is given a single value number.
+///
+/// Returns whether anything changed.
+pub fn gvn_cfg(cfg: &mut Cfg) -> bool {
+    let dom = Dominators::new(cfg);
+    let mut numbering: HashMap<String, String> = HashMap::new();
+    let mut table: HashMap<Key, String> = HashMap::new();
+    let mut redundant: HashSet<Location> = HashSet::new();
+    let mut changed = false;
+
+    // Each block is entered, then its children are visited, then it is left, which removes the expressions it added from scope
+    let mut stack = vec![(Cfg::ENTRY, true)];
+    let mut scopes: Vec<Vec<Key>> = Vec::new();
+    while let Some((block, enter)) = stack.pop() {
+        if !enter {
+            for key in scopes.pop().into_iter().flatten() {
+                table.remove(&key);
+            }
+            continue;
+        }
+        let mut scope = Vec::new();
+        for (index, instr) in cfg.blocks[block].instrs.iter_mut().enumerate() {
+            if !is_phi(instr) {
+                for a in instr.args_mut() {
+                    if let Some(n) = numbering.get(a.as_str()) {
+                        changed |= a != n;
+                        a.clone_from(n);
+                    }
+                }
+            }
+            let Some(dest) = instr.dest().map(str::to_string) else {
+                continue;
+            };
+            match key(instr, &dest) {
+                Some(Number::Copy(same)) => {
+                    numbering.insert(dest, same);
+                    redundant.insert(Location { block, index });
+                }
+                Some(Number::Computed(key)) => {
+                    if let Some(holder) = table.get(&key) {
+                        numbering.insert(dest, holder.clone());
+                        redundant.insert(Location { block, index });
+                    } else {
+                        table.insert(key.clone(), dest);
+                        scope.push(key);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // The arguments of `phi`s which come from this block hold the values at its end
+        let name = cfg.blocks[block].name.clone();
+        for s in cfg.blocks[block].successors.clone() {
+            for instr in cfg.blocks[s].instrs.iter_mut().filter(|i| is_phi(i)) {
+                let labels = instr.labels().to_vec();
+                for (a, l) in instr.args_mut().iter_mut().zip(labels) {
+                    if let Some(n) = numbering.get(a.as_str()).filter(|_| l == name) {
+                        changed |= a != n;
+                        a.clone_from(n);
+                    }
+                }
+            }
+        }
+
+        scopes.push(scope);
+        stack.push((block, false));
+        stack.extend(dom.children(block).iter().rev().map(|c| (*c, true)));
+    }
+
+    for (b, block) in cfg.blocks.iter_mut().enumerate() {
+        let mut index = 0;
+        block.instrs.retain(|_| {
+            index += 1;
+            !redundant.contains(&Location {
+                block: b,
+                index: index - 1,
+            })
+        });
+    }
+    changed || !redundant.is_empty()
+}
+
+// How to number the value that `instr` computes, which is a copy when it is an `id` or a `phi` with only one distinct argument
+fn key(instr: &Instruction, dest: &str) -> Option<Number> {
+    match instr {
+        Instruction::Constant {
+            const_type, value, ..
+        } => Some(Number::Computed(Key::Const(
+            const_type.clone(),
+            value.to_string(),
+        ))),
+        Instruction::Value {
+            op: ValueOps::Id,
+            args,
+            ..
+        } => Some(Number::Copy(args[0].clone())),
+        Instruction::Value {
+            op: ValueOps::Phi,
+            args,
+            labels,
+            ..
+        } => {
+            // A `phi` which only ever reads one variable besides itself is a copy of it
+            let first = args.iter().find(|a| *a != dest)?;
+            if args.iter().all(|a| a == first || a == dest) {
+                Some(Number::Copy(first.clone()))
+            } else {
+                Some(Number::Computed(Key::Phi(args.clone(), labels.clone())))
+            }
+        }
+        Instruction::Value { op_type, .. } => Expression::from_instruction(instr)
+            .map(|e| Number::Computed(Key::Expr(op_type.clone(), e))),
+        Instruction::Effect { .. } => None,
+    }
+}
+
+const fn is_phi(instr: &Instruction) -> bool {
+    matches!(
+        instr,
+        Instruction::Value {
+            op: ValueOps::Phi,
+            ..
+        }
+    )
+}
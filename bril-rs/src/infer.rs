@@ -0,0 +1,557 @@
+use std::{collections::HashMap, fmt::Display};
+
+use thiserror::Error;
+
+use crate::{
+    conversion::{ConversionError, PositionalConversionError},
+    AbstractCode, AbstractFunction, AbstractInstruction, AbstractProgram, EffectOps, Literal,
+    Position, Program, Type, ValueOps,
+};
+
+/// The errors from inferring the types of an [`AbstractProgram`] with [`infer_types`]
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum InferError {
+    /// A type annotation or operation in the program was not valid Bril
+    #[error("{0}")]
+    Conversion(#[from] ConversionError),
+
+    /// Conflicting types for {0}: {1}, where {1} names both types
+    #[error("Conflicting types for {0}: {1}")]
+    Conflict(String, String),
+
+    /// Could not infer a type for {0}
+    #[error("Could not infer a type for {0}")]
+    Unconstrained(String),
+}
+
+impl InferError {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn add_pos(self, pos_var: Option<Position>) -> PositionalInferError {
+        PositionalInferError {
+            e: self,
+            pos: pos_var,
+        }
+    }
+}
+
+/// Wraps [`InferError`] to optionally provide source code positions if they are available.
+#[derive(Error, Debug)]
+pub struct PositionalInferError {
+    #[doc(hidden)]
+    pub e: InferError,
+    #[doc(hidden)]
+    pub pos: Option<Position>,
+}
+
+impl From<PositionalConversionError> for PositionalInferError {
+    fn from(PositionalConversionError { e, pos }: PositionalConversionError) -> Self {
+        InferError::Conversion(e).add_pos(pos)
+    }
+}
+
+impl Display for PositionalInferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "position")]
+            Self { e, pos: Some(pos) } => {
+                write!(f, "Line {}, Column {}: {e}", pos.pos.row, pos.pos.col)
+            }
+            #[cfg(not(feature = "position"))]
+            Self { e: _, pos: Some(_) } => {
+                unreachable!()
+            }
+            Self { e, pos: None } => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Fills in the missing types of every value operation and constant in `program`, producing a [Program]
+///
+/// Each variable has a single type throughout its function, which is found by unifying the constraints from every instruction that defines or uses it, along with any annotations that are already present.
+/// Function signatures must be fully annotated and are used to type calls.
+/// Pointer types are found from how `alloc`, `load`, `store`, and `ptradd` are used.
+/// An integer literal defaults to `int` but can also be used as a `float`, as in the text format.
+/// # Errors
+/// Will return an error if two constraints on a variable conflict, if a variable's type is not determined by the program, or if the program is not otherwise valid Bril
+pub fn infer_types(mut program: AbstractProgram) -> Result<Program, PositionalInferError> {
+    let mut signatures = HashMap::new();
+    for f in &program.functions {
+        let args = f
+            .args
+            .iter()
+            .map(|a| Type::try_from(a.arg_type.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| InferError::from(e).add_pos(function_pos(f)))?;
+        let return_type = f
+            .return_type
+            .clone()
+            .map(Type::try_from)
+            .transpose()
+            .map_err(|e| InferError::from(e).add_pos(function_pos(f)))?;
+        signatures.insert(f.name.clone(), (args, return_type));
+    }
+
+    for f in &mut program.functions {
+        let (args, return_type) = &signatures[&f.name];
+        let mut inference = Inference::new(&signatures, return_type.as_ref());
+        for (a, ty) in f.args.iter().zip(args) {
+            inference
+                .expect(&a.name, &Term::from(ty))
+                .map_err(|e| e.add_pos(function_pos(f)))?;
+        }
+        inference.function(f)?;
+    }
+
+    Ok(program.try_into()?)
+}
+
+// A type which may still contain unknown parts, each of which is the index of a type variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Var(usize),
+    Base(Type),
+    #[cfg(feature = "memory")]
+    Ptr(Box<Self>),
+}
+
+impl From<&Type> for Term {
+    fn from(ty: &Type) -> Self {
+        match ty {
+            #[cfg(feature = "memory")]
+            Type::Pointer(t) => Self::Ptr(Box::new(t.as_ref().into())),
+            t => Self::Base(t.clone()),
+        }
+    }
+}
+
+// The signatures of the functions in the program
+type Signatures = HashMap<String, (Vec<Type>, Option<Type>)>;
+
+struct Inference<'a> {
+    signatures: &'a Signatures,
+    return_type: Option<&'a Type>,
+    // What each type variable has been unified with so far, if anything
+    bindings: Vec<Option<Term>>,
+    // The type variable standing for the type of each variable in the function
+    vars: HashMap<String, usize>,
+}
+
+impl<'a> Inference<'a> {
+    fn new(signatures: &'a Signatures, return_type: Option<&'a Type>) -> Self {
+        Self {
+            signatures,
+            return_type,
+            bindings: Vec::new(),
+            vars: HashMap::new(),
+        }
+    }
+
+    #[cfg(feature = "memory")]
+    fn fresh(&mut self) -> Term {
+        self.bindings.push(None);
+        Term::Var(self.bindings.len() - 1)
+    }
+
+    fn var(&mut self, name: &str) -> Term {
+        if let Some(v) = self.vars.get(name) {
+            return Term::Var(*v);
+        }
+        let v = self.bindings.len();
+        self.bindings.push(None);
+        self.vars.insert(name.to_string(), v);
+        Term::Var(v)
+    }
+
+    // Follows the bindings of `t` until it is either a type constructor or an unbound type variable
+    fn shallow(&self, t: &Term) -> Term {
+        let mut t = t.clone();
+        while let Term::Var(v) = t {
+            match &self.bindings[v] {
+                Some(b) => t = b.clone(),
+                None => break,
+            }
+        }
+        t
+    }
+
+    // The fully known type of `t`, if there is one
+    fn resolve(&self, t: &Term) -> Option<Type> {
+        match self.shallow(t) {
+            Term::Var(_) => None,
+            Term::Base(ty) => Some(ty),
+            #[cfg(feature = "memory")]
+            Term::Ptr(t) => Some(Type::Pointer(Box::new(self.resolve(&t)?))),
+        }
+    }
+
+    // How `t` is shown in an error, with `?` for the unknown parts
+    fn show(&self, t: &Term) -> String {
+        match self.shallow(t) {
+            Term::Var(_) => "?".to_string(),
+            Term::Base(ty) => ty.to_string(),
+            #[cfg(feature = "memory")]
+            Term::Ptr(t) => format!("ptr<{}>", self.show(&t)),
+        }
+    }
+
+    fn occurs(&self, v: usize, t: &Term) -> bool {
+        match self.shallow(t) {
+            Term::Var(w) => v == w,
+            Term::Base(_) => false,
+            #[cfg(feature = "memory")]
+            Term::Ptr(t) => self.occurs(v, &t),
+        }
+    }
+
+    // Makes `a` and `b` the same type, binding the type variables in `trail` so that they can be undone on failure
+    fn unify_with(&mut self, a: &Term, b: &Term, trail: &mut Vec<usize>) -> bool {
+        match (self.shallow(a), self.shallow(b)) {
+            (Term::Var(v), Term::Var(w)) if v == w => true,
+            (Term::Var(v), t) | (t, Term::Var(v)) => {
+                if self.occurs(v, &t) {
+                    return false;
+                }
+                self.bindings[v] = Some(t);
+                trail.push(v);
+                true
+            }
+            (Term::Base(x), Term::Base(y)) => x == y,
+            #[cfg(feature = "memory")]
+            (Term::Ptr(x), Term::Ptr(y)) => self.unify_with(&x, &y, trail),
+            #[cfg(feature = "memory")]
+            _ => false,
+        }
+    }
+
+    fn unify(&mut self, a: &Term, b: &Term) -> bool {
+        let mut trail = Vec::new();
+        let unified = self.unify_with(a, b, &mut trail);
+        if !unified {
+            for v in trail {
+                self.bindings[v] = None;
+            }
+        }
+        unified
+    }
+
+    // Constrains the variable `name` to have the type `t`
+    fn expect(&mut self, name: &str, t: &Term) -> Result<(), InferError> {
+        let v = self.var(name);
+        if self.unify(&v, t) {
+            Ok(())
+        } else {
+            Err(InferError::Conflict(
+                name.to_string(),
+                format!("{} and {}", self.show(&v), self.show(t)),
+            ))
+        }
+    }
+
+    fn expect_all(&mut self, names: &[String], t: &Term) -> Result<(), InferError> {
+        names.iter().try_for_each(|n| self.expect(n, t))
+    }
+
+    // Constrains the variables `a` and `b` to have the same type
+    fn same(&mut self, a: &str, b: &str) -> Result<(), InferError> {
+        let t = self.var(b);
+        self.expect(a, &t)
+    }
+
+    #[cfg(feature = "memory")]
+    fn ptr(t: Term) -> Term {
+        Term::Ptr(Box::new(t))
+    }
+
+    fn call(&mut self, args: &[String], funcs: &[String]) -> Result<Option<Type>, InferError> {
+        let Some((arg_types, return_type)) = funcs.first().and_then(|f| self.signatures.get(f))
+        else {
+            return Ok(None);
+        };
+        for (a, ty) in args.iter().zip(arg_types) {
+            self.expect(a, &ty.into())?;
+        }
+        Ok(return_type.clone())
+    }
+
+    #[cfg_attr(
+        not(feature = "other"),
+        expect(
+            clippy::trivially_copy_pass_by_ref,
+            reason = "Operations are only `Copy` without the `other` feature"
+        )
+    )]
+    fn value(
+        &mut self,
+        op: &ValueOps,
+        dest: &str,
+        args: &[String],
+        funcs: &[String],
+    ) -> Result<(), InferError> {
+        let int = Term::Base(Type::Int);
+        let bool = Term::Base(Type::Bool);
+        #[cfg(feature = "float")]
+        let float = Term::Base(Type::Float);
+        #[cfg(feature = "char")]
+        let char = Term::Base(Type::Char);
+        match op {
+            ValueOps::Add | ValueOps::Sub | ValueOps::Mul | ValueOps::Div => {
+                self.expect_all(args, &int)?;
+                self.expect(dest, &int)
+            }
+            ValueOps::Eq | ValueOps::Lt | ValueOps::Gt | ValueOps::Le | ValueOps::Ge => {
+                self.expect_all(args, &int)?;
+                self.expect(dest, &bool)
+            }
+            ValueOps::Not | ValueOps::And | ValueOps::Or => {
+                self.expect_all(args, &bool)?;
+                self.expect(dest, &bool)
+            }
+            ValueOps::Id => args.first().map_or(Ok(()), |a| self.same(dest, a)),
+            ValueOps::Call => self
+                .call(args, funcs)?
+                .map_or(Ok(()), |ty| self.expect(dest, &(&ty).into())),
+            #[cfg(feature = "ssa")]
+            ValueOps::Phi => args.iter().try_for_each(|a| self.same(dest, a)),
+            #[cfg(feature = "ssa2")]
+            ValueOps::Get | ValueOps::Undef => Ok(()),
+            #[cfg(feature = "float")]
+            ValueOps::Fadd | ValueOps::Fsub | ValueOps::Fmul | ValueOps::Fdiv => {
+                self.expect_all(args, &float)?;
+                self.expect(dest, &float)
+            }
+            #[cfg(feature = "float")]
+            ValueOps::Feq | ValueOps::Flt | ValueOps::Fgt | ValueOps::Fle | ValueOps::Fge => {
+                self.expect_all(args, &float)?;
+                self.expect(dest, &bool)
+            }
+            #[cfg(feature = "char")]
+            ValueOps::Ceq | ValueOps::Clt | ValueOps::Cgt | ValueOps::Cle | ValueOps::Cge => {
+                self.expect_all(args, &char)?;
+                self.expect(dest, &bool)
+            }
+            #[cfg(feature = "char")]
+            ValueOps::Char2int => {
+                self.expect_all(args, &char)?;
+                self.expect(dest, &int)
+            }
+            #[cfg(feature = "char")]
+            ValueOps::Int2char => {
+                self.expect_all(args, &int)?;
+                self.expect(dest, &char)
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::Alloc => {
+                self.expect_all(args, &int)?;
+                let pointee = self.fresh();
+                self.expect(dest, &Self::ptr(pointee))
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::Load => {
+                let pointee = self.var(dest);
+                self.expect_all(args, &Self::ptr(pointee))
+            }
+            #[cfg(feature = "memory")]
+            ValueOps::PtrAdd => {
+                let pointee = self.fresh();
+                let ptr = Self::ptr(pointee);
+                if let Some(p) = args.first() {
+                    self.expect(p, &ptr)?;
+                }
+                if let Some(offset) = args.get(1) {
+                    self.expect(offset, &int)?;
+                }
+                self.expect(dest, &ptr)
+            }
+            // Nothing is known about operations from other extensions, so they need to be annotated
+            #[cfg(feature = "other")]
+            ValueOps::Other(_) => Ok(()),
+        }
+    }
+
+    #[cfg_attr(
+        not(feature = "other"),
+        expect(
+            clippy::trivially_copy_pass_by_ref,
+            reason = "Operations are only `Copy` without the `other` feature"
+        )
+    )]
+    fn effect(
+        &mut self,
+        op: &EffectOps,
+        args: &[String],
+        funcs: &[String],
+    ) -> Result<(), InferError> {
+        match op {
+            EffectOps::Jump | EffectOps::Print | EffectOps::Nop => Ok(()),
+            #[cfg(feature = "speculate")]
+            EffectOps::Speculate | EffectOps::Commit => Ok(()),
+            EffectOps::Branch => self.expect_all(args, &Term::Base(Type::Bool)),
+            #[cfg(feature = "speculate")]
+            EffectOps::Guard => self.expect_all(args, &Term::Base(Type::Bool)),
+            EffectOps::Call => self.call(args, funcs).map(|_| ()),
+            EffectOps::Return => match (args.first(), self.return_type) {
+                (Some(a), Some(ty)) => self.expect(a, &ty.into()),
+                _ => Ok(()),
+            },
+            #[cfg(feature = "memory")]
+            EffectOps::Store => match args {
+                [p, v, ..] => {
+                    let pointee = self.var(v);
+                    self.expect(p, &Self::ptr(pointee))
+                }
+                _ => Ok(()),
+            },
+            #[cfg(feature = "memory")]
+            EffectOps::Free => {
+                let pointee = self.fresh();
+                self.expect_all(args, &Self::ptr(pointee))
+            }
+            #[cfg(feature = "ssa2")]
+            EffectOps::Set => match args {
+                [shadow, v, ..] => self.same(shadow, v),
+                _ => Ok(()),
+            },
+            #[cfg(feature = "other")]
+            EffectOps::Other(_) => Ok(()),
+        }
+    }
+
+    fn instruction(&mut self, instr: &AbstractInstruction) -> Result<(), InferError> {
+        match instr {
+            AbstractInstruction::Constant {
+                dest,
+                const_type,
+                value,
+                ..
+            } => {
+                if let Some(ty) = const_type {
+                    self.expect(dest, &(&Type::try_from(ty.clone())?).into())?;
+                }
+                // Integer literals are checked once the rest of the function is known, since they may also be floats
+                if matches!(value, Literal::Int(_)) {
+                    Ok(())
+                } else {
+                    self.expect(dest, &Term::Base(value.get_type()))
+                }
+            }
+            AbstractInstruction::Value {
+                args,
+                dest,
+                funcs,
+                op,
+                op_type,
+                ..
+            } => {
+                if let Some(ty) = op_type {
+                    self.expect(dest, &(&Type::try_from(ty.clone())?).into())?;
+                }
+                self.value(&op.parse()?, dest, args, funcs)
+            }
+            AbstractInstruction::Effect {
+                args, funcs, op, ..
+            } => self.effect(&op.parse()?, args, funcs),
+        }
+    }
+
+    fn int_literal(&mut self, dest: &str) -> Result<(), InferError> {
+        let v = self.var(dest);
+        match self.shallow(&v) {
+            #[cfg(feature = "float")]
+            Term::Base(Type::Float) => Ok(()),
+            _ => self.expect(dest, &Term::Base(Type::Int)),
+        }
+    }
+
+    // Solves the constraints of `function` and then writes its types back into it
+    fn function(&mut self, function: &mut AbstractFunction) -> Result<(), PositionalInferError> {
+        for code in &function.instrs {
+            if let AbstractCode::Instruction(i) = code {
+                self.instruction(i).map_err(|e| e.add_pos(instr_pos(i)))?;
+            }
+        }
+        for code in &function.instrs {
+            if let AbstractCode::Instruction(
+                i @ AbstractInstruction::Constant {
+                    dest,
+                    value: Literal::Int(_),
+                    ..
+                },
+            ) = code
+            {
+                self.int_literal(dest)
+                    .map_err(|e| e.add_pos(instr_pos(i)))?;
+            }
+        }
+
+        for code in &mut function.instrs {
+            let AbstractCode::Instruction(i) = code else {
+                continue;
+            };
+            let position = instr_pos(i);
+            let (slot, ty) = match i {
+                AbstractInstruction::Constant {
+                    dest,
+                    const_type,
+                    #[cfg(feature = "float")]
+                    value,
+                    ..
+                } => {
+                    let ty = self.type_of(dest).map_err(|e| e.add_pos(position))?;
+                    #[cfg(feature = "float")]
+                    if let (Type::Float, Literal::Int(n)) = (&ty, &*value) {
+                        #[expect(
+                            clippy::cast_precision_loss,
+                            reason = "This is how an integer literal is read as a float"
+                        )]
+                        let f = *n as f64;
+                        *value = Literal::Float(f);
+                    }
+                    (const_type, ty)
+                }
+                AbstractInstruction::Value { dest, op_type, .. } => {
+                    let ty = self.type_of(dest).map_err(|e| e.add_pos(position))?;
+                    (op_type, ty)
+                }
+                AbstractInstruction::Effect { .. } => continue,
+            };
+            *slot = Some(ty.into());
+        }
+        Ok(())
+    }
+
+    fn type_of(&mut self, dest: &str) -> Result<Type, InferError> {
+        let v = self.var(dest);
+        self.resolve(&v)
+            .ok_or_else(|| InferError::Unconstrained(dest.to_string()))
+    }
+}
+
+#[cfg(feature = "position")]
+fn instr_pos(instr: &AbstractInstruction) -> Option<Position> {
+    match instr {
+        AbstractInstruction::Constant { pos, .. }
+        | AbstractInstruction::Value { pos, .. }
+        | AbstractInstruction::Effect { pos, .. } => pos.clone(),
+    }
+}
+
+#[cfg(not(feature = "position"))]
+const fn instr_pos(_: &AbstractInstruction) -> Option<Position> {
+    None
+}
+
+#[cfg(feature = "position")]
+fn function_pos(function: &AbstractFunction) -> Option<Position> {
+    function.pos.clone()
+}
+
+#[cfg(not(feature = "position"))]
+const fn function_pos(_: &AbstractFunction) -> Option<Position> {
+    None
+}
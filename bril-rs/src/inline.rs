@@ -0,0 +1,293 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    callgraph::CallGraph,
+    cfg::fresh_name,
+    visit::{Visit, VisitMut},
+    Code, EffectOps, Function, Instruction, Program, Type, ValueOps,
+};
+
+/// The budget for [`inline`]
+///
+/// Sizes count instructions, not labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InlineOptions {
+    /// Only functions with at most this many instructions are inlined
+    pub max_callee_size: usize,
+    /// Calls stop being inlined into a function once it has grown to this many instructions
+    pub max_caller_size: usize,
+    /// How many copies of a recursive function can be nested inside each other at a call site, where 0 never inlines recursive functions
+    pub max_recursion_depth: usize,
+}
+
+impl Default for InlineOptions {
+    fn default() -> Self {
+        Self {
+            max_callee_size: 50,
+            max_caller_size: 1000,
+            max_recursion_depth: 1,
+        }
+    }
+}
+
+/// Inlines calls to the functions of `program` into their callers
+///
+/// Callees are always expanded from their original bodies, and the calls in the inlined code are inlined in turn, so the depth of recursive functions is tracked at each call site.
+/// The locals and labels of an inlined callee are given a fresh prefix, its arguments are copied with `id` unless they are never reassigned, and each `ret` becomes a copy into the destination of the call followed by a jump to the code after it.
+/// Calls to functions which are not defined in `program` are left alone, and functions which are no longer called are kept, so follow up with [`Program::remove_unreachable_functions`] to drop them.
+pub fn inline(program: &mut Program, options: InlineOptions) {
+    let graph = CallGraph::new(program);
+    let original = program.functions.clone();
+    for (f, function) in program.functions.iter_mut().enumerate() {
+        function.instrs = inline_function(&original, &graph, f, options);
+    }
+}
+
+fn size(instrs: &[Code]) -> usize {
+    instrs
+        .iter()
+        .filter(|c| matches!(c, Code::Instruction(_)))
+        .count()
+}
+
+// Collects every variable and label which appears in the code it visits
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Visit for Names {
+    fn visit_label(&mut self, label: &str) {
+        self.0.insert(label.to_string());
+    }
+
+    fn visit_dest(&mut self, dest: &str) {
+        self.0.insert(dest.to_string());
+    }
+
+    fn visit_arg(&mut self, arg: &str) {
+        self.0.insert(arg.to_string());
+    }
+
+    fn visit_target(&mut self, target: &str) {
+        self.0.insert(target.to_string());
+    }
+}
+
+// Every variable and label which appears in `instrs`, which inlined names must not collide with
+fn names(instrs: &[Code]) -> HashSet<String> {
+    let mut names = Names::default();
+    for code in instrs {
+        names.visit_code(code);
+    }
+    names.0
+}
+
+// Renames the variables of an inlined callee with `var` and puts `prefix` in front of its labels
+struct Rename<'a, F> {
+    prefix: &'a str,
+    var: F,
+}
+
+impl<F: Fn(&str) -> String> VisitMut for Rename<'_, F> {
+    fn visit_label_mut(&mut self, label: &mut String) {
+        *label = format!("{}{label}", self.prefix);
+    }
+
+    fn visit_dest_mut(&mut self, dest: &mut String) {
+        *dest = (self.var)(dest);
+    }
+
+    fn visit_arg_mut(&mut self, arg: &mut String) {
+        *arg = (self.var)(arg);
+    }
+
+    fn visit_target_mut(&mut self, target: &mut String) {
+        *target = format!("{}{target}", self.prefix);
+    }
+}
+
+fn inline_function(
+    functions: &[Function],
+    graph: &CallGraph,
+    f: usize,
+    options: InlineOptions,
+) -> Vec<Code> {
+    let function = &functions[f];
+    let mut taken = names(&function.instrs);
+    taken.extend(function.args.iter().map(|a| a.name.clone()));
+    let mut caller_size = size(&function.instrs);
+    // Each instruction carries how many recursive functions it has been inlined through
+    let mut worklist: VecDeque<(Code, usize)> =
+        function.instrs.iter().cloned().map(|c| (c, 0)).collect();
+    let mut instrs = Vec::new();
+    while let Some((code, depth)) = worklist.pop_front() {
+        let callee = match &code {
+            Code::Instruction(
+                Instruction::Value {
+                    op: ValueOps::Call,
+                    funcs,
+                    ..
+                }
+                | Instruction::Effect {
+                    op: EffectOps::Call,
+                    funcs,
+                    ..
+                },
+            ) => funcs.first().and_then(|name| graph.index(name)),
+            _ => None,
+        };
+        let Some(callee) = callee.filter(|g| {
+            size(&functions[*g].instrs) <= options.max_callee_size
+                && (!graph.is_recursive(*g) || depth < options.max_recursion_depth)
+        }) else {
+            instrs.push(code);
+            continue;
+        };
+        let Code::Instruction(call) = &code else {
+            unreachable!()
+        };
+        // The call is replaced by the body, along with its copies of the arguments and jumps out of each `ret`
+        let Some(body) = expand(call, &functions[callee], &taken)
+            .filter(|body| caller_size - 1 + size(body) <= options.max_caller_size)
+        else {
+            instrs.push(code);
+            continue;
+        };
+        let depth = if graph.is_recursive(callee) {
+            depth + 1
+        } else {
+            depth
+        };
+        caller_size = caller_size - 1 + size(&body);
+        taken.extend(names(&body));
+        for c in body.into_iter().rev() {
+            worklist.push_front((c, depth));
+        }
+    }
+    instrs
+}
+
+// The code which replaces `call` with the body of `callee`, or `None` if the call doesn't match the signature of `callee`
+fn expand(call: &Instruction, callee: &Function, taken: &HashSet<String>) -> Option<Vec<Code>> {
+    let (dest, args) = match call {
+        Instruction::Value {
+            dest,
+            op_type,
+            args,
+            ..
+        } => (Some((dest, op_type)), args),
+        Instruction::Effect { args, .. } => (None, args),
+        Instruction::Constant { .. } => return None,
+    };
+    if args.len() != callee.args.len() || dest.is_some() != callee.return_type.is_some() {
+        return None;
+    }
+
+    // The label after the inlined code is the prefix itself, which no renamed name can be equal to
+    let end = fresh_name(&format!("{}.", callee.name), |p| {
+        taken
+            .iter()
+            .any(|t| t == p || t.starts_with(&format!("{p}.")))
+    });
+    let prefix = format!("{end}.");
+    let assigned: HashSet<&str> = callee
+        .instrs
+        .iter()
+        .filter_map(|c| match c {
+            Code::Instruction(i) => i.dest(),
+            Code::Label { .. } => None,
+        })
+        .collect();
+    // Arguments which are never reassigned can refer to the variable that was passed in
+    let rename = |var: &str| {
+        callee
+            .args
+            .iter()
+            .zip(args)
+            .find(|(a, _)| a.name == var && !assigned.contains(var))
+            .map_or_else(|| format!("{prefix}{var}"), |(_, arg)| arg.clone())
+    };
+
+    let mut body = Vec::new();
+    for (a, arg) in callee.args.iter().zip(args) {
+        if assigned.contains(a.name.as_str()) {
+            body.push(copy(
+                call,
+                format!("{prefix}{}", a.name),
+                a.arg_type.clone(),
+                arg.clone(),
+            ));
+        }
+    }
+    let mut renaming = Rename {
+        prefix: &prefix,
+        var: &rename,
+    };
+    let mut jumps = false;
+    for (i, code) in callee.instrs.iter().enumerate() {
+        if let Code::Instruction(Instruction::Effect {
+            op: EffectOps::Return,
+            args: ret,
+            ..
+        }) = code
+        {
+            if let (Some((dest, op_type)), Some(ret)) = (dest, ret.first()) {
+                body.push(copy(call, dest.clone(), op_type.clone(), rename(ret)));
+            }
+            if i + 1 < callee.instrs.len() {
+                jumps = true;
+                body.push(jump(call, end.clone()));
+            }
+        } else {
+            let mut code = code.clone();
+            renaming.visit_code_mut(&mut code);
+            body.push(code);
+        }
+    }
+    if jumps {
+        body.push(Code::Label {
+            label: end,
+            #[cfg(feature = "position")]
+            pos: call.get_pos(),
+        });
+    }
+
+    Some(body)
+}
+
+#[cfg_attr(
+    not(feature = "position"),
+    expect(
+        unused_variables,
+        reason = "The position of the call is only copied when there are positions"
+    )
+)]
+fn copy(call: &Instruction, dest: String, op_type: Type, arg: String) -> Code {
+    Code::Instruction(Instruction::Value {
+        args: vec![arg],
+        dest,
+        funcs: Vec::new(),
+        labels: Vec::new(),
+        op: ValueOps::Id,
+        #[cfg(feature = "position")]
+        pos: call.get_pos(),
+        op_type,
+    })
+}
+
+#[cfg_attr(
+    not(feature = "position"),
+    expect(
+        unused_variables,
+        reason = "The position of the call is only copied when there are positions"
+    )
+)]
+fn jump(call: &Instruction, label: String) -> Code {
+    Code::Instruction(Instruction::Effect {
+        args: Vec::new(),
+        funcs: Vec::new(),
+        labels: vec![label],
+        op: EffectOps::Jump,
+        #[cfg(feature = "position")]
+        pos: call.get_pos(),
+    })
+}
@@ -0,0 +1,701 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    ops::{Add, Mul, Sub},
+};
+
+use thiserror::Error;
+
+use crate::{
+    absint::{interpret, AbstractInterpretation, Lattice},
+    cfg::{Cfg, CfgError},
+    dataflow::Location,
+    error::fmt_positional,
+    EffectOps, Function, Instruction, Literal, Position, Type, ValueOps,
+};
+
+/// The integers from `lo` to `hi`, inclusive
+///
+/// Bril integers wrap on overflow, so an operation which might overflow gives [`Interval::TOP`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval {
+    /// The smallest value in the interval
+    pub lo: i64,
+    /// The largest value in the interval
+    pub hi: i64,
+}
+
+impl Interval {
+    /// Every 64-bit integer
+    pub const TOP: Self = Self {
+        lo: i64::MIN,
+        hi: i64::MAX,
+    };
+
+    /// The interval holding only `value`
+    #[must_use]
+    pub const fn constant(value: i64) -> Self {
+        Self {
+            lo: value,
+            hi: value,
+        }
+    }
+
+    /// Whether `value` is in the interval
+    #[must_use]
+    pub const fn contains(self, value: i64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    /// The smallest interval containing both `self` and `other`
+    #[must_use]
+    pub fn join(self, other: Self) -> Self {
+        Self {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    /// The values in both `self` and `other`, or [`None`] if there are none
+    #[must_use]
+    pub fn meet(self, other: Self) -> Option<Self> {
+        Self::checked(self.lo.max(other.lo).into(), self.hi.min(other.hi).into())
+    }
+
+    /// Standard interval widening: each bound of `self` which `other` goes past is pushed to the end of the integers
+    #[must_use]
+    pub const fn widen(self, other: Self) -> Self {
+        Self {
+            lo: if other.lo < self.lo {
+                i64::MIN
+            } else {
+                self.lo
+            },
+            hi: if other.hi > self.hi {
+                i64::MAX
+            } else {
+                self.hi
+            },
+        }
+    }
+
+    /// Standard interval narrowing: each bound of `self` which widening pushed to the end of the integers is replaced by the bound of `other`
+    #[must_use]
+    pub const fn narrow(self, other: Self) -> Self {
+        Self {
+            lo: if self.lo == i64::MIN {
+                other.lo
+            } else {
+                self.lo
+            },
+            hi: if self.hi == i64::MAX {
+                other.hi
+            } else {
+                self.hi
+            },
+        }
+    }
+
+    /// The values of `div` on arguments from `self` and `other`, leaving out division by 0, or [`None`] if `other` only holds 0
+    #[must_use]
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        // Division is monotone in each argument as long as the sign of the divisor is fixed
+        let negative = Self::checked(other.lo.into(), other.hi.min(-1).into());
+        let positive = Self::checked(other.lo.max(1).into(), other.hi.into());
+        negative
+            .into_iter()
+            .chain(positive)
+            .map(|d| Self::corners(self, d, |a, b| a / b))
+            .reduce(Self::join)
+    }
+
+    fn checked(lo: i128, hi: i128) -> Option<Self> {
+        (lo <= hi).then(|| Self::wrapping(lo, hi))
+    }
+
+    fn wrapping(lo: i128, hi: i128) -> Self {
+        match (i64::try_from(lo), i64::try_from(hi)) {
+            (Ok(lo), Ok(hi)) => Self { lo, hi },
+            _ => Self::TOP,
+        }
+    }
+
+    fn corners(a: Self, b: Self, op: impl Fn(i128, i128) -> i128) -> Self {
+        let values = [(a.lo, b.lo), (a.lo, b.hi), (a.hi, b.lo), (a.hi, b.hi)]
+            .map(|(x, y)| op(i128::from(x), i128::from(y)));
+        Self::wrapping(
+            values.into_iter().min().unwrap(),
+            values.into_iter().max().unwrap(),
+        )
+    }
+}
+
+/// The values of `add` on arguments from each interval
+impl Add for Interval {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::wrapping(
+            i128::from(self.lo) + i128::from(other.lo),
+            i128::from(self.hi) + i128::from(other.hi),
+        )
+    }
+}
+
+/// The values of `sub` on arguments from each interval
+impl Sub for Interval {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::wrapping(
+            i128::from(self.lo) - i128::from(other.hi),
+            i128::from(self.hi) - i128::from(other.lo),
+        )
+    }
+}
+
+/// The values of `mul` on arguments from each interval
+impl Mul for Interval {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::corners(self, other, |a, b| a * b)
+    }
+}
+
+// The bounds at the ends of the integers print as infinities
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.lo {
+            i64::MIN => write!(f, "[-∞, ")?,
+            lo => write!(f, "[{lo}, ")?,
+        }
+        match self.hi {
+            i64::MAX => write!(f, "∞]"),
+            hi => write!(f, "{hi}]"),
+        }
+    }
+}
+
+/// What [`IntervalAnalysis`] knows about the value of a variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbstractValue {
+    /// An integer in this interval
+    Int(Interval),
+    /// A pointer into an allocation made by `alloc`
+    Pointer {
+        /// The number of entries in the allocation
+        size: Interval,
+        /// How many entries past the start of the allocation the pointer is
+        offset: Interval,
+    },
+    /// A boolean which holds exactly when `op` holds on the integers `args`, or when it doesn't if `negated` is set
+    Comparison {
+        /// One of `eq`, `lt`, `gt`, `le`, or `ge`
+        op: ValueOps,
+        /// The variables which were compared, which haven't been assigned since
+        args: [String; 2],
+        /// Whether the boolean is the negation of the comparison
+        negated: bool,
+    },
+    /// Any value of the type of the variable
+    Unknown,
+}
+
+impl AbstractValue {
+    fn join(&mut self, other: &Self) {
+        *self = match (&*self, other) {
+            (Self::Int(a), Self::Int(b)) => Self::Int(a.join(*b)),
+            (
+                Self::Pointer { size, offset },
+                Self::Pointer {
+                    size: other_size,
+                    offset: other_offset,
+                },
+            ) => Self::Pointer {
+                size: size.join(*other_size),
+                offset: offset.join(*other_offset),
+            },
+            (a, b) if a == b => return,
+            _ => Self::Unknown,
+        };
+    }
+
+    fn widen(&mut self, other: &Self) {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => *a = a.widen(*b),
+            (
+                Self::Pointer { size, offset },
+                Self::Pointer {
+                    size: other_size,
+                    offset: other_offset,
+                },
+            ) => {
+                *size = size.widen(*other_size);
+                *offset = offset.widen(*other_offset);
+            }
+            (a, b) => a.join(b),
+        }
+    }
+
+    const fn narrow(&mut self, other: &Self) {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => *a = a.narrow(*b),
+            (
+                Self::Pointer { size, offset },
+                Self::Pointer {
+                    size: other_size,
+                    offset: other_offset,
+                },
+            ) => {
+                *size = size.narrow(*other_size);
+                *offset = offset.narrow(*other_offset);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The state of [`IntervalAnalysis`] at a program point
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntervalState {
+    /// The value of every variable which may have been assigned, where variables which are missing are never assigned on any path to the program point
+    pub vars: BTreeMap<String, AbstractValue>,
+}
+
+impl IntervalState {
+    /// The interval holding the integer `var`, if it may have been assigned
+    #[must_use]
+    pub fn int(&self, var: &str) -> Option<Interval> {
+        match self.vars.get(var)? {
+            AbstractValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    // Assigns `value` to `dest`, or makes it unassigned if `value` is `None`, forgetting the comparisons which read `dest`, since they were made with its old value
+    fn assign(&mut self, dest: &str, value: Option<AbstractValue>) {
+        match value {
+            Some(v) => self.vars.insert(dest.to_string(), v),
+            None => self.vars.remove(dest),
+        };
+        for v in self.vars.values_mut() {
+            if matches!(v, AbstractValue::Comparison { args, .. } if args.iter().any(|a| a == dest))
+            {
+                *v = AbstractValue::Unknown;
+            }
+        }
+    }
+}
+
+impl Lattice for IntervalState {
+    fn join(&mut self, other: &Self) {
+        for (var, v) in &other.vars {
+            self.vars
+                .entry(var.clone())
+                .and_modify(|value| value.join(v))
+                .or_insert_with(|| v.clone());
+        }
+    }
+
+    fn widen(&mut self, other: &Self) {
+        for (var, v) in &other.vars {
+            self.vars
+                .entry(var.clone())
+                .and_modify(|value| value.widen(v))
+                .or_insert_with(|| v.clone());
+        }
+    }
+
+    fn narrow(&mut self, other: &Self) {
+        for (var, value) in &mut self.vars {
+            if let Some(v) = other.vars.get(var) {
+                value.narrow(v);
+            }
+        }
+    }
+}
+
+/// Computes the interval holding each integer variable, along with the allocation each pointer points into and where
+///
+/// Branches on the result of comparing two integers narrow the intervals of those integers on each side of the branch, so that a loop counter which is compared against a bound stays within it.
+/// In SSA form, `phi` takes the value of its argument for the edge which was taken.
+/// Nothing is known about the arguments of the function, or about the results of `call` and `load`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IntervalAnalysis;
+
+impl AbstractInterpretation for IntervalAnalysis {
+    type State = IntervalState;
+
+    fn entry(&self, cfg: &Cfg) -> Self::State {
+        IntervalState {
+            vars: cfg
+                .args
+                .iter()
+                .map(|a| (a.name.clone(), unknown(&a.arg_type)))
+                .collect(),
+        }
+    }
+
+    fn transfer(&self, state: &mut Self::State, instr: &Instruction, _loc: Location) {
+        let value = match instr {
+            Instruction::Constant {
+                const_type: Type::Int,
+                value: Literal::Int(i),
+                ..
+            } => AbstractValue::Int(Interval::constant(*i)),
+            Instruction::Constant { const_type, .. } => unknown(const_type),
+            #[cfg(feature = "ssa")]
+            Instruction::Value {
+                op: ValueOps::Phi, ..
+            } => return,
+            Instruction::Value {
+                op, args, op_type, ..
+            } => evaluate(state, op, args, op_type),
+            Instruction::Effect { .. } => return,
+        };
+        if let Some(dest) = instr.dest() {
+            state.assign(dest, Some(value));
+        }
+    }
+
+    fn edge(&self, state: &mut Self::State, cfg: &Cfg, from: usize, to: usize) -> bool {
+        if let Some(Instruction::Effect {
+            op: EffectOps::Branch,
+            args,
+            labels,
+            ..
+        }) = cfg.blocks[from].terminator()
+        {
+            let target = &cfg.blocks[to].name;
+            if labels[0] != labels[1] {
+                if let Some(AbstractValue::Comparison { op, args, negated }) =
+                    state.vars.get(&args[0]).cloned()
+                {
+                    let holds = (*target == labels[0]) != negated;
+                    if !refine(state, &op, &args, holds) {
+                        return false;
+                    }
+                }
+            }
+        }
+        assign_phis(state, cfg, from, to);
+        true
+    }
+}
+
+const fn unknown(t: &Type) -> AbstractValue {
+    if matches!(t, Type::Int) {
+        AbstractValue::Int(Interval::TOP)
+    } else {
+        AbstractValue::Unknown
+    }
+}
+
+// The value of `op` on `args` as the value of `state`, which gives a value of type `op_type`
+#[cfg_attr(
+    not(feature = "other"),
+    expect(
+        clippy::trivially_copy_pass_by_ref,
+        reason = "Operations are only `Copy` without the `other` feature"
+    )
+)]
+fn evaluate(
+    state: &IntervalState,
+    op: &ValueOps,
+    args: &[String],
+    op_type: &Type,
+) -> AbstractValue {
+    let int = |i: usize| state.int(&args[i]).unwrap_or(Interval::TOP);
+    match op {
+        ValueOps::Id => state
+            .vars
+            .get(&args[0])
+            .cloned()
+            .unwrap_or_else(|| unknown(op_type)),
+        ValueOps::Add => AbstractValue::Int(int(0) + int(1)),
+        ValueOps::Sub => AbstractValue::Int(int(0) - int(1)),
+        ValueOps::Mul => AbstractValue::Int(int(0) * int(1)),
+        ValueOps::Div => AbstractValue::Int(int(0).checked_div(int(1)).unwrap_or(Interval::TOP)),
+        ValueOps::Eq | ValueOps::Lt | ValueOps::Gt | ValueOps::Le | ValueOps::Ge => {
+            AbstractValue::Comparison {
+                op: ValueOps::clone(op),
+                args: [args[0].clone(), args[1].clone()],
+                negated: false,
+            }
+        }
+        ValueOps::Not => match state.vars.get(&args[0]) {
+            Some(AbstractValue::Comparison { op, args, negated }) => AbstractValue::Comparison {
+                op: ValueOps::clone(op),
+                args: args.clone(),
+                negated: !negated,
+            },
+            _ => AbstractValue::Unknown,
+        },
+        #[cfg(feature = "char")]
+        ValueOps::Char2int => AbstractValue::Int(Interval {
+            lo: 0,
+            hi: u32::from(char::MAX).into(),
+        }),
+        #[cfg(feature = "memory")]
+        // Allocating a negative number of entries is an error, so the size is never negative afterwards
+        ValueOps::Alloc => AbstractValue::Pointer {
+            size: int(0)
+                .meet(Interval {
+                    lo: 0,
+                    hi: i64::MAX,
+                })
+                .unwrap_or(Interval::constant(0)),
+            offset: Interval::constant(0),
+        },
+        #[cfg(feature = "memory")]
+        ValueOps::PtrAdd => match state.vars.get(&args[0]) {
+            Some(AbstractValue::Pointer { size, offset }) => AbstractValue::Pointer {
+                size: *size,
+                offset: *offset + int(1),
+            },
+            _ => AbstractValue::Unknown,
+        },
+        _ => unknown(op_type),
+    }
+}
+
+// Narrows the intervals of `args` to the values for which `op` on them is `holds`, returning `false` if there are none
+#[cfg_attr(
+    not(feature = "other"),
+    expect(
+        clippy::trivially_copy_pass_by_ref,
+        reason = "Operations are only `Copy` without the `other` feature"
+    )
+)]
+fn refine(state: &mut IntervalState, op: &ValueOps, args: &[String; 2], holds: bool) -> bool {
+    let [a, b] = args;
+    let (Some(x), Some(y)) = (state.int(a), state.int(b)) else {
+        return true;
+    };
+    if a == b {
+        return matches!(
+            (op, holds),
+            (ValueOps::Eq | ValueOps::Le | ValueOps::Ge, true)
+                | (ValueOps::Lt | ValueOps::Gt, false)
+        );
+    }
+    let swap = |(y, x)| (x, y);
+    let refined = match (op, holds) {
+        (ValueOps::Lt, true) | (ValueOps::Ge, false) => below(x, y, 1),
+        (ValueOps::Le, true) | (ValueOps::Gt, false) => below(x, y, 0),
+        (ValueOps::Gt, true) | (ValueOps::Le, false) => below(y, x, 1).map(swap),
+        (ValueOps::Ge, true) | (ValueOps::Lt, false) => below(y, x, 0).map(swap),
+        (ValueOps::Eq, true) => x.meet(y).map(|i| (i, i)),
+        (ValueOps::Eq, false) => exclude(x, y).zip(exclude(y, x)),
+        _ => return true,
+    };
+    let Some((x, y)) = refined else {
+        return false;
+    };
+    // The variables keep their values, so comparisons which read them are still valid
+    state.vars.insert(a.clone(), AbstractValue::Int(x));
+    state.vars.insert(b.clone(), AbstractValue::Int(y));
+    true
+}
+
+// Narrows `x` and `y` to the values where `x + gap <= y`
+fn below(x: Interval, y: Interval, gap: i64) -> Option<(Interval, Interval)> {
+    let gap = i128::from(gap);
+    Interval::checked(x.lo.into(), i128::from(x.hi).min(i128::from(y.hi) - gap)).zip(
+        Interval::checked(i128::from(y.lo).max(i128::from(x.lo) + gap), y.hi.into()),
+    )
+}
+
+// Narrows `x` to the values which aren't equal to `y`, which is only possible when `y` is a single value at one end of `x`
+fn exclude(x: Interval, y: Interval) -> Option<Interval> {
+    if y.lo != y.hi {
+        return Some(x);
+    }
+    let lo = i128::from(x.lo) + i128::from(x.lo == y.lo);
+    let hi = i128::from(x.hi) - i128::from(x.hi == y.lo);
+    Interval::checked(lo, hi)
+}
+
+// Gives the destination of each `phi` at the start of `to` the value of its argument from `from`, where a missing argument leaves it unassigned
+#[cfg(feature = "ssa")]
+fn assign_phis(state: &mut IntervalState, cfg: &Cfg, from: usize, to: usize) {
+    let from = &cfg.blocks[from].name;
+    let values: Vec<(&str, Option<AbstractValue>)> = cfg.blocks[to]
+        .instrs
+        .iter()
+        .filter_map(|instr| match instr {
+            Instruction::Value {
+                op: ValueOps::Phi,
+                dest,
+                args,
+                labels,
+                ..
+            } => {
+                let value = args
+                    .iter()
+                    .zip(labels)
+                    .find(|(_, l)| *l == from)
+                    .and_then(|(a, _)| state.vars.get(a).cloned());
+                Some((dest.as_str(), value))
+            }
+            _ => None,
+        })
+        .collect();
+    for (dest, value) in values {
+        state.assign(dest, value);
+    }
+}
+
+#[cfg(not(feature = "ssa"))]
+const fn assign_phis(_: &mut IntervalState, _: &Cfg, _: usize, _: usize) {}
+
+/// The possible runtime errors which [`check_cfg`] looks for
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IntervalWarning {
+    /// The divisor {0} of `div` is in {1}, which contains 0
+    #[error("Possible attempt to divide by 0, `{0}` is in {1}")]
+    DivisionByZero(String, Interval),
+
+    /// The pointer {1} which `{0}` reads, writes, or produces is at an offset in {2} of an allocation with a size in {3}, which may be out of bounds
+    #[error("Possible out of bounds `{0}`, `{1}` is at an offset in {2} of an allocation with a size in {3}")]
+    OutOfBounds(String, String, Interval, Interval),
+
+    /// The argument {0} of `int2char` is in {1}, which contains values that aren't characters
+    #[error("Possible value that cannot be converted to char, `{0}` is in {1}")]
+    ToCharError(String, Interval),
+}
+
+impl IntervalWarning {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn add_pos(self, pos_var: Option<Position>) -> PositionalIntervalWarning {
+        PositionalIntervalWarning {
+            e: self,
+            pos: pos_var,
+        }
+    }
+}
+
+/// Wraps [`IntervalWarning`] to optionally provide source code positions if they are available.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct PositionalIntervalWarning {
+    #[doc(hidden)]
+    pub e: IntervalWarning,
+    #[doc(hidden)]
+    pub pos: Option<Position>,
+}
+
+impl Display for PositionalIntervalWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_positional(f, &self.e, self.pos.as_ref())
+    }
+}
+
+/// Runs [`check_cfg`] on `function`
+/// # Errors
+/// Returns an error if the labels of `function` don't resolve
+pub fn check_function(function: &Function) -> Result<Vec<PositionalIntervalWarning>, CfgError> {
+    Ok(check_cfg(&Cfg::try_from(function.clone())?))
+}
+
+/// Reports the instructions of `cfg` which [`IntervalAnalysis`] can't rule out stopping the program with an error
+///
+/// These are `div` by an interval containing 0, `int2char` of an interval containing values which aren't characters, and `load`, `store`, or `ptradd` of a pointer which may be outside of its allocation, where `ptradd` may also point just past the end.
+/// Pointers which don't come from an `alloc` in the same function are never reported, and instructions in blocks which can't be reached are skipped.
+#[must_use]
+pub fn check_cfg(cfg: &Cfg) -> Vec<PositionalIntervalWarning> {
+    let result = interpret(&IntervalAnalysis, cfg);
+    let mut warnings = Vec::new();
+    for (block, state) in result.ins.into_iter().enumerate() {
+        let Some(mut state) = state else {
+            continue;
+        };
+        for (index, instr) in cfg.blocks[block].instrs.iter().enumerate() {
+            if let Some(w) = check(&state, instr) {
+                warnings.push(w.add_pos(instr_pos(instr)));
+            }
+            IntervalAnalysis.transfer(&mut state, instr, Location { block, index });
+        }
+    }
+    warnings
+}
+
+fn check(state: &IntervalState, instr: &Instruction) -> Option<IntervalWarning> {
+    match instr {
+        Instruction::Value {
+            op: ValueOps::Div,
+            args,
+            ..
+        } => state
+            .int(&args[1])
+            .filter(|i| i.contains(0))
+            .map(|i| IntervalWarning::DivisionByZero(args[1].clone(), i)),
+        #[cfg(feature = "char")]
+        Instruction::Value {
+            op: ValueOps::Int2char,
+            args,
+            ..
+        } => state
+            .int(&args[0])
+            .filter(|i| !is_char(*i))
+            .map(|i| IntervalWarning::ToCharError(args[0].clone(), i)),
+        #[cfg(feature = "memory")]
+        Instruction::Value {
+            op: ValueOps::Load,
+            args,
+            ..
+        } => out_of_bounds("load", &args[0], state.vars.get(&args[0]), 1),
+        #[cfg(feature = "memory")]
+        Instruction::Effect {
+            op: EffectOps::Store,
+            args,
+            ..
+        } => out_of_bounds("store", &args[0], state.vars.get(&args[0]), 1),
+        #[cfg(feature = "memory")]
+        Instruction::Value {
+            op: ValueOps::PtrAdd,
+            args,
+            dest,
+            op_type,
+            ..
+        } => out_of_bounds(
+            "ptradd",
+            dest,
+            Some(&evaluate(state, &ValueOps::PtrAdd, args, op_type)),
+            0,
+        ),
+        _ => None,
+    }
+}
+
+// Reports `op` on the pointer `ptr` if it may not be at least `room` entries before the end of its allocation
+#[cfg(feature = "memory")]
+fn out_of_bounds(
+    op: &str,
+    ptr: &str,
+    value: Option<&AbstractValue>,
+    room: i64,
+) -> Option<IntervalWarning> {
+    let Some(AbstractValue::Pointer { size, offset }) = value else {
+        return None;
+    };
+    (offset.lo < 0 || i128::from(offset.hi) + i128::from(room) > size.lo.into())
+        .then(|| IntervalWarning::OutOfBounds(op.to_string(), ptr.to_string(), *offset, *size))
+}
+
+#[cfg(feature = "char")]
+const fn is_char(i: Interval) -> bool {
+    (i.lo >= 0 && i.hi < 0xD800) || (i.lo > 0xDFFF && i.hi <= char::MAX as i64)
+}
+
+#[cfg(feature = "position")]
+fn instr_pos(instr: &Instruction) -> Option<Position> {
+    instr.get_pos()
+}
+
+#[cfg(not(feature = "position"))]
+const fn instr_pos(_: &Instruction) -> Option<Position> {
+    None
+}
@@ -3,28 +3,95 @@
 #![warn(clippy::allow_attributes)]
 #![doc = include_str!("../README.md")]
 
+/// Provides [`absint::interpret`], an abstract interpretation solver over a [`cfg::Cfg`] with widening and narrowing at loop heads
+pub mod absint;
 /// Provides the unstructured representation of Bril programs
 pub mod abstract_program;
+/// Provides [`binary::write_binary`] and [`binary::read_binary`], a compact binary encoding of a [Program]
+pub mod binary;
+/// Provides [`builder::FunctionBuilder`], for constructing a [Function] one instruction at a time
+pub mod builder;
+/// Provides [`bytecode::write_bytecode`] and [`bytecode::read_bytecode`], which convert a [Program] to and from the bytecode of `fastbril`
+pub mod bytecode;
+/// Provides [`callgraph::CallGraph`], which records the functions that each function of a [Program] calls
+pub mod callgraph;
+/// Provides [`canonical::canonicalize`], which renames the variables and labels of a [Function] in a fixed order, and [`canonical::alpha_eq`], which compares programs up to those names
+pub mod canonical;
+/// Provides [`cfg::Cfg`], a control-flow graph representation of a [Function]
+pub mod cfg;
 /// Provides the Error handling and conversion between [`AbstractProgram`] and [Program]
 pub mod conversion;
+/// Provides [`dataflow::solve`], a generic worklist solver for dataflow analyses over a [`cfg::Cfg`]
+pub mod dataflow;
+/// Provides [`dce::trivial_dce`] and [`dce::global_dce`], which remove instructions whose results are never used
+pub mod dce;
+/// Provides [`dom::Dominators`], the dominance relation of a [`cfg::Cfg`]
+pub mod dom;
+/// Provides [`error::LoadError`], the error type for fallibly loading Bril programs
+pub mod error;
+/// Provides [`extensions::Extensions`], the set of Bril extensions which a [Program] uses
+pub mod extensions;
+/// Provides [`fold::fold`], which evaluates value operations on constant arguments
+pub mod fold;
+/// Provides [`gvn::gvn`], global value numbering over a [Program] in SSA form
+#[cfg(feature = "ssa")]
+pub mod gvn;
+/// Provides [`infer::infer_types`], which fills in the missing types of an [`AbstractProgram`]
+pub mod infer;
+/// Provides [`inline::inline`], which inlines the functions of a [Program] into their callers
+pub mod inline;
+/// Provides [`interval::IntervalAnalysis`] and [`interval::check_function`], which reports the instructions of a [Function] that may fail at runtime according to the range of each integer
+pub mod interval;
+/// Provides [`licm::licm`], which hoists loop-invariant instructions out of the loops of a [Function]
+pub mod licm;
+/// Provides [`loops::natural_loops`], which finds the loops of a [`cfg::Cfg`] and how they nest, and [`loops::insert_preheaders`]
+pub mod loops;
+/// Provides [`lvn::lvn`], a local value numbering optimization pass
+pub mod lvn;
+/// Provides [`memcheck::check_program`], which statically reports leaks, double frees, uses after free, and frees of offset pointers in a [Program]
+#[cfg(feature = "memory")]
+pub mod memcheck;
 /// Provides the structured representation of Bril programs
 pub mod program;
+/// Provides [`sccp::sccp`], sparse conditional constant propagation over a [Program] in SSA form
+#[cfg(feature = "ssa")]
+pub mod sccp;
+/// Provides [`ssa::to_ssa`] and [`ssa::from_ssa`], which convert a [`cfg::Cfg`] into and out of SSA form
+#[cfg(feature = "ssa")]
+pub mod ssa;
+/// Provides the [`visit::Visit`] and [`visit::VisitMut`] traits for traversing and rewriting a [Program]
+pub mod visit;
+/// Provides [`wellformed::check_program`], which reports every violation of the structural rules of well-formed Bril in a [Program]
+pub mod wellformed;
 pub use abstract_program::*;
 pub use program::*;
 
 use std::io::{self, Write};
 
+use error::LoadError;
+
 // todo Have versions of the output_* functions that take a [std::io::Write]
 // todo possible deprecate/remove the wrapper functions to make the code base cleaner
-// todo Wrap the outputs of serde in an error instead of panic-ing
 
 /// A helper function for parsing a Bril program from ```input``` in JSON format to [Program]
+///
+/// Input in the format of [`binary::write_binary`] is detected and read as well.
+/// # Errors
+/// Will return an error if the input could not be read or if the input JSON is not well-formed bril JSON
+pub fn try_load_program_from_read<R: std::io::Read>(mut input: R) -> Result<Program, LoadError> {
+    let mut buffer = Vec::new();
+    input.read_to_end(&mut buffer)?;
+    if binary::is_binary(&buffer) {
+        return Ok(binary::read_binary_from_slice(&buffer)?);
+    }
+    Ok(serde_json::from_slice(&buffer)?)
+}
+
+/// A panicking wrapper of [`try_load_program_from_read`]
 /// # Panics
 /// Will panic if the input JSON is not well-formed bril JSON
-pub fn load_program_from_read<R: std::io::Read>(mut input: R) -> Program {
-    let mut buffer = String::new();
-    input.read_to_string(&mut buffer).unwrap();
-    serde_json::from_str(&buffer).unwrap()
+pub fn load_program_from_read<R: std::io::Read>(input: R) -> Program {
+    try_load_program_from_read(input).unwrap()
 }
 
 /// A wrapper of [`load_program_from_read`] which assumes [`std::io::Stdin`]
@@ -42,12 +109,21 @@ pub fn output_program(p: &Program) {
 }
 
 /// A helper function for parsing a Bril program from ```input``` in JSON format to [`AbstractProgram`]
+/// # Errors
+/// Will return an error if the input could not be read or if the input JSON is not well-formed bril JSON
+pub fn try_load_abstract_program_from_read<R: std::io::Read>(
+    mut input: R,
+) -> Result<AbstractProgram, LoadError> {
+    let mut buffer = String::new();
+    input.read_to_string(&mut buffer)?;
+    Ok(serde_json::from_str(&buffer)?)
+}
+
+/// A panicking wrapper of [`try_load_abstract_program_from_read`]
 /// # Panics
 /// Will panic if the input JSON is not well-formed bril JSON
-pub fn load_abstract_program_from_read<R: std::io::Read>(mut input: R) -> AbstractProgram {
-    let mut buffer = String::new();
-    input.read_to_string(&mut buffer).unwrap();
-    serde_json::from_str(&buffer).unwrap()
+pub fn load_abstract_program_from_read<R: std::io::Read>(input: R) -> AbstractProgram {
+    try_load_abstract_program_from_read(input).unwrap()
 }
 
 /// A wrapper of [`load_abstract_program_from_read`] which assumes [`std::io::Stdin`]
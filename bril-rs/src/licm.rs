@@ -0,0 +1,362 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{
+    cfg::{Cfg, CfgError},
+    dataflow::{solve, Analysis, DataflowResult, Direction, LiveVariables, Location},
+    dce::has_side_effects,
+    dom::Dominators,
+    loops::{insert_preheaders, natural_loops, Loop},
+    EffectOps, Function, Instruction, Type, ValueOps,
+};
+
+/// Hoists loop-invariant instructions out of every loop of `function`, like [`licm_cfg`]
+///
+/// Returns whether anything was hoisted.
+/// # Errors
+/// Returns an error if the labels of `function` don't resolve, in which case `function` is unchanged
+pub fn licm(function: &mut Function) -> Result<bool, CfgError> {
+    let mut cfg = Cfg::try_from(function.clone())?;
+    let changed = licm_cfg(&mut cfg);
+    *function = cfg.into();
+    Ok(changed)
+}
+
+/// Moves the instructions of each loop of `cfg` which compute the same value on every iteration into the preheader of the loop, after inserting preheaders with [`insert_preheaders`]
+///
+/// Inner loops are visited first, so that an instruction can be hoisted through several loops.
+/// An instruction is hoisted when it has no side effects, its arguments are only assigned outside of the loop or by another hoisted instruction which runs before it, and its destination is only defined by it within the loop and isn't live into the header.
+/// It must also run on every trip through the loop, by dominating every block which leaves the loop, unless it can't fail and its destination isn't live after the loop.
+/// `load` is only hoisted when the loop has no `call`, and no `store` or `free` of a pointer of the same type, which is the only way that memory of that type can change.
+///
+/// Returns whether anything was hoisted.
+pub fn licm_cfg(cfg: &mut Cfg) -> bool {
+    insert_preheaders(cfg);
+    let dom = Dominators::new(cfg);
+    let mut types: HashMap<String, Type> = cfg
+        .args
+        .iter()
+        .map(|a| (a.name.clone(), a.arg_type.clone()))
+        .collect();
+    for instr in cfg.blocks.iter().flat_map(|b| &b.instrs) {
+        if let Instruction::Constant {
+            dest,
+            const_type: t,
+            ..
+        }
+        | Instruction::Value {
+            dest, op_type: t, ..
+        } = instr
+        {
+            types.insert(dest.clone(), t.clone());
+        }
+    }
+
+    let mut changed = false;
+    // Hoisting only moves instructions between blocks, so the analyses stay valid until something is hoisted
+    let mut facts = None;
+    for l in natural_loops(cfg, &dom) {
+        if let Some(preheader) = l.preheader(cfg) {
+            let solved = facts.get_or_insert_with(|| Facts::new(cfg));
+            if hoist(cfg, &dom, &l, preheader, &types, solved) {
+                changed = true;
+                facts = None;
+            }
+        }
+    }
+    changed
+}
+
+// The analyses which decide what can be hoisted, for the whole function
+struct Facts {
+    live: DataflowResult<BTreeSet<String>>,
+    defined: DataflowResult<Option<BTreeSet<String>>>,
+}
+
+impl Facts {
+    fn new(cfg: &Cfg) -> Self {
+        Self {
+            live: solve(&LiveVariables, cfg),
+            defined: solve(&DefinedVariables, cfg),
+        }
+    }
+}
+
+fn hoist(
+    cfg: &mut Cfg,
+    dom: &Dominators,
+    l: &Loop,
+    preheader: usize,
+    types: &HashMap<String, Type>,
+    facts: &Facts,
+) -> bool {
+    if l.blocks
+        .iter()
+        .flat_map(|b| &cfg.blocks[*b].instrs)
+        .any(is_speculative)
+    {
+        return false;
+    }
+
+    let live = &facts.live;
+    let defined = facts.defined.outs[preheader].as_ref();
+    // Where each variable is assigned in the loop
+    let mut definitions: HashMap<&str, Vec<Location>> = HashMap::new();
+    for &block in &l.blocks {
+        for (index, instr) in cfg.blocks[block].instrs.iter().enumerate() {
+            if let Some(dest) = instr.dest() {
+                definitions
+                    .entry(dest)
+                    .or_default()
+                    .push(Location { block, index });
+            }
+        }
+    }
+    let exits = l.exits(cfg);
+    // The blocks of the loop which can leave it, including by returning
+    let exiting: Vec<usize> = l
+        .blocks
+        .iter()
+        .copied()
+        .filter(|b| {
+            let successors = &cfg.blocks[*b].successors;
+            successors.is_empty() || successors.iter().any(|s| !l.contains(*s))
+        })
+        .collect();
+    let writes = Writes::new(cfg, l, types);
+
+    let mut hoisted: Vec<Location> = Vec::new();
+    let mut invariant: HashSet<Location> = HashSet::new();
+    let mut progress = true;
+    while progress {
+        progress = false;
+        for &block in &l.blocks {
+            for (index, instr) in cfg.blocks[block].instrs.iter().enumerate() {
+                let loc = Location { block, index };
+                if invariant.contains(&loc) {
+                    continue;
+                }
+                let Some(dest) = instr.dest() else {
+                    continue;
+                };
+                let args_invariant = instr.args().iter().all(|a| {
+                    match definitions.get(a.as_str()).map(Vec::as_slice) {
+                        // The loop never assigns `a`, so it keeps the value it had before the loop
+                        None => defined.is_none_or(|vars| vars.contains(a)),
+                        // `a` is only assigned by an invariant instruction which always runs first
+                        Some([d]) => invariant.contains(d) && runs_before(dom, *d, loc),
+                        Some(_) => false,
+                    }
+                });
+                let runs_every_time = exiting.iter().all(|e| dom.dominates(block, *e));
+                let dead_after = exits.iter().all(|e| !live.ins[*e].contains(dest));
+                if can_move(instr, &writes)
+                    && args_invariant
+                    && definitions[dest].len() == 1
+                    && !live.ins[l.header].contains(dest)
+                    && if can_fail(instr) {
+                        // Failing in the preheader must not skip anything visible which the loop did first
+                        runs_every_time && !effect_before(cfg, l, loc)
+                    } else {
+                        runs_every_time || dead_after
+                    }
+                {
+                    invariant.insert(loc);
+                    hoisted.push(loc);
+                    progress = true;
+                }
+            }
+        }
+    }
+
+    let instrs: Vec<Instruction> = hoisted
+        .iter()
+        .map(|loc| cfg.blocks[loc.block].instrs[loc.index].clone())
+        .collect();
+    for loc in hoisted.iter().collect::<BTreeSet<_>>().into_iter().rev() {
+        cfg.blocks[loc.block].instrs.remove(loc.index);
+    }
+    let target = &mut cfg.blocks[preheader];
+    let at = target.instrs.len() - usize::from(target.terminator().is_some());
+    target.instrs.splice(at..at, instrs);
+    !hoisted.is_empty()
+}
+
+// Whether every path to `after` goes through `before`
+fn runs_before(dom: &Dominators, before: Location, after: Location) -> bool {
+    if before.block == after.block {
+        before.index < after.index
+    } else {
+        dom.dominates(before.block, after.block)
+    }
+}
+
+// Whether an instruction with side effects, other than a jump or branch, can run between entering `l` and `loc`
+fn effect_before(cfg: &Cfg, l: &Loop, loc: Location) -> bool {
+    let is_effect = |instr: &Instruction| {
+        has_side_effects(instr)
+            && !matches!(
+                instr,
+                Instruction::Effect {
+                    op: EffectOps::Jump | EffectOps::Branch,
+                    ..
+                }
+            )
+    };
+    if cfg.blocks[loc.block].instrs[..loc.index]
+        .iter()
+        .any(is_effect)
+    {
+        return true;
+    }
+    // The blocks of the loop on a path from the header to `loc`
+    let mut seen = HashSet::new();
+    let mut stack = vec![loc.block];
+    while let Some(b) = stack.pop() {
+        if b == l.header {
+            continue;
+        }
+        for &p in &cfg.blocks[b].predecessors {
+            if l.contains(p) && seen.insert(p) {
+                stack.push(p);
+            }
+        }
+    }
+    seen.into_iter()
+        .any(|b| cfg.blocks[b].instrs.iter().any(is_effect))
+}
+
+// Whether `instr` computes a value from nothing but its arguments, or is a `load` which no write in the loop can change
+#[cfg_attr(
+    not(feature = "memory"),
+    expect(
+        unused_variables,
+        clippy::missing_const_for_fn,
+        reason = "Only loads need to know what the loop writes"
+    )
+)]
+fn can_move(instr: &Instruction, writes: &Writes) -> bool {
+    match instr {
+        Instruction::Constant { .. } => true,
+        Instruction::Value { op, op_type, .. } => match op {
+            #[cfg(feature = "memory")]
+            ValueOps::Load => !writes.calls && !writes.types.contains(op_type),
+            #[cfg(feature = "ssa")]
+            ValueOps::Phi => false,
+            #[cfg(feature = "ssa2")]
+            ValueOps::Get => false,
+            _ => !has_side_effects(instr),
+        },
+        Instruction::Effect { .. } => false,
+    }
+}
+
+// Whether `instr` can stop the program with an error when its arguments are defined, which it shouldn't do on a path where it wouldn't have run
+const fn can_fail(instr: &Instruction) -> bool {
+    match instr {
+        Instruction::Value { op, .. } => match op {
+            ValueOps::Div => true,
+            #[cfg(feature = "memory")]
+            ValueOps::Load => true,
+            #[cfg(feature = "char")]
+            ValueOps::Int2char => true,
+            _ => false,
+        },
+        Instruction::Constant { .. } | Instruction::Effect { .. } => false,
+    }
+}
+
+const fn is_speculative(instr: &Instruction) -> bool {
+    match instr {
+        #[cfg(feature = "speculate")]
+        Instruction::Effect {
+            op: EffectOps::Speculate | EffectOps::Commit | EffectOps::Guard,
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
+// What a loop can do to memory: every `call` might write to anything, while `store` and `free` only touch memory of the type that their pointer points to
+#[derive(Default)]
+#[cfg_attr(
+    not(feature = "memory"),
+    expect(dead_code, reason = "Only loads need to know what the loop writes")
+)]
+struct Writes {
+    calls: bool,
+    types: HashSet<Type>,
+}
+
+impl Writes {
+    #[cfg_attr(
+        not(feature = "memory"),
+        expect(unused_variables, reason = "Only stores and frees have pointer types")
+    )]
+    fn new(cfg: &Cfg, l: &Loop, types: &HashMap<String, Type>) -> Self {
+        let mut writes = Self::default();
+        for instr in l.blocks.iter().flat_map(|b| &cfg.blocks[*b].instrs) {
+            match instr {
+                Instruction::Value {
+                    op: ValueOps::Call, ..
+                }
+                | Instruction::Effect {
+                    op: EffectOps::Call,
+                    ..
+                } => writes.calls = true,
+                #[cfg(feature = "other")]
+                Instruction::Value {
+                    op: ValueOps::Other(_),
+                    ..
+                }
+                | Instruction::Effect {
+                    op: EffectOps::Other(_),
+                    ..
+                } => writes.calls = true,
+                #[cfg(feature = "memory")]
+                Instruction::Effect {
+                    op: EffectOps::Store | EffectOps::Free,
+                    args,
+                    ..
+                } => match args.first().and_then(|p| types.get(p)) {
+                    Some(Type::Pointer(t)) => {
+                        writes.types.insert(t.as_ref().clone());
+                    }
+                    _ => writes.calls = true,
+                },
+                _ => {}
+            }
+        }
+        writes
+    }
+}
+
+// Definite assignment: the variables which are assigned on every path to each point, where `None` stands for every variable at points which haven't been reached yet
+struct DefinedVariables;
+
+impl Analysis for DefinedVariables {
+    type Domain = Option<BTreeSet<String>>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn boundary(&self, cfg: &Cfg) -> Self::Domain {
+        Some(cfg.args.iter().map(|a| a.name.clone()).collect())
+    }
+
+    fn init(&self, _cfg: &Cfg) -> Self::Domain {
+        None
+    }
+
+    fn meet(&self, value: &mut Self::Domain, other: &Self::Domain) {
+        match (value.as_mut(), other) {
+            (_, None) => {}
+            (None, Some(other)) => *value = Some(other.clone()),
+            (Some(vars), Some(other)) => vars.retain(|v| other.contains(v)),
+        }
+    }
+
+    fn transfer(&self, value: &mut Self::Domain, instr: &Instruction, _loc: Location) {
+        if let (Some(vars), Some(dest)) = (value.as_mut(), instr.dest()) {
+            vars.insert(dest.to_string());
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    cfg::{fresh_name, BasicBlock, Cfg},
+    dom::Dominators,
+    EffectOps, Instruction,
+};
+
+/// A natural loop of a [`Cfg`]: a header along with every block which can reach a back edge into the header without going through it
+///
+/// Back edges are edges into a block which dominates their source, and all of the back edges into the same header form one loop.
+/// All blocks are referred to by their index in [`Cfg::blocks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    /// The only block of the loop which can be entered from outside of it
+    pub header: usize,
+    /// The blocks of the loop with a back edge to the header
+    pub latches: Vec<usize>,
+    /// Every block of the loop, including the header, in increasing order
+    pub blocks: Vec<usize>,
+    /// The index of the innermost loop which contains this one in the result of [`natural_loops`]
+    pub parent: Option<usize>,
+    /// How many loops this one is nested in, counting itself, so outermost loops have a depth of 1
+    pub depth: usize,
+}
+
+impl Loop {
+    /// Whether `block` is part of the loop
+    #[must_use]
+    pub fn contains(&self, block: usize) -> bool {
+        self.blocks.binary_search(&block).is_ok()
+    }
+
+    /// The blocks outside of the loop which a block of the loop can jump to
+    #[must_use]
+    pub fn exits(&self, cfg: &Cfg) -> Vec<usize> {
+        let mut exits: Vec<usize> = self
+            .blocks
+            .iter()
+            .flat_map(|b| &cfg.blocks[*b].successors)
+            .copied()
+            .filter(|s| !self.contains(*s))
+            .collect();
+        exits.sort_unstable();
+        exits.dedup();
+        exits
+    }
+
+    /// The preheader of the loop if it has one: the only block outside of the loop which jumps to the header, which can't go anywhere else
+    ///
+    /// Code at the end of the preheader runs exactly once each time the loop is entered. Use [`insert_preheaders`] to make sure every loop has one.
+    #[must_use]
+    pub fn preheader(&self, cfg: &Cfg) -> Option<usize> {
+        let mut entries = cfg.blocks[self.header]
+            .predecessors
+            .iter()
+            .filter(|p| !self.contains(**p));
+        match (entries.next(), entries.next()) {
+            (Some(&p), None)
+                if cfg.blocks[p].successors == [self.header]
+                    && !matches!(
+                        cfg.blocks[p].terminator(),
+                        Some(Instruction::Effect {
+                            op: EffectOps::Branch,
+                            ..
+                        })
+                    ) =>
+            {
+                Some(p)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Finds the natural loops of `cfg`, with inner loops coming before the loops that contain them
+///
+/// Blocks which are unreachable from the entry block are never part of a loop.
+#[must_use]
+pub fn natural_loops(cfg: &Cfg, dom: &Dominators) -> Vec<Loop> {
+    let mut back_edges: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        if !dom.is_reachable(b) {
+            continue;
+        }
+        for &s in &block.successors {
+            if dom.dominates(s, b) {
+                back_edges.entry(s).or_default().push(b);
+            }
+        }
+    }
+
+    let mut loops: Vec<Loop> = back_edges
+        .into_iter()
+        .map(|(header, latches)| {
+            let mut in_loop = vec![false; cfg.blocks.len()];
+            in_loop[header] = true;
+            let mut worklist = latches.clone();
+            while let Some(b) = worklist.pop() {
+                if !in_loop[b] {
+                    in_loop[b] = true;
+                    worklist.extend(
+                        cfg.blocks[b]
+                            .predecessors
+                            .iter()
+                            .filter(|p| dom.is_reachable(**p)),
+                    );
+                }
+            }
+            Loop {
+                header,
+                latches,
+                blocks: (0..cfg.blocks.len()).filter(|b| in_loop[*b]).collect(),
+                parent: None,
+                depth: 1,
+            }
+        })
+        .collect();
+
+    // A loop which contains another one has more blocks than it, so the first larger loop containing a header is its parent
+    loops.sort_by_key(|l| l.blocks.len());
+    for i in 0..loops.len() {
+        loops[i].parent = (i + 1..loops.len()).find(|j| loops[*j].contains(loops[i].header));
+    }
+    for i in (0..loops.len()).rev() {
+        if let Some(p) = loops[i].parent {
+            loops[i].depth = loops[p].depth + 1;
+        }
+    }
+    loops
+}
+
+/// Gives every loop of `cfg` a [`Loop::preheader`] by inserting an empty block right before each header which doesn't have one
+///
+/// Jumps into the header from outside of the loop are redirected to the new block. In SSA form, the arguments of each `phi` in the header which come from outside of the loop are merged by a new `phi` in the new block.
+/// # Panics
+/// Will panic if a jump in `cfg` refers to a block which does not exist
+pub fn insert_preheaders(cfg: &mut Cfg) {
+    let dom = Dominators::new(cfg);
+    let loops = natural_loops(cfg, &dom);
+    let mut block_names: HashSet<String> = cfg.blocks.iter().map(|b| b.name.clone()).collect();
+    let mut vars: HashSet<String> = cfg.args.iter().map(|a| a.name.clone()).collect();
+    vars.extend(
+        cfg.blocks
+            .iter()
+            .flat_map(|b| &b.instrs)
+            .filter_map(|i| i.dest().map(str::to_string)),
+    );
+    let mut preheaders: Vec<Option<BasicBlock>> = vec![None; cfg.blocks.len()];
+    for l in &loops {
+        if l.preheader(cfg).is_some() {
+            continue;
+        }
+        let header = l.header;
+        let header_name = cfg.blocks[header].name.clone();
+        let name = fresh_name(&format!("{header_name}.preheader."), |n| {
+            block_names.contains(n)
+        });
+        block_names.insert(name.clone());
+        let mut preheader = BasicBlock::new(name.clone(), true);
+
+        let entries: Vec<usize> = cfg.blocks[header]
+            .predecessors
+            .iter()
+            .copied()
+            .filter(|p| !l.contains(*p))
+            .collect();
+        for &p in &entries {
+            if let Some(
+                i @ Instruction::Effect {
+                    op: EffectOps::Jump | EffectOps::Branch,
+                    ..
+                },
+            ) = cfg.blocks[p].instrs.last_mut()
+            {
+                for target in i.labels_mut().iter_mut().filter(|t| **t == header_name) {
+                    target.clone_from(&name);
+                }
+            }
+        }
+        // A latch which falls through into the header would fall into the preheader instead
+        let before = &mut cfg.blocks[header - 1];
+        if l.contains(header - 1) && before.terminator().is_none() {
+            before.instrs.push(jump(header_name.clone()));
+        }
+        cfg.blocks[header].labeled = true;
+
+        let entry_names: Vec<String> = entries
+            .iter()
+            .map(|p| cfg.blocks[*p].name.clone())
+            .collect();
+        for instr in &mut cfg.blocks[header].instrs {
+            preheader
+                .instrs
+                .extend(split_phi(instr, &entry_names, &name, &mut vars));
+        }
+        preheaders[header] = Some(preheader);
+    }
+
+    let blocks = std::mem::take(&mut cfg.blocks);
+    for (block, preheader) in blocks.into_iter().zip(preheaders) {
+        cfg.blocks.extend(preheader);
+        cfg.blocks.push(block);
+    }
+    cfg.recompute_edges().unwrap();
+}
+
+// Moves the arguments of `instr` which come from `entries` into a new `phi` in the preheader called `preheader`, which is returned, if `instr` is a `phi`
+#[cfg(feature = "ssa")]
+fn split_phi(
+    instr: &mut Instruction,
+    entries: &[String],
+    preheader: &str,
+    vars: &mut HashSet<String>,
+) -> Option<Instruction> {
+    #[cfg(feature = "position")]
+    let pos = instr.get_pos();
+    let Instruction::Value {
+        args,
+        dest,
+        labels,
+        op: crate::ValueOps::Phi,
+        op_type,
+        ..
+    } = instr
+    else {
+        return None;
+    };
+    let (outside, inside): (Vec<_>, Vec<_>) = args
+        .iter()
+        .cloned()
+        .zip(labels.iter().cloned())
+        .partition(|(_, l)| entries.contains(l));
+    if outside.is_empty() {
+        return None;
+    }
+    let merged = fresh_name(&format!("{dest}.preheader."), |n| vars.contains(n));
+    vars.insert(merged.clone());
+    let (outside_args, outside_labels) = outside.into_iter().unzip();
+    let phi = Instruction::Value {
+        args: outside_args,
+        dest: merged.clone(),
+        funcs: Vec::new(),
+        labels: outside_labels,
+        op: crate::ValueOps::Phi,
+        #[cfg(feature = "position")]
+        pos,
+        op_type: op_type.clone(),
+    };
+    (*args, *labels) = inside.into_iter().unzip();
+    args.push(merged);
+    labels.push(preheader.to_string());
+    Some(phi)
+}
+
+#[cfg(not(feature = "ssa"))]
+const fn split_phi(
+    _: &mut Instruction,
+    _: &[String],
+    _: &str,
+    _: &mut HashSet<String>,
+) -> Option<Instruction> {
+    None
+}
+
+fn jump(label: String) -> Instruction {
+    Instruction::Effect {
+        args: Vec::new(),
+        funcs: Vec::new(),
+        labels: vec![label],
+        op: EffectOps::Jump,
+        #[cfg(feature = "position")]
+        pos: None,
+    }
+}
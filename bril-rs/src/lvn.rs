@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "memory")]
+use crate::EffectOps;
+use crate::{
+    cfg::{fresh_name, map_blocks},
+    dataflow::is_commutative,
+    fold::fold,
+    Code, ConstOps, Function, Instruction, Literal, Program, Type, ValueOps,
+};
+
+/// Which extensions of the basic local value numbering algorithm to enable, mirroring the `-p`, `-c`, and `-f` flags of `examples/lvn.py`
+///
+/// The default enables all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LvnOptions {
+    /// Copy propagation: a variable defined by `id` shares the value number of its argument
+    pub propagate: bool,
+    /// Commutativity: the arguments of commutative operations are sorted so that `add a b` and `add b a` are the same value
+    pub canonicalize: bool,
+    /// Constant folding: values whose arguments are all constants are replaced by `const`, along with a few algebraic identities like `eq x x`
+    pub fold: bool,
+}
+
+impl Default for LvnOptions {
+    fn default() -> Self {
+        Self {
+            propagate: true,
+            canonicalize: true,
+            fold: true,
+        }
+    }
+}
+
+/// Runs local value numbering over every function of `program`
+pub fn lvn(program: &mut Program, options: LvnOptions) {
+    for f in &mut program.functions {
+        lvn_function(f, options);
+    }
+}
+
+/// Runs local value numbering over each basic block of `function`
+///
+/// Redundant computations are replaced with copies(or constants), and arguments are rewritten to the first variable known to hold their value.
+/// Instructions are never removed, so this is best followed by dead code elimination.
+/// Variables which are reassigned later in the same block are renamed to fresh `lvn.N` variables so that their values stay available.
+pub fn lvn_function(function: &mut Function, options: LvnOptions) {
+    let mut taken: HashSet<String> = function.args.iter().map(|a| a.name.clone()).collect();
+    for code in &function.instrs {
+        if let Code::Instruction(i) = code {
+            taken.extend(i.args().iter().cloned());
+            taken.extend(i.dest().map(ToString::to_string));
+        }
+    }
+
+    map_blocks(&mut function.instrs, |block| {
+        lvn_block(block, options, &mut taken);
+    });
+}
+
+// A computation in terms of the value numbers of its arguments
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Value {
+    op: ValueOps,
+    args: Vec<usize>,
+}
+
+// Whether the result of `op` only depends on its arguments(or, for `load`, on its arguments and the current state of memory)
+#[cfg_attr(
+    not(feature = "other"),
+    expect(
+        clippy::trivially_copy_pass_by_ref,
+        reason = "Operations are only `Copy` without the `other` feature"
+    )
+)]
+const fn is_numberable(op: &ValueOps) -> bool {
+    match op {
+        ValueOps::Call => false,
+        #[cfg(feature = "memory")]
+        ValueOps::Alloc => false,
+        #[cfg(feature = "ssa")]
+        ValueOps::Phi => false,
+        #[cfg(feature = "ssa2")]
+        ValueOps::Get | ValueOps::Undef => false,
+        #[cfg(feature = "other")]
+        ValueOps::Other(_) => false,
+        _ => true,
+    }
+}
+
+// Whether `instr` may write to memory, which invalidates the values of any `load`s
+#[cfg(feature = "memory")]
+const fn clobbers_memory(instr: &Instruction) -> bool {
+    match instr {
+        Instruction::Value {
+            op: ValueOps::Call, ..
+        }
+        | Instruction::Effect {
+            op: EffectOps::Call | EffectOps::Store | EffectOps::Free,
+            ..
+        } => true,
+        // Nothing is known about what operations from other extensions do
+        #[cfg(feature = "other")]
+        Instruction::Value {
+            op: ValueOps::Other(_),
+            ..
+        }
+        | Instruction::Effect {
+            op: EffectOps::Other(_),
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct Numbering {
+    // The current value number of every variable
+    var2num: HashMap<String, usize>,
+    // The value number of each computation which is still held by some variable
+    value2num: HashMap<Value, usize>,
+    // The variables holding each value number, the first of which is the canonical one
+    num2vars: Vec<Vec<String>>,
+    // The value numbers which are known constants
+    num2const: HashMap<usize, Literal>,
+}
+
+impl Numbering {
+    // Gives `var` a fresh value number, held by `holder`
+    fn add(&mut self, var: String, holder: String) -> usize {
+        let num = self.num2vars.len();
+        self.var2num.insert(var, num);
+        self.num2vars.push(vec![holder]);
+        num
+    }
+
+    fn fold(&self, value: &Value, op_type: &Type) -> Option<Literal> {
+        let consts: Option<Vec<&Literal>> =
+            value.args.iter().map(|n| self.num2const.get(n)).collect();
+        if let Some(consts) = consts {
+            // Infinities and `NaN` can't be written back as a `const` in JSON
+            return fold(&value.op, op_type, &consts).filter(|c| match c {
+                #[cfg(feature = "float")]
+                Literal::Float(f) => f.is_finite(),
+                _ => true,
+            });
+        }
+        match (&value.op, value.args.as_slice()) {
+            // Floats are left out because `NaN` is not equal to itself
+            (ValueOps::Eq | ValueOps::Le | ValueOps::Ge, [a, b]) if a == b => {
+                Some(Literal::Bool(true))
+            }
+            #[cfg(feature = "char")]
+            (ValueOps::Ceq | ValueOps::Cle | ValueOps::Cge, [a, b]) if a == b => {
+                Some(Literal::Bool(true))
+            }
+            // `and` with a false argument and `or` with a true argument short circuit
+            (ValueOps::And | ValueOps::Or, [a, b]) => {
+                let short_circuit = Literal::Bool(value.op == ValueOps::Or);
+                [a, b]
+                    .iter()
+                    .any(|n| self.num2const.get(n) == Some(&short_circuit))
+                    .then_some(short_circuit)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn lvn_block(block: &mut [Instruction], options: LvnOptions, taken: &mut HashSet<String>) {
+    let mut numbering = Numbering::default();
+
+    // Variables which are read before they are written in this block are their own canonical source
+    let mut written = HashSet::new();
+    for instr in block.iter() {
+        for a in reads(instr) {
+            if !written.contains(a) && !numbering.var2num.contains_key(a) {
+                numbering.add(a.clone(), a.clone());
+            }
+        }
+        if let Some(d) = instr.dest() {
+            written.insert(d.to_string());
+        }
+    }
+
+    // Whether each instruction is the last write to its destination in this block
+    let mut seen = HashSet::new();
+    let mut last_write = vec![false; block.len()];
+    for (i, instr) in block.iter().enumerate().rev() {
+        if let Some(d) = instr.dest() {
+            last_write[i] = seen.insert(d.to_string());
+        }
+    }
+
+    for (instr, last_write) in block.iter_mut().zip(last_write) {
+        #[cfg(feature = "memory")]
+        if clobbers_memory(instr) {
+            numbering.value2num.retain(|v, _| v.op != ValueOps::Load);
+        }
+
+        let arg_nums: Vec<usize> = reads(instr).map(|a| numbering.var2num[a]).collect();
+        for (a, n) in reads_mut(instr).zip(&arg_nums) {
+            if let Some(canonical) = numbering.num2vars[*n].first() {
+                a.clone_from(canonical);
+            }
+        }
+
+        let Some(dest) = instr.dest().map(ToString::to_string) else {
+            continue;
+        };
+        // The old value of `dest` can no longer be found in it
+        for vars in &mut numbering.num2vars {
+            vars.retain(|v| *v != dest);
+        }
+
+        let value = match instr {
+            Instruction::Value { op, .. } if is_numberable(op) => {
+                let mut args = arg_nums;
+                if options.canonicalize && is_commutative(op) {
+                    args.sort_unstable();
+                }
+                Some(Value {
+                    op: ValueOps::clone(op),
+                    args,
+                })
+            }
+            _ => None,
+        };
+
+        if let Some(value) = &value {
+            let existing = if options.propagate && value.op == ValueOps::Id {
+                value.args.first().copied()
+            } else {
+                numbering.value2num.get(value).copied()
+            };
+            if let Some(num) = existing {
+                numbering.var2num.insert(dest.clone(), num);
+                if let Some(c) = numbering.num2const.get(&num) {
+                    replace_with_const(instr, c.clone());
+                } else if let Some(canonical) = numbering.num2vars[num].first() {
+                    replace_with_id(instr, canonical.clone());
+                    numbering.num2vars[num].push(dest);
+                }
+                continue;
+            }
+        }
+
+        // Keep the value available under a fresh name if `dest` is reassigned later in the block
+        let var = if last_write {
+            dest.clone()
+        } else {
+            fresh_lvn_name(numbering.num2vars.len(), taken)
+        };
+        let num = numbering.add(dest, var.clone());
+        if let Some(d) = instr.dest_mut() {
+            *d = var;
+        }
+
+        match (instr, value) {
+            (Instruction::Constant { value: c, .. }, _) => {
+                numbering.num2const.insert(num, c.clone());
+            }
+            (instr @ Instruction::Value { .. }, Some(value)) => {
+                let Instruction::Value { op_type, .. } = &*instr else {
+                    unreachable!()
+                };
+                if let Some(c) = options
+                    .fold
+                    .then(|| numbering.fold(&value, op_type))
+                    .flatten()
+                {
+                    numbering.num2const.insert(num, c.clone());
+                    replace_with_const(instr, c);
+                } else {
+                    numbering.value2num.insert(value, num);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// `lvn.{num}` unless that is already a variable in the function
+fn fresh_lvn_name(num: usize, taken: &mut HashSet<String>) -> String {
+    let name = format!("lvn.{num}");
+    let name = if taken.contains(&name) {
+        fresh_name(&format!("{name}."), |n| taken.contains(n))
+    } else {
+        name
+    };
+    taken.insert(name.clone());
+    name
+}
+
+// The arguments of `instr` which are reads of variables in this block
+// The arguments of `phi` are read at the end of other blocks
+fn reads(instr: &Instruction) -> impl Iterator<Item = &String> {
+    instr.uses().iter().skip(skipped_args(instr))
+}
+
+fn reads_mut(instr: &mut Instruction) -> impl Iterator<Item = &mut String> {
+    let skip = skipped_args(instr);
+    instr.uses_mut().iter_mut().skip(skip)
+}
+
+const fn skipped_args(instr: &Instruction) -> usize {
+    match instr {
+        #[cfg(feature = "ssa")]
+        Instruction::Value {
+            op: ValueOps::Phi, ..
+        } => usize::MAX,
+        _ => 0,
+    }
+}
+
+fn replace_with_const(instr: &mut Instruction, value: Literal) {
+    if let Instruction::Value {
+        dest,
+        op_type,
+        #[cfg(feature = "position")]
+        pos,
+        ..
+    } = instr
+    {
+        *instr = Instruction::Constant {
+            dest: std::mem::take(dest),
+            op: ConstOps::Const,
+            #[cfg(feature = "position")]
+            pos: pos.take(),
+            const_type: op_type.clone(),
+            value,
+        };
+    }
+}
+
+fn replace_with_id(instr: &mut Instruction, var: String) {
+    if let Instruction::Value {
+        args,
+        funcs,
+        labels,
+        op,
+        ..
+    } = instr
+    {
+        *args = vec![var];
+        funcs.clear();
+        labels.clear();
+        *op = ValueOps::Id;
+    }
+}
@@ -0,0 +1,634 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+};
+
+use thiserror::Error;
+
+use crate::{
+    absint::{interpret, AbstractInterpretation, Lattice},
+    callgraph::CallGraph,
+    cfg::{Cfg, CfgError},
+    dataflow::Location,
+    error::fmt_positional,
+    EffectOps, Instruction, Literal, Position, Program, Type, ValueOps,
+};
+
+/// The misuses of memory which [`check_program`] looks for
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The memory allocated here is never freed, stored, passed on, or returned, on any path through the function
+    #[error("Memory allocated here is never freed")]
+    Leak,
+
+    /// {0} is freed, but it has already been freed on every path here
+    #[error("Double free of `{0}`, which has already been freed")]
+    DoubleFree(String),
+
+    /// {0} is read or written with `load` or `store`, but it has already been freed on every path here
+    #[error("Use after free of `{0}`, which has already been freed")]
+    UseAfterFree(String),
+
+    /// {0} is freed, but it points {1} entries past the start of its allocation
+    #[error("Tried to free `{0}` at offset `{1}`. Offset must be 0.")]
+    IllegalFree(String, i64),
+}
+
+impl MemoryError {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn add_pos(self, pos_var: Option<Position>) -> PositionalMemoryError {
+        PositionalMemoryError {
+            e: self,
+            pos: pos_var,
+        }
+    }
+}
+
+/// Wraps [`MemoryError`] to optionally provide source code positions if they are available.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct PositionalMemoryError {
+    #[doc(hidden)]
+    pub e: MemoryError,
+    #[doc(hidden)]
+    pub pos: Option<Position>,
+}
+
+impl Display for PositionalMemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_positional(f, &self.e, self.pos.as_ref())
+    }
+}
+
+/// Statically finds the memory errors that `brilirs` would only find while running `program`: leaks, double frees, uses after free, and frees of pointers which were moved with `ptradd`
+///
+/// Each function is checked on its own by tracking which allocations each pointer may point to and whether those allocations are live or freed, where every allocation made by the same instruction is treated as one.
+/// Functions are visited bottom-up, and each call uses a summary of its callee which says whether the callee frees or keeps the memory passed to it and whether it returns memory that the caller must free.
+/// Calls within a recursive cycle and calls to functions outside of `program` are assumed to do anything to the memory passed to them.
+///
+/// Only definite errors are reported: a `free`, `load`, or `store` is only reported when its pointer has been freed on every path to it, and an allocation is only reported as leaked when no path through the function frees it or lets it escape by storing it, passing it to a function which may keep it, or returning it.
+/// # Errors
+/// Returns an error if the labels of a function don't resolve
+pub fn check_program(program: &Program) -> Result<Vec<PositionalMemoryError>, CfgError> {
+    let graph = CallGraph::new(program);
+    let mut errors = vec![Vec::new(); program.functions.len()];
+    let mut summaries: HashMap<String, Summary> = HashMap::new();
+    for scc in graph.sccs() {
+        // Functions in the same component only see each other's summaries once they have all been checked
+        let mut checked = Vec::with_capacity(scc.len());
+        for &f in scc {
+            let function = &program.functions[f];
+            let cfg = Cfg::try_from(function.clone())?;
+            let (e, summary) = check_cfg(&cfg, &summaries);
+            errors[f] = e;
+            checked.push((function.name.clone(), summary));
+        }
+        summaries.extend(checked);
+    }
+    Ok(errors.into_iter().flatten().collect())
+}
+
+// What a function does with the memory its caller gives it
+#[derive(Debug, Clone)]
+struct Summary {
+    // What happens to the memory that each argument points to
+    args: Vec<ArgEffect>,
+    // Whether every path returns a pointer to the start of memory which the function allocated and the caller now owns
+    returns_allocation: bool,
+    // Whether the function may free memory which it didn't get directly as an argument, like memory that it loaded a pointer to
+    frees_unknown: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgEffect {
+    // The memory is still live and owned by the caller afterwards
+    Kept,
+    // The memory has been freed on every path
+    Freed,
+    // The memory may have been freed, stored, or returned
+    Escaped,
+}
+
+// Where the memory a pointer points into came from, which stands for every allocation made there
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Site {
+    // The memory that the pointer argument with this index points into, which the caller owns
+    Arg(usize),
+    // An `alloc`, or a `call` of a function which returns memory that the caller must free
+    Alloc(Location),
+}
+
+// What may have happened to the allocations made at a site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "Each flag is a separate fact which is joined on its own"
+)]
+struct Allocation {
+    live: bool,
+    freed: bool,
+    // Whether a pointer to it may have been stored, passed on, or returned, after which it can be freed through other pointers
+    escaped: bool,
+    // Whether it may stand for more than one allocation at once, so freeing one of them doesn't free the others
+    many: bool,
+}
+
+impl Allocation {
+    const fn join(&mut self, other: Self) {
+        self.live |= other.live;
+        self.freed |= other.freed;
+        self.escaped |= other.escaped;
+        self.many |= other.many;
+    }
+
+    const fn definitely_freed(self) -> bool {
+        self.freed && !self.live && !self.many
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pointer {
+    sites: BTreeSet<Site>,
+    // How far the pointer is from the start of its allocation, if that is known
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct MemoryState {
+    // The pointers whose allocations are known, where pointers which are missing may point anywhere
+    pointers: BTreeMap<String, Pointer>,
+    // The integers whose values are known, which are used for the offsets of pointers
+    ints: BTreeMap<String, i64>,
+    allocations: BTreeMap<Site, Allocation>,
+}
+
+impl Lattice for MemoryState {
+    fn join(&mut self, other: &Self) {
+        self.pointers.retain(|var, p| {
+            let Some(o) = other.pointers.get(var) else {
+                return false;
+            };
+            p.sites.extend(&o.sites);
+            if p.offset != o.offset {
+                p.offset = None;
+            }
+            true
+        });
+        self.ints.retain(|var, i| other.ints.get(var) == Some(i));
+        for (site, a) in &other.allocations {
+            self.allocations
+                .entry(*site)
+                .and_modify(|allocation| allocation.join(*a))
+                .or_insert(*a);
+        }
+    }
+
+    // The lattice has finite height, so joining is enough
+    fn widen(&mut self, other: &Self) {
+        self.join(other);
+    }
+}
+
+impl MemoryState {
+    // Makes `dest` an unknown value, before it is assigned
+    fn forget(&mut self, dest: &str) {
+        self.pointers.remove(dest);
+        self.ints.remove(dest);
+    }
+
+    // The sites that `var` points into, which is empty if it may point anywhere
+    fn sites(&self, var: &str) -> BTreeSet<Site> {
+        self.pointers
+            .get(var)
+            .map(|p| p.sites.clone())
+            .unwrap_or_default()
+    }
+
+    // Whether `var` points into memory which has been freed on every path
+    fn freed(&self, var: &str) -> bool {
+        let sites = self.sites(var);
+        !sites.is_empty()
+            && sites.iter().all(|s| {
+                self.allocations
+                    .get(s)
+                    .is_some_and(|a| a.definitely_freed())
+            })
+    }
+
+    fn alloc(&mut self, dest: &str, site: Site) {
+        self.forget(dest);
+        let shared = self.pointers.values().any(|p| p.sites.contains(&site));
+        let a = self.allocations.entry(site).or_default();
+        if shared || a.escaped || a.many {
+            // Another allocation from this site may still be around
+            a.live = true;
+            a.many = true;
+        } else {
+            *a = Allocation {
+                live: true,
+                ..Allocation::default()
+            };
+        }
+        self.pointers.insert(
+            dest.to_string(),
+            Pointer {
+                sites: BTreeSet::from([site]),
+                offset: Some(0),
+            },
+        );
+    }
+
+    // Frees the memory `var` points to, returning `false` if it may point anywhere
+    fn free(&mut self, var: &str) -> bool {
+        let sites = self.sites(var);
+        if sites.is_empty() {
+            self.free_escaped();
+            return false;
+        }
+        let strong = sites.len() == 1;
+        for s in sites {
+            let a = self.allocations.entry(s).or_default();
+            a.freed = true;
+            if strong && !a.many {
+                a.live = false;
+            }
+        }
+        true
+    }
+
+    // Any memory which was escaped or given by the caller may have been freed through another pointer
+    fn free_escaped(&mut self) {
+        for (site, a) in &mut self.allocations {
+            if a.escaped || matches!(site, Site::Arg(_)) {
+                a.freed = true;
+            }
+        }
+    }
+
+    // Lets the memory `var` points to escape to somewhere else, where it may be freed
+    fn escape(&mut self, var: &str) {
+        for s in self.sites(var) {
+            self.allocations.entry(s).or_default().escaped = true;
+        }
+    }
+
+    // Applies the effects of calling a function summarized by `summary`, or an unknown function if it is `None`, on `args`
+    fn call(&mut self, summary: Option<&Summary>, args: &[String]) {
+        for (i, a) in args.iter().enumerate() {
+            match summary.and_then(|s| s.args.get(i)) {
+                Some(ArgEffect::Kept) => {}
+                Some(ArgEffect::Freed) => {
+                    self.free(a);
+                }
+                Some(ArgEffect::Escaped) | None => {
+                    self.escape(a);
+                    for s in self.sites(a) {
+                        self.allocations.entry(s).or_default().freed = true;
+                    }
+                }
+            }
+        }
+        if summary.is_none_or(|s| s.frees_unknown) {
+            self.free_escaped();
+        }
+    }
+}
+
+struct MemoryAnalysis<'a> {
+    summaries: &'a HashMap<String, Summary>,
+}
+
+impl MemoryAnalysis<'_> {
+    fn summary(&self, funcs: &[String]) -> Option<&Summary> {
+        funcs.first().and_then(|f| self.summaries.get(f))
+    }
+}
+
+impl AbstractInterpretation for MemoryAnalysis<'_> {
+    type State = MemoryState;
+
+    fn entry(&self, cfg: &Cfg) -> Self::State {
+        let mut state = MemoryState::default();
+        for (i, a) in cfg.args.iter().enumerate() {
+            if matches!(a.arg_type, Type::Pointer(_)) {
+                state.pointers.insert(
+                    a.name.clone(),
+                    Pointer {
+                        sites: BTreeSet::from([Site::Arg(i)]),
+                        offset: None,
+                    },
+                );
+                state.allocations.insert(
+                    Site::Arg(i),
+                    Allocation {
+                        live: true,
+                        ..Allocation::default()
+                    },
+                );
+            }
+        }
+        state
+    }
+
+    fn transfer(&self, state: &mut Self::State, instr: &Instruction, loc: Location) {
+        match instr {
+            Instruction::Constant {
+                dest,
+                value: Literal::Int(i),
+                const_type: Type::Int,
+                ..
+            } => {
+                state.forget(dest);
+                state.ints.insert(dest.clone(), *i);
+            }
+            #[cfg(feature = "ssa")]
+            Instruction::Value {
+                op: ValueOps::Phi, ..
+            } => {}
+            Instruction::Value {
+                op: ValueOps::Alloc,
+                dest,
+                ..
+            } => state.alloc(dest, Site::Alloc(loc)),
+            Instruction::Value {
+                op: op @ (ValueOps::Id | ValueOps::PtrAdd),
+                dest,
+                args,
+                ..
+            } => {
+                let mut pointer = state.pointers.get(&args[0]).cloned();
+                let int = state.ints.get(&args[0]).copied();
+                if let Some(p) = &mut pointer {
+                    if *op == ValueOps::PtrAdd {
+                        let by = state.ints.get(&args[1]);
+                        p.offset = p.offset.zip(by).map(|(o, by)| o.wrapping_add(*by));
+                    }
+                }
+                state.forget(dest);
+                state.pointers.extend(pointer.map(|p| (dest.clone(), p)));
+                state.ints.extend(int.map(|i| (dest.clone(), i)));
+            }
+            Instruction::Value {
+                op: ValueOps::Call,
+                dest,
+                args,
+                funcs,
+                ..
+            } => {
+                let summary = self.summary(funcs);
+                state.call(summary, args);
+                if summary.is_some_and(|s| s.returns_allocation) {
+                    state.alloc(dest, Site::Alloc(loc));
+                } else {
+                    state.forget(dest);
+                }
+            }
+            Instruction::Constant { dest, .. } | Instruction::Value { dest, .. } => {
+                state.forget(dest);
+            }
+            Instruction::Effect {
+                op: EffectOps::Free,
+                args,
+                ..
+            } => {
+                state.free(&args[0]);
+            }
+            Instruction::Effect {
+                op: EffectOps::Store,
+                args,
+                ..
+            } => state.escape(&args[1]),
+            Instruction::Effect {
+                op: EffectOps::Call,
+                args,
+                funcs,
+                ..
+            } => state.call(self.summary(funcs), args),
+            #[cfg(feature = "other")]
+            Instruction::Effect {
+                op: EffectOps::Other(_),
+                args,
+                ..
+            } => state.call(None, args),
+            Instruction::Effect { .. } => {}
+        }
+    }
+
+    fn edge(&self, state: &mut Self::State, cfg: &Cfg, from: usize, to: usize) -> bool {
+        assign_phis(state, cfg, from, to);
+        true
+    }
+}
+
+// Gives the destination of each `phi` at the start of `to` the value of its argument from `from`
+#[cfg(feature = "ssa")]
+fn assign_phis(state: &mut MemoryState, cfg: &Cfg, from: usize, to: usize) {
+    let from = &cfg.blocks[from].name;
+    let mut values = Vec::new();
+    for instr in &cfg.blocks[to].instrs {
+        if let Instruction::Value {
+            op: ValueOps::Phi,
+            dest,
+            args,
+            labels,
+            ..
+        } = instr
+        {
+            let arg = args.iter().zip(labels).find(|(_, l)| *l == from);
+            let value =
+                arg.map(|(a, _)| (state.pointers.get(a).cloned(), state.ints.get(a).copied()));
+            values.push((dest, value.unwrap_or_default()));
+        }
+    }
+    for (dest, (pointer, int)) in values {
+        state.forget(dest);
+        state.pointers.extend(pointer.map(|p| (dest.clone(), p)));
+        state.ints.extend(int.map(|i| (dest.clone(), i)));
+    }
+}
+
+#[cfg(not(feature = "ssa"))]
+const fn assign_phis(_: &mut MemoryState, _: &Cfg, _: usize, _: usize) {}
+
+// Finds the errors in `cfg` and summarizes what it does with memory for its callers
+fn check_cfg(
+    cfg: &Cfg,
+    summaries: &HashMap<String, Summary>,
+) -> (Vec<PositionalMemoryError>, Summary) {
+    let analysis = MemoryAnalysis { summaries };
+    let result = interpret(&analysis, cfg);
+    let mut errors = Vec::new();
+    let mut frees_unknown = false;
+    for (block, state) in result.ins.iter().enumerate() {
+        let Some(mut state) = state.clone() else {
+            continue;
+        };
+        for (index, instr) in cfg.blocks[block].instrs.iter().enumerate() {
+            let (e, unknown) = check(&analysis, &state, instr);
+            errors.extend(e.map(|e| e.add_pos(instr_pos(instr))));
+            frees_unknown |= unknown;
+            analysis.transfer(&mut state, instr, Location { block, index });
+        }
+    }
+
+    // The state at the end of each block which leaves the function, along with the sites of the pointer it returns
+    let exits: Vec<(&MemoryState, BTreeSet<Site>)> = cfg
+        .blocks
+        .iter()
+        .zip(&result.outs)
+        .filter(|(b, _)| b.successors.is_empty())
+        .filter_map(|(b, state)| {
+            let state = state.as_ref()?;
+            let returned = match b.terminator() {
+                Some(Instruction::Effect {
+                    op: EffectOps::Return,
+                    args,
+                    ..
+                }) => args.first().map(|a| state.sites(a)).unwrap_or_default(),
+                _ => BTreeSet::new(),
+            };
+            Some((state, returned))
+        })
+        .collect();
+
+    let sites: BTreeSet<Site> = exits
+        .iter()
+        .flat_map(|(state, _)| state.allocations.keys().copied())
+        .collect();
+    for site in sites {
+        let Site::Alloc(loc) = site else {
+            continue;
+        };
+        let leaked = exits.iter().all(|(state, returned)| {
+            !returned.contains(&site)
+                && state
+                    .allocations
+                    .get(&site)
+                    .is_none_or(|a| !a.freed && !a.escaped)
+        });
+        if leaked {
+            let instr = &cfg.blocks[loc.block].instrs[loc.index];
+            errors.push(MemoryError::Leak.add_pos(instr_pos(instr)));
+        }
+    }
+
+    let summary = Summary {
+        args: (0..cfg.args.len())
+            .map(|i| arg_effect(&exits, Site::Arg(i)))
+            .collect(),
+        returns_allocation: matches!(cfg.return_type, Some(Type::Pointer(_)))
+            && !exits.is_empty()
+            && exits.iter().all(|(state, returned)| {
+                !returned.is_empty()
+                    && returned.iter().all(|s| {
+                        matches!(s, Site::Alloc(_))
+                            && state.allocations.get(s)
+                                == Some(&Allocation {
+                                    live: true,
+                                    ..Allocation::default()
+                                })
+                    })
+            }),
+        frees_unknown,
+    };
+    (errors, summary)
+}
+
+// What the function does to the memory at `site`, given the states at the end of each block which leaves it
+fn arg_effect(exits: &[(&MemoryState, BTreeSet<Site>)], site: Site) -> ArgEffect {
+    let mut effects = exits
+        .iter()
+        .map(|(state, returned)| match state.allocations.get(&site) {
+            Some(a) if a.escaped || returned.contains(&site) => ArgEffect::Escaped,
+            Some(a) if a.definitely_freed() => ArgEffect::Freed,
+            Some(a) if a.freed => ArgEffect::Escaped,
+            _ => ArgEffect::Kept,
+        });
+    let first = effects.next().unwrap_or(ArgEffect::Kept);
+    if effects.all(|e| e == first) {
+        first
+    } else {
+        ArgEffect::Escaped
+    }
+}
+
+// The error `instr` definitely causes in `state`, along with whether it may free memory which isn't known
+fn check(
+    analysis: &MemoryAnalysis,
+    state: &MemoryState,
+    instr: &Instruction,
+) -> (Option<MemoryError>, bool) {
+    let double_free = |var: &String| {
+        if state.freed(var) {
+            Some(MemoryError::DoubleFree(var.clone()))
+        } else {
+            match state.pointers.get(var).and_then(|p| p.offset) {
+                Some(offset) if offset != 0 => Some(MemoryError::IllegalFree(var.clone(), offset)),
+                _ => None,
+            }
+        }
+    };
+    match instr {
+        Instruction::Effect {
+            op: EffectOps::Free,
+            args,
+            ..
+        } => (
+            double_free(&args[0]),
+            !state.pointers.contains_key(&args[0]),
+        ),
+        Instruction::Value {
+            op: ValueOps::Load,
+            args,
+            ..
+        }
+        | Instruction::Effect {
+            op: EffectOps::Store,
+            args,
+            ..
+        } => (
+            state
+                .freed(&args[0])
+                .then(|| MemoryError::UseAfterFree(args[0].clone())),
+            false,
+        ),
+        Instruction::Value {
+            op: ValueOps::Call,
+            args,
+            funcs,
+            ..
+        }
+        | Instruction::Effect {
+            op: EffectOps::Call,
+            args,
+            funcs,
+            ..
+        } => {
+            let summary = analysis.summary(funcs);
+            let freed = summary.and_then(|s| {
+                args.iter()
+                    .zip(&s.args)
+                    .filter(|(_, e)| **e == ArgEffect::Freed)
+                    .find_map(|(a, _)| state.freed(a).then(|| MemoryError::DoubleFree(a.clone())))
+            });
+            (freed, summary.is_none_or(|s| s.frees_unknown))
+        }
+        #[cfg(feature = "other")]
+        Instruction::Effect {
+            op: EffectOps::Other(_),
+            ..
+        } => (None, true),
+        _ => (None, false),
+    }
+}
+
+#[cfg(feature = "position")]
+fn instr_pos(instr: &Instruction) -> Option<Position> {
+    instr.get_pos()
+}
+
+#[cfg(not(feature = "position"))]
+const fn instr_pos(_: &Instruction) -> Option<Position> {
+    None
+}
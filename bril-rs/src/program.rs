@@ -7,6 +7,8 @@ use std::{
 use serde::{Deserialize, Serialize};
 
 use crate::conversion::ConversionError;
+#[cfg(feature = "other")]
+use crate::AbstractType;
 
 /// Equivalent to a file of bril code
 #[cfg_attr(not(feature = "float"), derive(Eq))]
@@ -258,6 +260,103 @@ impl Instruction {
     }
 }
 
+impl Instruction {
+    /// The variable this instruction writes to, if it has one
+    #[must_use]
+    pub fn dest(&self) -> Option<&str> {
+        match self {
+            Self::Constant { dest, .. } | Self::Value { dest, .. } => Some(dest),
+            Self::Effect { .. } => None,
+        }
+    }
+
+    /// A mutable reference to the variable this instruction writes to, if it has one
+    pub const fn dest_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::Constant { dest, .. } | Self::Value { dest, .. } => Some(dest),
+            Self::Effect { .. } => None,
+        }
+    }
+
+    /// The variables this instruction reads from
+    #[must_use]
+    pub fn args(&self) -> &[String] {
+        match self {
+            Self::Constant { .. } => &[],
+            Self::Value { args, .. } | Self::Effect { args, .. } => args,
+        }
+    }
+
+    /// Mutable references to the variables this instruction reads from
+    pub fn args_mut(&mut self) -> &mut [String] {
+        match self {
+            Self::Constant { .. } => &mut [],
+            Self::Value { args, .. } | Self::Effect { args, .. } => args,
+        }
+    }
+
+    /// Like [`Self::args`], but without the first argument of `set`, which names a shadow variable instead of reading a variable
+    #[must_use]
+    pub fn uses(&self) -> &[String] {
+        match self {
+            #[cfg(feature = "ssa2")]
+            Self::Effect {
+                op: EffectOps::Set,
+                args,
+                ..
+            } => args.get(1..).unwrap_or_default(),
+            _ => self.args(),
+        }
+    }
+
+    /// Mutable references to the variables in [`Self::uses`]
+    pub fn uses_mut(&mut self) -> &mut [String] {
+        match self {
+            #[cfg(feature = "ssa2")]
+            Self::Effect {
+                op: EffectOps::Set,
+                args,
+                ..
+            } => args.get_mut(1..).unwrap_or_default(),
+            _ => self.args_mut(),
+        }
+    }
+
+    /// The functions this instruction refers to
+    #[must_use]
+    pub fn funcs(&self) -> &[String] {
+        match self {
+            Self::Constant { .. } => &[],
+            Self::Value { funcs, .. } | Self::Effect { funcs, .. } => funcs,
+        }
+    }
+
+    /// Mutable references to the functions this instruction refers to
+    pub fn funcs_mut(&mut self) -> &mut [String] {
+        match self {
+            Self::Constant { .. } => &mut [],
+            Self::Value { funcs, .. } | Self::Effect { funcs, .. } => funcs,
+        }
+    }
+
+    /// The labels this instruction refers to
+    #[must_use]
+    pub fn labels(&self) -> &[String] {
+        match self {
+            Self::Constant { .. } => &[],
+            Self::Value { labels, .. } | Self::Effect { labels, .. } => labels,
+        }
+    }
+
+    /// Mutable references to the labels this instruction refers to
+    pub fn labels_mut(&mut self) -> &mut [String] {
+        match self {
+            Self::Constant { .. } => &mut [],
+            Self::Value { labels, .. } | Self::Effect { labels, .. } => labels,
+        }
+    }
+}
+
 impl Display for Instruction {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -334,7 +433,8 @@ impl Display for ConstOps {
 }
 
 /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#effect-operation>
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "other"), derive(Copy))]
 #[serde(rename_all = "lowercase")]
 pub enum EffectOps {
     /// <https://capra.cs.cornell.edu/bril/lang/core.html#control>
@@ -367,6 +467,13 @@ pub enum EffectOps {
     /// <https://capra.cs.cornell.edu/bril/lang/spec.html#operations>
     #[cfg(feature = "speculate")]
     Guard,
+    /// <https://capra.cs.cornell.edu/bril/lang/ssa.html#operations>
+    #[cfg(feature = "ssa2")]
+    Set,
+    /// Any other effect operation, which is kept by name so that it is written back out unchanged
+    #[cfg(feature = "other")]
+    #[serde(untagged)]
+    Other(String),
 }
 
 impl Display for EffectOps {
@@ -388,6 +495,10 @@ impl Display for EffectOps {
             Self::Commit => write!(f, "commit"),
             #[cfg(feature = "speculate")]
             Self::Guard => write!(f, "guard"),
+            #[cfg(feature = "ssa2")]
+            Self::Set => write!(f, "set"),
+            #[cfg(feature = "other")]
+            Self::Other(op) => write!(f, "{op}"),
         }
     }
 }
@@ -413,13 +524,19 @@ impl FromStr for EffectOps {
             "commit" => Self::Commit,
             #[cfg(feature = "speculate")]
             "guard" => Self::Guard,
+            #[cfg(feature = "ssa2")]
+            "set" => Self::Set,
+            #[cfg(feature = "other")]
+            e => Self::Other(e.to_string()),
+            #[cfg(not(feature = "other"))]
             e => Err(ConversionError::InvalidEffectOps(e.to_string()))?,
         })
     }
 }
 
 /// <https://capra.cs.cornell.edu/bril/lang/syntax.html#value-operation>
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "other"), derive(Copy))]
 #[serde(rename_all = "lowercase")]
 pub enum ValueOps {
     /// <https://capra.cs.cornell.edu/bril/lang/core.html#arithmetic>
@@ -453,6 +570,12 @@ pub enum ValueOps {
     /// <https://capra.cs.cornell.edu/bril/lang/ssa.html#operations>
     #[cfg(feature = "ssa")]
     Phi,
+    /// <https://capra.cs.cornell.edu/bril/lang/ssa.html#operations>
+    #[cfg(feature = "ssa2")]
+    Get,
+    /// <https://capra.cs.cornell.edu/bril/lang/ssa.html#operations>
+    #[cfg(feature = "ssa2")]
+    Undef,
     /// <https://capra.cs.cornell.edu/bril/lang/float.html#operations>
     #[cfg(feature = "float")]
     Fadd,
@@ -510,6 +633,10 @@ pub enum ValueOps {
     /// <https://capra.cs.cornell.edu/bril/lang/memory.html#operations>
     #[cfg(feature = "memory")]
     PtrAdd,
+    /// Any other value operation, which is kept by name so that it is written back out unchanged
+    #[cfg(feature = "other")]
+    #[serde(untagged)]
+    Other(String),
 }
 
 impl Display for ValueOps {
@@ -531,6 +658,10 @@ impl Display for ValueOps {
             Self::Id => write!(f, "id"),
             #[cfg(feature = "ssa")]
             Self::Phi => write!(f, "phi"),
+            #[cfg(feature = "ssa2")]
+            Self::Get => write!(f, "get"),
+            #[cfg(feature = "ssa2")]
+            Self::Undef => write!(f, "undef"),
             #[cfg(feature = "float")]
             Self::Fadd => write!(f, "fadd"),
             #[cfg(feature = "float")]
@@ -569,6 +700,8 @@ impl Display for ValueOps {
             Self::Load => write!(f, "load"),
             #[cfg(feature = "memory")]
             Self::PtrAdd => write!(f, "ptradd"),
+            #[cfg(feature = "other")]
+            Self::Other(op) => write!(f, "{op}"),
         }
     }
 }
@@ -594,6 +727,10 @@ impl FromStr for ValueOps {
             "sub" => Self::Sub,
             #[cfg(feature = "ssa")]
             "phi" => Self::Phi,
+            #[cfg(feature = "ssa2")]
+            "get" => Self::Get,
+            #[cfg(feature = "ssa2")]
+            "undef" => Self::Undef,
             #[cfg(feature = "float")]
             "fadd" => Self::Fadd,
             #[cfg(feature = "float")]
@@ -632,6 +769,9 @@ impl FromStr for ValueOps {
             "load" => Self::Load,
             #[cfg(feature = "memory")]
             "ptradd" => Self::PtrAdd,
+            #[cfg(feature = "other")]
+            v => Self::Other(v.to_string()),
+            #[cfg(not(feature = "other"))]
             v => Err(ConversionError::InvalidValueOps(v.to_string()))?,
         })
     }
@@ -655,6 +795,10 @@ pub enum Type {
     #[cfg(feature = "memory")]
     #[serde(rename = "ptr")]
     Pointer(Box<Self>),
+    /// Any other type, which is kept as it was written so that it is written back out unchanged
+    #[cfg(feature = "other")]
+    #[serde(untagged)]
+    Other(AbstractType),
 }
 
 impl Display for Type {
@@ -668,6 +812,8 @@ impl Display for Type {
             Self::Char => write!(f, "char"),
             #[cfg(feature = "memory")]
             Self::Pointer(tpe) => write!(f, "ptr<{tpe}>"),
+            #[cfg(feature = "other")]
+            Self::Other(tpe) => write!(f, "{tpe}"),
         }
     }
 }
@@ -683,6 +829,9 @@ impl FromStr for Type {
             "float" => Ok(Self::Float),
             #[cfg(feature = "char")]
             "char" => Ok(Self::Char),
+            #[cfg(feature = "other")]
+            _ => Ok(Self::Other(AbstractType::Primitive(s.to_string()))),
+            #[cfg(not(feature = "other"))]
             _ => Err(ConversionError::InvalidPrimitive(s.to_string())),
         }
     }
@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::{
+    cfg::{Cfg, CfgError},
+    dataflow::ConstValue,
+    fold::{cast, fold},
+    ssa::{is_ssa, remove_unreachable_blocks},
+    ConstOps, EffectOps, Instruction, Literal, Program, ValueOps,
+};
+
+/// Errors from running [`sccp`] over a [Program]
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug)]
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "I allow the `Error` suffix for enums"
+)]
+pub enum SccpError {
+    /// A function assigns a variable more than once, so it isn't in SSA form
+    #[error("@{0} is not in SSA form")]
+    NotSsa(String),
+    /// The labels of a function don't resolve
+    #[error("@{0}: {1}")]
+    Cfg(String, CfgError),
+}
+
+/// Runs [`sccp_cfg`] over every function of `program`, which must be in SSA form like the output of [`crate::ssa::to_ssa`]
+/// # Errors
+/// Returns an error if a function is not in SSA form or its labels don't resolve, in which case `program` is unchanged
+pub fn sccp(program: &mut Program) -> Result<(), SccpError> {
+    let mut cfgs = Vec::with_capacity(program.functions.len());
+    for f in &program.functions {
+        if !is_ssa(f) {
+            return Err(SccpError::NotSsa(f.name.clone()));
+        }
+        cfgs.push(Cfg::try_from(f.clone()).map_err(|e| SccpError::Cfg(f.name.clone(), e))?);
+    }
+    for (f, mut cfg) in program.functions.iter_mut().zip(cfgs) {
+        sccp_cfg(&mut cfg);
+        *f = cfg.into();
+    }
+    Ok(())
+}
+
+/// Sparse conditional constant propagation, from "Constant Propagation with Conditional Branches" by Wegman and Zadeck
+///
+/// Values are only propagated along edges which can be taken given what is known so far, so a `phi` ignores its arguments from blocks which never run.
+/// Afterwards, every instruction whose value is constant becomes a `const`, each `br` on a constant becomes a `jmp`, and blocks which can never run are removed along with the arguments of `phi`s that come from them.
+/// Operations are evaluated with [`fold`], so those which would be a runtime error, like dividing by zero, are left alone.
+/// `cfg` must be in SSA form, since each variable is given a single value.
+///
+/// Returns whether anything changed.
+/// # Panics
+/// Will panic if a jump in `cfg` refers to a block which does not exist
+pub fn sccp_cfg(cfg: &mut Cfg) -> bool {
+    let (values, executable) = propagate(cfg);
+
+    let mut changed = false;
+    let block_map: HashMap<String, usize> = cfg
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b.name.clone(), i))
+        .collect();
+    for (b, block) in cfg.blocks.iter_mut().enumerate() {
+        for instr in &mut block.instrs {
+            if let Some(c) = constant(instr, &values) {
+                changed |= *instr != c;
+                *instr = c;
+            }
+            match instr {
+                Instruction::Effect {
+                    op: op @ EffectOps::Branch,
+                    args,
+                    labels,
+                    ..
+                } => {
+                    if let Some(ConstValue::Known(Literal::Bool(taken))) = values.get(&args[0]) {
+                        *op = EffectOps::Jump;
+                        args.clear();
+                        *labels = vec![labels[usize::from(!taken)].clone()];
+                        changed = true;
+                    }
+                }
+                Instruction::Value {
+                    op: ValueOps::Phi,
+                    args,
+                    labels,
+                    ..
+                } => {
+                    let live = |l: &String| {
+                        block_map
+                            .get(l)
+                            .is_some_and(|p| executable.contains(&(*p, b)))
+                    };
+                    let before = labels.len();
+                    let mut keep = labels.iter().map(live).collect::<Vec<_>>().into_iter();
+                    args.retain(|_| keep.next().unwrap_or(false));
+                    labels.retain(live);
+                    changed |= labels.len() != before;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    cfg.recompute_edges().unwrap();
+    let before = cfg.blocks.len();
+    remove_unreachable_blocks(cfg);
+    changed || cfg.blocks.len() != before
+}
+
+// The value of each variable, where variables which are missing haven't been given a value yet, and the edges which can be taken
+fn propagate(cfg: &Cfg) -> (HashMap<String, ConstValue>, HashSet<(usize, usize)>) {
+    let block_map = cfg.block_map();
+    let mut uses: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        for a in block.instrs.iter().flat_map(Instruction::args) {
+            uses.entry(a.as_str()).or_default().push(b);
+        }
+    }
+
+    let mut values: HashMap<String, ConstValue> = cfg
+        .args
+        .iter()
+        .map(|a| (a.name.clone(), ConstValue::Unknown))
+        .collect();
+    let mut executable: HashSet<(usize, usize)> = HashSet::new();
+    let mut visited = vec![false; cfg.blocks.len()];
+    let mut worklist = vec![Cfg::ENTRY];
+    let mut in_worklist = vec![false; cfg.blocks.len()];
+    in_worklist[Cfg::ENTRY] = true;
+    while let Some(b) = worklist.pop() {
+        in_worklist[b] = false;
+        visited[b] = true;
+        let mut push = |s: usize, worklist: &mut Vec<usize>| {
+            if !in_worklist[s] {
+                in_worklist[s] = true;
+                worklist.push(s);
+            }
+        };
+
+        let block = &cfg.blocks[b];
+        for instr in &block.instrs {
+            let Some(dest) = instr.dest() else {
+                continue;
+            };
+            let Some(value) = evaluate(instr, b, &values, &executable, &block_map) else {
+                continue;
+            };
+            if values.get(dest) != Some(&value) {
+                values.insert(dest.to_string(), value);
+                for &u in uses.get(dest).into_iter().flatten() {
+                    if visited[u] {
+                        push(u, &mut worklist);
+                    }
+                }
+            }
+        }
+
+        let targets: Vec<usize> = match block.terminator() {
+            Some(Instruction::Effect {
+                op: EffectOps::Branch,
+                args,
+                labels,
+                ..
+            }) => match values.get(&args[0]) {
+                Some(ConstValue::Known(Literal::Bool(taken))) => {
+                    vec![block_map[labels[usize::from(!taken)].as_str()]]
+                }
+                // A condition which never gets a value, like one computed from an undefined variable, could go either way
+                _ => block.successors.clone(),
+            },
+            _ => block.successors.clone(),
+        };
+        for s in targets {
+            if executable.insert((b, s)) {
+                push(s, &mut worklist);
+            }
+        }
+    }
+    (values, executable)
+}
+
+// The value of the destination of `instr` given the values of its arguments, or `None` if it doesn't have one yet
+fn evaluate(
+    instr: &Instruction,
+    block: usize,
+    values: &HashMap<String, ConstValue>,
+    executable: &HashSet<(usize, usize)>,
+    block_map: &HashMap<&str, usize>,
+) -> Option<ConstValue> {
+    match instr {
+        Instruction::Constant {
+            const_type, value, ..
+        } => Some(cast(value, const_type).map_or(ConstValue::Unknown, ConstValue::Known)),
+        Instruction::Value {
+            op: ValueOps::Phi,
+            args,
+            labels,
+            ..
+        } => {
+            let mut incoming = args.iter().zip(labels).filter_map(|(a, l)| {
+                let p = block_map.get(l.as_str())?;
+                if executable.contains(&(*p, block)) {
+                    values.get(a)
+                } else {
+                    None
+                }
+            });
+            let first = incoming.next()?.clone();
+            Some(incoming.fold(first, |value, v| {
+                if value == *v {
+                    value
+                } else {
+                    ConstValue::Unknown
+                }
+            }))
+        }
+        Instruction::Value {
+            op, args, op_type, ..
+        } => {
+            let mut lits = Vec::with_capacity(args.len());
+            for a in args {
+                match values.get(a) {
+                    Some(ConstValue::Known(l)) => lits.push(l),
+                    Some(ConstValue::Unknown) => return Some(ConstValue::Unknown),
+                    None => return None,
+                }
+            }
+            Some(fold(op, op_type, &lits).map_or(ConstValue::Unknown, ConstValue::Known))
+        }
+        Instruction::Effect { .. } => None,
+    }
+}
+
+// The `const` which can replace `instr`, if it assigns a constant
+fn constant(instr: &Instruction, values: &HashMap<String, ConstValue>) -> Option<Instruction> {
+    let (dest, op_type) = match instr {
+        Instruction::Value { dest, op_type, .. } => (dest, op_type),
+        Instruction::Constant { .. } | Instruction::Effect { .. } => return None,
+    };
+    let Some(ConstValue::Known(value)) = values.get(dest) else {
+        return None;
+    };
+    // Infinities and `NaN` can't be written back as a `const` in JSON
+    #[cfg(feature = "float")]
+    if matches!(value, Literal::Float(f) if !f.is_finite()) {
+        return None;
+    }
+    Some(Instruction::Constant {
+        dest: dest.clone(),
+        op: ConstOps::Const,
+        #[cfg(feature = "position")]
+        pos: instr.get_pos(),
+        const_type: op_type.clone(),
+        value: value.clone(),
+    })
+}
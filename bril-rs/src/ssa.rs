@@ -0,0 +1,410 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::{
+    cfg::{fresh_name, BasicBlock, Cfg},
+    dataflow::{solve, LiveVariables},
+    dom::Dominators,
+    Code, EffectOps, Function, Instruction, Type, ValueOps,
+};
+
+/// Whether every variable of `function` is assigned exactly once, counting the arguments of the function as assignments
+#[must_use]
+pub fn is_ssa(function: &Function) -> bool {
+    let mut assigned: HashSet<&str> = function.args.iter().map(|a| a.name.as_str()).collect();
+    function.instrs.iter().all(|code| match code {
+        Code::Instruction(i) => i.dest().is_none_or(|d| assigned.insert(d)),
+        Code::Label { .. } => true,
+    })
+}
+
+/// Converts `cfg` into pruned SSA form
+///
+/// A `phi` is only placed at the start of a block for a variable which is live into that block. Each assignment to a variable `x` is renamed to a fresh `x.0`, `x.1`, ... and the arguments of the function keep their names.
+/// Along paths where a variable is not defined, the `phi` reads the original name of the variable, which is never assigned once in SSA form.
+/// Unreachable blocks are removed and every block which a `phi` refers to is given a label.
+///
+/// The destination of a `get` keeps its name so that it still matches the `set`s which write its shadow variable, and the first argument of `set` is not renamed as a read.
+/// Renaming assumes that assignments are never undone, so functions which use the speculate extension may not behave the same after conversion.
+pub fn to_ssa(cfg: &mut Cfg) {
+    remove_unreachable_blocks(cfg);
+
+    let doms = Dominators::new(cfg);
+    let live = solve(&LiveVariables, cfg);
+
+    let mut types: HashMap<String, Type> = HashMap::new();
+    let mut def_blocks: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    let mut taken: HashSet<String> = HashSet::new();
+    for a in &cfg.args {
+        types.insert(a.name.clone(), a.arg_type.clone());
+        taken.insert(a.name.clone());
+        def_blocks
+            .entry(a.name.clone())
+            .or_default()
+            .insert(Cfg::ENTRY);
+    }
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        for instr in &block.instrs {
+            taken.extend(instr.args().iter().cloned());
+            if let Instruction::Constant {
+                dest,
+                const_type: t,
+                ..
+            }
+            | Instruction::Value {
+                dest, op_type: t, ..
+            } = instr
+            {
+                types.insert(dest.clone(), t.clone());
+                taken.insert(dest.clone());
+                def_blocks.entry(dest.clone()).or_default().insert(b);
+            }
+        }
+    }
+
+    // Place phis on the iterated dominance frontier of each variable's definitions
+    let mut phi_vars: Vec<Vec<String>> = vec![Vec::new(); cfg.blocks.len()];
+    for (var, defs) in &def_blocks {
+        let mut has_phi = vec![false; cfg.blocks.len()];
+        let mut worklist: Vec<usize> = defs.iter().copied().collect();
+        while let Some(d) = worklist.pop() {
+            for &f in doms.frontier(d) {
+                if !has_phi[f] && live.ins[f].contains(var) {
+                    has_phi[f] = true;
+                    phi_vars[f].push(var.clone());
+                    if !defs.contains(&f) {
+                        worklist.push(f);
+                    }
+                }
+            }
+        }
+    }
+    for (block, vars) in cfg.blocks.iter_mut().zip(&phi_vars) {
+        let phis = vars.iter().map(|v| Instruction::Value {
+            args: Vec::new(),
+            dest: v.clone(),
+            funcs: Vec::new(),
+            labels: Vec::new(),
+            op: ValueOps::Phi,
+            #[cfg(feature = "position")]
+            pos: None,
+            op_type: types[v].clone(),
+        });
+        block.instrs.splice(0..0, phis);
+    }
+
+    let mut renamer = Renamer {
+        stacks: cfg
+            .args
+            .iter()
+            .map(|a| (a.name.clone(), vec![a.name.clone()]))
+            .collect(),
+        counters: HashMap::new(),
+        taken,
+        phi_vars,
+    };
+    renamer.rename(cfg, &doms, Cfg::ENTRY);
+
+    for b in 0..cfg.blocks.len() {
+        if cfg.blocks[b].instrs.iter().any(is_phi) {
+            for p in cfg.blocks[b].predecessors.clone() {
+                cfg.blocks[p].labeled = true;
+            }
+        }
+    }
+}
+
+struct Renamer {
+    stacks: HashMap<String, Vec<String>>,
+    counters: HashMap<String, usize>,
+    taken: HashSet<String>,
+    // The original variable of each phi inserted at the start of each block
+    phi_vars: Vec<Vec<String>>,
+}
+
+impl Renamer {
+    fn fresh(&mut self, var: &str) -> String {
+        let counter = self.counters.entry(var.to_string()).or_default();
+        loop {
+            let name = format!("{var}.{counter}");
+            *counter += 1;
+            if self.taken.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+
+    fn current(&self, var: &str) -> Option<&String> {
+        self.stacks.get(var).and_then(|s| s.last())
+    }
+
+    fn rename(&mut self, cfg: &mut Cfg, doms: &Dominators, b: usize) {
+        let mut pushed = Vec::new();
+        let mut instrs = std::mem::take(&mut cfg.blocks[b].instrs);
+        for instr in &mut instrs {
+            // The arguments of phis are renamed from the end of the predecessor they come from
+            if !is_phi(instr) {
+                for a in instr.uses_mut() {
+                    if let Some(new) = self.current(a) {
+                        a.clone_from(new);
+                    }
+                }
+            }
+            let keep_name = is_get(instr);
+            if let Some(dest) = instr.dest_mut() {
+                let new = if keep_name {
+                    dest.clone()
+                } else {
+                    self.fresh(dest)
+                };
+                self.stacks
+                    .entry(dest.clone())
+                    .or_default()
+                    .push(new.clone());
+                pushed.push(std::mem::replace(dest, new));
+            }
+        }
+        cfg.blocks[b].instrs = instrs;
+
+        let name = cfg.blocks[b].name.clone();
+        for s in cfg.blocks[b].successors.clone() {
+            let num_new_phis = self.phi_vars[s].len();
+            for (i, instr) in cfg.blocks[s].instrs.iter_mut().enumerate() {
+                let Instruction::Value {
+                    args,
+                    labels,
+                    op: ValueOps::Phi,
+                    ..
+                } = instr
+                else {
+                    continue;
+                };
+                if i < num_new_phis {
+                    let var = &self.phi_vars[s][i];
+                    args.push(self.current(var).unwrap_or(var).clone());
+                    labels.push(name.clone());
+                } else {
+                    for (a, l) in args.iter_mut().zip(labels.iter()) {
+                        if *l == name {
+                            if let Some(new) = self.current(a) {
+                                a.clone_from(new);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for &c in doms.children(b) {
+            self.rename(cfg, doms, c);
+        }
+
+        for var in pushed {
+            self.stacks.get_mut(&var).unwrap().pop();
+        }
+    }
+}
+
+/// Converts `cfg` out of SSA form by replacing each `phi` with copies along the edges into its block
+///
+/// Copies are placed at the end of the predecessor when it only has one successor. Otherwise the edge is split by a new block holding the copies, so that they only execute along that edge.
+/// The copies for an edge happen in parallel like the `phi`s they replace; temporary variables are introduced when a cycle of copies would otherwise overwrite a value before it is read.
+/// Arguments which are never defined in the function are skipped, since their `phi` can't be reached along that edge with a value.
+/// # Panics
+/// Will panic if a jump in `cfg` refers to a block which does not exist
+pub fn from_ssa(cfg: &mut Cfg) {
+    let mut defined: HashSet<String> = cfg.args.iter().map(|a| a.name.clone()).collect();
+    let mut taken = defined.clone();
+    for instr in cfg.blocks.iter().flat_map(|b| &b.instrs) {
+        taken.extend(instr.args().iter().cloned());
+        if let Some(d) = instr.dest() {
+            defined.insert(d.to_string());
+            taken.insert(d.to_string());
+        }
+    }
+
+    let block_map: HashMap<String, usize> = cfg
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b.name.clone(), i))
+        .collect();
+
+    let mut edge_copies: BTreeMap<(usize, usize), Vec<PhiCopy>> = BTreeMap::new();
+    for (s, block) in cfg.blocks.iter_mut().enumerate() {
+        for instr in &block.instrs {
+            if let Instruction::Value {
+                args,
+                dest,
+                labels,
+                op: ValueOps::Phi,
+                op_type,
+                ..
+            } = instr
+            {
+                for (a, l) in args.iter().zip(labels) {
+                    if let Some(&p) = block_map.get(l) {
+                        if block.predecessors.contains(&p) && defined.contains(a) {
+                            edge_copies.entry((p, s)).or_default().push((
+                                dest.clone(),
+                                op_type.clone(),
+                                a.clone(),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        block.instrs.retain(|i| !is_phi(i));
+    }
+
+    let mut block_names: HashSet<String> = block_map.into_keys().collect();
+    let mut split_blocks: Vec<Vec<BasicBlock>> = vec![Vec::new(); cfg.blocks.len()];
+    for ((p, s), copies) in edge_copies {
+        let copies = sequentialize(copies, &mut taken);
+        let pred = &cfg.blocks[p];
+        let is_branch = matches!(
+            pred.terminator(),
+            Some(Instruction::Effect {
+                op: EffectOps::Branch,
+                ..
+            })
+        );
+        if pred.successors.len() == 1 && !is_branch {
+            let at = pred.instrs.len() - usize::from(pred.terminator().is_some());
+            cfg.blocks[p].instrs.splice(at..at, copies);
+            continue;
+        }
+
+        let succ_name = cfg.blocks[s].name.clone();
+        let split_name = fresh_name(&format!("{}.{succ_name}.", cfg.blocks[p].name), |n| {
+            block_names.contains(n)
+        });
+        block_names.insert(split_name.clone());
+        cfg.blocks[s].labeled = true;
+        let pred = &mut cfg.blocks[p];
+        if pred.terminator().is_none() {
+            pred.instrs.push(jump(succ_name.clone()));
+        }
+        for instr in &mut pred.instrs {
+            for l in instr.labels_mut().iter_mut().filter(|l| **l == succ_name) {
+                l.clone_from(&split_name);
+            }
+        }
+        let mut split = BasicBlock::new(split_name, true);
+        split.instrs = copies;
+        split.instrs.push(jump(succ_name));
+        split_blocks[p].push(split);
+    }
+
+    // Each new block goes right after its predecessor, which can't fall through into it because it ends with a jump or branch
+    let blocks = std::mem::take(&mut cfg.blocks);
+    cfg.blocks = blocks
+        .into_iter()
+        .zip(split_blocks)
+        .flat_map(|(b, splits)| std::iter::once(b).chain(splits))
+        .collect();
+    cfg.recompute_edges().unwrap();
+}
+
+// A copy of (dest, type, source)
+type PhiCopy = (String, Type, String);
+
+// Orders a parallel copy so that no source is overwritten before it is read
+fn sequentialize(copies: Vec<PhiCopy>, taken: &mut HashSet<String>) -> Vec<Instruction> {
+    let mut pending: Vec<PhiCopy> = copies.into_iter().filter(|(d, _, s)| d != s).collect();
+    let mut sequence = Vec::new();
+    while !pending.is_empty() {
+        if let Some(i) = pending
+            .iter()
+            .position(|(d, _, _)| !pending.iter().any(|(_, _, s)| s == d))
+        {
+            let (dest, t, src) = pending.remove(i);
+            sequence.push(copy(dest, t, src));
+        } else {
+            // Every remaining destination is still to be read, so they form cycles which are broken by saving one value
+            let (dest, t, _) = pending[0].clone();
+            let tmp = fresh_name(&format!("{dest}.tmp"), |n| taken.contains(n));
+            taken.insert(tmp.clone());
+            for c in &mut pending {
+                if c.2 == dest {
+                    c.2.clone_from(&tmp);
+                }
+            }
+            sequence.push(copy(tmp, t, dest));
+        }
+    }
+    sequence
+}
+
+pub(crate) fn remove_unreachable_blocks(cfg: &mut Cfg) {
+    let mut new_index = vec![None; cfg.blocks.len()];
+    let mut reachable = cfg.reverse_post_order();
+    reachable.sort_unstable();
+    for (i, &b) in reachable.iter().enumerate() {
+        new_index[b] = Some(i);
+    }
+    if reachable.len() == cfg.blocks.len() {
+        return;
+    }
+    // A reachable block never falls through into an unreachable one, so removing them doesn't change control flow
+    let blocks = std::mem::take(&mut cfg.blocks);
+    cfg.blocks = blocks
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| new_index[*i].is_some())
+        .map(|(_, mut b)| {
+            b.predecessors = b
+                .predecessors
+                .iter()
+                .filter_map(|p| new_index[*p])
+                .collect();
+            b.successors = b.successors.iter().filter_map(|s| new_index[*s]).collect();
+            b
+        })
+        .collect();
+}
+
+const fn is_phi(instr: &Instruction) -> bool {
+    matches!(
+        instr,
+        Instruction::Value {
+            op: ValueOps::Phi,
+            ..
+        }
+    )
+}
+
+// The destination of `get` also names the shadow variable it reads, which the `set`s that write it refer to
+const fn is_get(instr: &Instruction) -> bool {
+    match instr {
+        #[cfg(feature = "ssa2")]
+        Instruction::Value {
+            op: ValueOps::Get, ..
+        } => true,
+        _ => false,
+    }
+}
+
+fn copy(dest: String, op_type: Type, src: String) -> Instruction {
+    Instruction::Value {
+        args: vec![src],
+        dest,
+        funcs: Vec::new(),
+        labels: Vec::new(),
+        op: ValueOps::Id,
+        #[cfg(feature = "position")]
+        pos: None,
+        op_type,
+    }
+}
+
+fn jump(label: String) -> Instruction {
+    Instruction::Effect {
+        args: Vec::new(),
+        funcs: Vec::new(),
+        labels: vec![label],
+        op: EffectOps::Jump,
+        #[cfg(feature = "position")]
+        pos: None,
+    }
+}
@@ -0,0 +1,284 @@
+#[cfg(feature = "import")]
+use crate::Import;
+use crate::{Argument, Code, Function, Instruction, Literal, Program, Type};
+
+/// A read-only traversal of a [Program]
+///
+/// Each `visit_*` method defaults to calling the matching `walk_*` function, which visits the children of that node.
+/// Overriding a method and calling the `walk_*` function from inside of it keeps the traversal going underneath the node.
+pub trait Visit {
+    /// Visits a whole program
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    /// Visits an import of other functions into the program
+    #[cfg(feature = "import")]
+    fn visit_import(&mut self, _import: &Import) {}
+
+    /// Visits a function definition
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    /// Visits a parameter of a function
+    fn visit_argument(&mut self, argument: &Argument) {
+        walk_argument(self, argument);
+    }
+
+    /// Visits a label or instruction in the body of a function
+    fn visit_code(&mut self, code: &Code) {
+        walk_code(self, code);
+    }
+
+    /// Visits the name of a label in the body of a function
+    fn visit_label(&mut self, _label: &str) {}
+
+    /// Visits an instruction
+    fn visit_instruction(&mut self, instr: &Instruction) {
+        walk_instruction(self, instr);
+    }
+
+    /// Visits the destination of an instruction
+    fn visit_dest(&mut self, _dest: &str) {}
+
+    /// Visits a variable read by an instruction
+    fn visit_arg(&mut self, _arg: &str) {}
+
+    /// Visits a function referred to by an instruction
+    fn visit_func(&mut self, _func: &str) {}
+
+    /// Visits a label referred to by an instruction
+    fn visit_target(&mut self, _target: &str) {}
+
+    /// Visits the value of a `const` instruction
+    fn visit_literal(&mut self, _literal: &Literal) {}
+
+    /// Visits a type, including the pointee of a pointer type
+    fn visit_type(&mut self, ty: &Type) {
+        walk_type(self, ty);
+    }
+}
+
+/// Visits the imports and functions of `program`
+pub fn walk_program<V: Visit + ?Sized>(v: &mut V, program: &Program) {
+    #[cfg(feature = "import")]
+    for i in &program.imports {
+        v.visit_import(i);
+    }
+    for f in &program.functions {
+        v.visit_function(f);
+    }
+}
+
+/// Visits the arguments, return type, and body of `function`
+pub fn walk_function<V: Visit + ?Sized>(v: &mut V, function: &Function) {
+    for a in &function.args {
+        v.visit_argument(a);
+    }
+    if let Some(t) = &function.return_type {
+        v.visit_type(t);
+    }
+    for c in &function.instrs {
+        v.visit_code(c);
+    }
+}
+
+/// Visits the type of `argument`
+pub fn walk_argument<V: Visit + ?Sized>(v: &mut V, argument: &Argument) {
+    v.visit_type(&argument.arg_type);
+}
+
+/// Visits the label or instruction in `code`
+pub fn walk_code<V: Visit + ?Sized>(v: &mut V, code: &Code) {
+    match code {
+        Code::Label { label, .. } => v.visit_label(label),
+        Code::Instruction(i) => v.visit_instruction(i),
+    }
+}
+
+/// Visits the destination, type, arguments, functions, labels, and value of `instr`, whichever it has
+pub fn walk_instruction<V: Visit + ?Sized>(v: &mut V, instr: &Instruction) {
+    match instr {
+        Instruction::Constant {
+            dest,
+            const_type,
+            value,
+            ..
+        } => {
+            v.visit_dest(dest);
+            v.visit_type(const_type);
+            v.visit_literal(value);
+        }
+        Instruction::Value { dest, op_type, .. } => {
+            v.visit_dest(dest);
+            v.visit_type(op_type);
+        }
+        Instruction::Effect { .. } => {}
+    }
+    for a in instr.args() {
+        v.visit_arg(a);
+    }
+    for f in instr.funcs() {
+        v.visit_func(f);
+    }
+    for l in instr.labels() {
+        v.visit_target(l);
+    }
+}
+
+/// Visits the pointee of `ty` if it is a pointer type
+#[cfg_attr(
+    not(feature = "memory"),
+    expect(
+        unused_variables,
+        clippy::missing_const_for_fn,
+        reason = "Only pointer types have children"
+    )
+)]
+pub fn walk_type<V: Visit + ?Sized>(v: &mut V, ty: &Type) {
+    match ty {
+        #[cfg(feature = "memory")]
+        Type::Pointer(t) => v.visit_type(t),
+        _ => {}
+    }
+}
+
+/// A traversal of a [Program] which can rewrite it in place, in the same way as [Visit]
+pub trait VisitMut {
+    /// Visits a whole program
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    /// Visits an import of other functions into the program
+    #[cfg(feature = "import")]
+    fn visit_import_mut(&mut self, _import: &mut Import) {}
+
+    /// Visits a function definition
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        walk_function_mut(self, function);
+    }
+
+    /// Visits a parameter of a function
+    fn visit_argument_mut(&mut self, argument: &mut Argument) {
+        walk_argument_mut(self, argument);
+    }
+
+    /// Visits a label or instruction in the body of a function
+    fn visit_code_mut(&mut self, code: &mut Code) {
+        walk_code_mut(self, code);
+    }
+
+    /// Visits the name of a label in the body of a function
+    fn visit_label_mut(&mut self, _label: &mut String) {}
+
+    /// Visits an instruction
+    fn visit_instruction_mut(&mut self, instr: &mut Instruction) {
+        walk_instruction_mut(self, instr);
+    }
+
+    /// Visits the destination of an instruction
+    fn visit_dest_mut(&mut self, _dest: &mut String) {}
+
+    /// Visits a variable read by an instruction
+    fn visit_arg_mut(&mut self, _arg: &mut String) {}
+
+    /// Visits a function referred to by an instruction
+    fn visit_func_mut(&mut self, _func: &mut String) {}
+
+    /// Visits a label referred to by an instruction
+    fn visit_target_mut(&mut self, _target: &mut String) {}
+
+    /// Visits the value of a `const` instruction
+    fn visit_literal_mut(&mut self, _literal: &mut Literal) {}
+
+    /// Visits a type, including the pointee of a pointer type
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        walk_type_mut(self, ty);
+    }
+}
+
+/// Visits the imports and functions of `program`
+pub fn walk_program_mut<V: VisitMut + ?Sized>(v: &mut V, program: &mut Program) {
+    #[cfg(feature = "import")]
+    for i in &mut program.imports {
+        v.visit_import_mut(i);
+    }
+    for f in &mut program.functions {
+        v.visit_function_mut(f);
+    }
+}
+
+/// Visits the arguments, return type, and body of `function`
+pub fn walk_function_mut<V: VisitMut + ?Sized>(v: &mut V, function: &mut Function) {
+    for a in &mut function.args {
+        v.visit_argument_mut(a);
+    }
+    if let Some(t) = &mut function.return_type {
+        v.visit_type_mut(t);
+    }
+    for c in &mut function.instrs {
+        v.visit_code_mut(c);
+    }
+}
+
+/// Visits the type of `argument`
+pub fn walk_argument_mut<V: VisitMut + ?Sized>(v: &mut V, argument: &mut Argument) {
+    v.visit_type_mut(&mut argument.arg_type);
+}
+
+/// Visits the label or instruction in `code`
+pub fn walk_code_mut<V: VisitMut + ?Sized>(v: &mut V, code: &mut Code) {
+    match code {
+        Code::Label { label, .. } => v.visit_label_mut(label),
+        Code::Instruction(i) => v.visit_instruction_mut(i),
+    }
+}
+
+/// Visits the destination, type, arguments, functions, labels, and value of `instr`, whichever it has
+pub fn walk_instruction_mut<V: VisitMut + ?Sized>(v: &mut V, instr: &mut Instruction) {
+    match instr {
+        Instruction::Constant {
+            dest,
+            const_type,
+            value,
+            ..
+        } => {
+            v.visit_dest_mut(dest);
+            v.visit_type_mut(const_type);
+            v.visit_literal_mut(value);
+        }
+        Instruction::Value { dest, op_type, .. } => {
+            v.visit_dest_mut(dest);
+            v.visit_type_mut(op_type);
+        }
+        Instruction::Effect { .. } => {}
+    }
+    for a in instr.args_mut() {
+        v.visit_arg_mut(a);
+    }
+    for f in instr.funcs_mut() {
+        v.visit_func_mut(f);
+    }
+    for l in instr.labels_mut() {
+        v.visit_target_mut(l);
+    }
+}
+
+/// Visits the pointee of `ty` if it is a pointer type
+#[cfg_attr(
+    not(feature = "memory"),
+    expect(
+        unused_variables,
+        clippy::missing_const_for_fn,
+        reason = "Only pointer types have children"
+    )
+)]
+pub fn walk_type_mut<V: VisitMut + ?Sized>(v: &mut V, ty: &mut Type) {
+    match ty {
+        #[cfg(feature = "memory")]
+        Type::Pointer(t) => v.visit_type_mut(t),
+        _ => {}
+    }
+}
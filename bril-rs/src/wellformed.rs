@@ -0,0 +1,509 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use thiserror::Error;
+
+use crate::{Code, EffectOps, Function, Instruction, Position, Program, Type, ValueOps};
+
+/// The violations of the rules in `docs/lang/wellformed.md` which [`check_program`] looks for
+// Having the #[error(...)] for all variants derives the Display trait as well
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WellFormedError {
+    /// Function @{0} is defined more than once
+    #[error("Function @{0} is defined more than once")]
+    DuplicateFunction(String),
+
+    /// Argument {0} is defined more than once
+    #[error("Argument {0} is defined more than once")]
+    DuplicateArgument(String),
+
+    /// Label .{0} is defined more than once
+    #[error("Label .{0} is defined more than once")]
+    DuplicateLabel(String),
+
+    /// Label .{0} is not defined
+    #[error("Label .{0} is not defined")]
+    MissingLabel(String),
+
+    /// Function @{0} is not defined
+    #[error("Function @{0} is not defined")]
+    MissingFunction(String),
+
+    /// Variable {0} is not defined
+    #[error("Variable {0} is not defined")]
+    UndefinedVariable(String),
+
+    /// {0} expects {1} arguments, found {2}
+    #[error("{0} expects {1} arguments, found {2}")]
+    BadNumArgs(String, usize, usize),
+
+    /// {0} expects {1} labels, found {2}
+    #[error("{0} expects {1} labels, found {2}")]
+    BadNumLabels(String, usize, usize),
+
+    /// {0} expects {1} functions, found {2}
+    #[error("{0} expects {1} functions, found {2}")]
+    BadNumFuncs(String, usize, usize),
+
+    /// Variable {0} is assigned type {1}, but already has type {2}
+    #[error("Variable {0} is assigned type {1}, but already has type {2}")]
+    ConflictingTypes(String, Type, Type),
+
+    /// Expected {0} to have type {1}, found {2}
+    #[error("Expected {0} to have type {1}, found {2}")]
+    BadType(String, Type, Type),
+
+    /// @{0} does not return a value
+    #[error("@{0} does not return a value")]
+    NoReturnValue(String),
+
+    /// @{0} must return a value of type {1}
+    #[error("@{0} must return a value of type {1}")]
+    MissingReturnValue(String, Type),
+
+    /// @{0} returns a value of type {1}, which must be assigned to a variable
+    #[error("@{0} returns a value of type {1}, which must be assigned to a variable")]
+    UnusedReturnValue(String, Type),
+
+    /// @main must not have a return type, found {0}
+    #[error("@main must not have a return type, found {0}")]
+    MainReturnType(Type),
+}
+
+impl WellFormedError {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn add_pos(self, pos_var: Option<Position>) -> PositionalWellFormedError {
+        PositionalWellFormedError {
+            e: self,
+            pos: pos_var,
+        }
+    }
+}
+
+/// Wraps [`WellFormedError`] to optionally provide source code positions if they are available.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct PositionalWellFormedError {
+    #[doc(hidden)]
+    pub e: WellFormedError,
+    #[doc(hidden)]
+    pub pos: Option<Position>,
+}
+
+impl Display for PositionalWellFormedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "position")]
+            Self { e, pos: Some(pos) } => {
+                write!(f, "Line {}, Column {}: {e}", pos.pos.row, pos.pos.col)
+            }
+            #[cfg(not(feature = "position"))]
+            Self { e: _, pos: Some(_) } => {
+                unreachable!()
+            }
+            Self { e, pos: None } => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Checks that `program` follows the structural rules of well-formed Bril
+///
+/// Labels and functions must be defined exactly once and every reference to them must resolve, each variable that is used must be defined somewhere in its function with a single type, and each operation must have the right number of arguments, labels, and functions.
+/// Calls must match the signature of the function they call and `ret` must match the return type of its function.
+/// Unlike type checking, the types of the arguments to most operations are not checked.
+/// # Errors
+/// Returns every violation which was found, along with its position when it has one
+pub fn check_program(program: &Program) -> Result<(), Vec<PositionalWellFormedError>> {
+    let mut checker = Checker {
+        functions: HashMap::new(),
+        errors: Vec::new(),
+    };
+    #[cfg(feature = "import")]
+    for i in &program.imports {
+        for f in &i.functions {
+            checker
+                .functions
+                .insert(f.alias.as_ref().unwrap_or(&f.name), None);
+        }
+    }
+    for f in &program.functions {
+        if checker.functions.contains_key(f.name.as_str()) {
+            checker.error(
+                WellFormedError::DuplicateFunction(f.name.clone()),
+                function_pos(f),
+            );
+        } else {
+            checker.functions.insert(&f.name, Some(f));
+        }
+    }
+    for f in &program.functions {
+        checker.function(f);
+    }
+    if checker.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(checker.errors)
+    }
+}
+
+struct Checker<'a> {
+    // Every function which can be called, along with its signature unless it was imported from another file
+    functions: HashMap<&'a str, Option<&'a Function>>,
+    errors: Vec<PositionalWellFormedError>,
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, e: WellFormedError, at: Option<Position>) {
+        self.errors.push(e.add_pos(at));
+    }
+
+    fn function(&mut self, function: &'a Function) {
+        if let (Some(t), "main") = (&function.return_type, function.name.as_str()) {
+            self.error(
+                WellFormedError::MainReturnType(t.clone()),
+                function_pos(function),
+            );
+        }
+
+        let mut types: HashMap<&str, &Type> = HashMap::new();
+        for a in &function.args {
+            if types.contains_key(a.name.as_str()) {
+                self.error(
+                    WellFormedError::DuplicateArgument(a.name.clone()),
+                    function_pos(function),
+                );
+            } else {
+                types.insert(&a.name, &a.arg_type);
+            }
+        }
+
+        // Variables and labels may be used before they are defined, so they are all collected up front and then everything is checked in order
+        let mut labels = HashSet::new();
+        for code in &function.instrs {
+            match code {
+                Code::Label { label, .. } => {
+                    labels.insert(label.as_str());
+                }
+                Code::Instruction(i) => {
+                    if let (Some(dest), Some(ty)) = (i.dest(), assigned_type(i)) {
+                        types.entry(dest).or_insert(ty);
+                    }
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for code in &function.instrs {
+            match code {
+                Code::Label { label, .. } => {
+                    if !seen.insert(label.as_str()) {
+                        self.error(
+                            WellFormedError::DuplicateLabel(label.clone()),
+                            code_pos(code),
+                        );
+                    }
+                }
+                Code::Instruction(i) => {
+                    let mut errors = Vec::new();
+                    if let (Some(dest), Some(ty)) = (i.dest(), assigned_type(i)) {
+                        if types[dest] != ty {
+                            errors.push(WellFormedError::ConflictingTypes(
+                                dest.to_string(),
+                                ty.clone(),
+                                types[dest].clone(),
+                            ));
+                        }
+                    }
+                    self.instruction(function, &types, &labels, i, &mut errors);
+                    for e in errors {
+                        self.error(e, code_pos(code));
+                    }
+                }
+            }
+        }
+    }
+
+    fn instruction(
+        &self,
+        function: &Function,
+        types: &HashMap<&str, &Type>,
+        labels: &HashSet<&str>,
+        instr: &Instruction,
+        errors: &mut Vec<WellFormedError>,
+    ) {
+        for l in instr.labels() {
+            if !labels.contains(l.as_str()) {
+                errors.push(WellFormedError::MissingLabel(l.clone()));
+            }
+        }
+        for f in instr.funcs() {
+            if !self.functions.contains_key(f.as_str()) {
+                errors.push(WellFormedError::MissingFunction(f.clone()));
+            }
+        }
+        // The arguments of `phi` are read along its incoming edges, where `examples/to_ssa.py` passes `__undefined` for variables which have no value
+        if !is_phi(instr) {
+            for a in instr.args() {
+                if !types.contains_key(a.as_str()) {
+                    errors.push(WellFormedError::UndefinedVariable(a.clone()));
+                }
+            }
+        }
+
+        let Some((num_args, num_labels, num_funcs)) = self.shape(function, types, instr, errors)
+        else {
+            return;
+        };
+        let op = op_name(instr);
+        if let Some(n) = num_args {
+            if instr.args().len() != n {
+                errors.push(WellFormedError::BadNumArgs(
+                    op.clone(),
+                    n,
+                    instr.args().len(),
+                ));
+            }
+        }
+        if instr.labels().len() != num_labels {
+            errors.push(WellFormedError::BadNumLabels(
+                op.clone(),
+                num_labels,
+                instr.labels().len(),
+            ));
+        }
+        if instr.funcs().len() != num_funcs {
+            errors.push(WellFormedError::BadNumFuncs(
+                op,
+                num_funcs,
+                instr.funcs().len(),
+            ));
+        }
+    }
+
+    // The number of arguments, labels, and functions which `instr` takes, where `None` stands for any number of arguments
+    // Calls and returns are also checked against the signatures they refer to
+    fn shape(
+        &self,
+        function: &Function,
+        types: &HashMap<&str, &Type>,
+        instr: &Instruction,
+        errors: &mut Vec<WellFormedError>,
+    ) -> Option<(Option<usize>, usize, usize)> {
+        Some(match instr {
+            Instruction::Constant { .. } => return None,
+            Instruction::Value {
+                op,
+                args,
+                dest,
+                op_type,
+                funcs,
+                ..
+            } => match op {
+                ValueOps::Add
+                | ValueOps::Sub
+                | ValueOps::Mul
+                | ValueOps::Div
+                | ValueOps::Eq
+                | ValueOps::Lt
+                | ValueOps::Gt
+                | ValueOps::Le
+                | ValueOps::Ge
+                | ValueOps::And
+                | ValueOps::Or => (Some(2), 0, 0),
+                ValueOps::Not | ValueOps::Id => (Some(1), 0, 0),
+                ValueOps::Call => {
+                    self.call(args, funcs, Some((dest, op_type)), types, errors);
+                    (None, 0, 1)
+                }
+                #[cfg(feature = "ssa")]
+                ValueOps::Phi => (None, args.len(), 0),
+                #[cfg(feature = "ssa2")]
+                ValueOps::Get | ValueOps::Undef => (Some(0), 0, 0),
+                #[cfg(feature = "float")]
+                ValueOps::Fadd
+                | ValueOps::Fsub
+                | ValueOps::Fmul
+                | ValueOps::Fdiv
+                | ValueOps::Feq
+                | ValueOps::Flt
+                | ValueOps::Fgt
+                | ValueOps::Fle
+                | ValueOps::Fge => (Some(2), 0, 0),
+                #[cfg(feature = "char")]
+                ValueOps::Ceq | ValueOps::Clt | ValueOps::Cgt | ValueOps::Cle | ValueOps::Cge => {
+                    (Some(2), 0, 0)
+                }
+                #[cfg(feature = "char")]
+                ValueOps::Char2int | ValueOps::Int2char => (Some(1), 0, 0),
+                #[cfg(feature = "memory")]
+                ValueOps::Alloc | ValueOps::Load => (Some(1), 0, 0),
+                #[cfg(feature = "memory")]
+                ValueOps::PtrAdd => (Some(2), 0, 0),
+                // Nothing is known about operations from other extensions
+                #[cfg(feature = "other")]
+                ValueOps::Other(_) => return None,
+            },
+            Instruction::Effect {
+                op, args, funcs, ..
+            } => match op {
+                EffectOps::Jump => (Some(0), 1, 0),
+                EffectOps::Branch => (Some(1), 2, 0),
+                EffectOps::Call => {
+                    self.call(args, funcs, None, types, errors);
+                    (None, 0, 1)
+                }
+                EffectOps::Return => {
+                    ret(function, args, types, errors);
+                    (None, 0, 0)
+                }
+                EffectOps::Print => (None, 0, 0),
+                EffectOps::Nop => (Some(0), 0, 0),
+                #[cfg(feature = "memory")]
+                EffectOps::Store => (Some(2), 0, 0),
+                #[cfg(feature = "memory")]
+                EffectOps::Free => (Some(1), 0, 0),
+                #[cfg(feature = "speculate")]
+                EffectOps::Speculate | EffectOps::Commit => (Some(0), 0, 0),
+                #[cfg(feature = "speculate")]
+                EffectOps::Guard => (Some(1), 1, 0),
+                #[cfg(feature = "ssa2")]
+                EffectOps::Set => (Some(2), 0, 0),
+                #[cfg(feature = "other")]
+                EffectOps::Other(_) => return None,
+            },
+        })
+    }
+
+    // Checks a call against the signature of the function it calls, where `dest` is the variable the result is assigned to
+    fn call(
+        &self,
+        args: &[String],
+        funcs: &[String],
+        dest: Option<(&String, &Type)>,
+        types: &HashMap<&str, &Type>,
+        errors: &mut Vec<WellFormedError>,
+    ) {
+        let [name] = funcs else {
+            return;
+        };
+        let Some(Some(callee)) = self.functions.get(name.as_str()) else {
+            return;
+        };
+        if args.len() != callee.args.len() {
+            errors.push(WellFormedError::BadNumArgs(
+                format!("@{name}"),
+                callee.args.len(),
+                args.len(),
+            ));
+        }
+        for (a, param) in args.iter().zip(&callee.args) {
+            expect_type(a, &param.arg_type, types, errors);
+        }
+        match (dest, &callee.return_type) {
+            (Some((dest, ty)), Some(t)) if ty != t => {
+                errors.push(WellFormedError::BadType(
+                    dest.clone(),
+                    t.clone(),
+                    ty.clone(),
+                ));
+            }
+            (Some(_), None) => errors.push(WellFormedError::NoReturnValue(name.clone())),
+            (None, Some(t)) => {
+                errors.push(WellFormedError::UnusedReturnValue(name.clone(), t.clone()));
+            }
+            _ => {}
+        }
+    }
+}
+
+// Checks a `ret` against the return type of `function`
+fn ret(
+    function: &Function,
+    args: &[String],
+    types: &HashMap<&str, &Type>,
+    errors: &mut Vec<WellFormedError>,
+) {
+    match (args, &function.return_type) {
+        ([], None) => {}
+        ([], Some(t)) => errors.push(WellFormedError::MissingReturnValue(
+            function.name.clone(),
+            t.clone(),
+        )),
+        ([_], None) => errors.push(WellFormedError::NoReturnValue(function.name.clone())),
+        ([a], Some(t)) => expect_type(a, t, types, errors),
+        (args, t) => errors.push(WellFormedError::BadNumArgs(
+            "ret".to_string(),
+            usize::from(t.is_some()),
+            args.len(),
+        )),
+    }
+}
+
+// Undefined variables are reported separately, so only variables with a known type are checked
+fn expect_type(
+    var: &str,
+    expected: &Type,
+    types: &HashMap<&str, &Type>,
+    errors: &mut Vec<WellFormedError>,
+) {
+    if let Some(ty) = types.get(var) {
+        if *ty != expected {
+            errors.push(WellFormedError::BadType(
+                var.to_string(),
+                expected.clone(),
+                (*ty).clone(),
+            ));
+        }
+    }
+}
+
+const fn assigned_type(instr: &Instruction) -> Option<&Type> {
+    match instr {
+        Instruction::Constant { const_type, .. } => Some(const_type),
+        Instruction::Value { op_type, .. } => Some(op_type),
+        Instruction::Effect { .. } => None,
+    }
+}
+
+fn op_name(instr: &Instruction) -> String {
+    match instr {
+        Instruction::Constant { op, .. } => op.to_string(),
+        Instruction::Value { op, .. } => op.to_string(),
+        Instruction::Effect { op, .. } => op.to_string(),
+    }
+}
+
+const fn is_phi(instr: &Instruction) -> bool {
+    match instr {
+        #[cfg(feature = "ssa")]
+        Instruction::Value {
+            op: ValueOps::Phi, ..
+        } => true,
+        _ => false,
+    }
+}
+
+#[cfg(feature = "position")]
+fn function_pos(function: &Function) -> Option<Position> {
+    function.pos.clone()
+}
+
+#[cfg(not(feature = "position"))]
+const fn function_pos(_: &Function) -> Option<Position> {
+    None
+}
+
+#[cfg(feature = "position")]
+fn code_pos(code: &Code) -> Option<Position> {
+    match code {
+        Code::Label { pos, .. } => pos.clone(),
+        Code::Instruction(i) => i.get_pos(),
+    }
+}
+
+#[cfg(not(feature = "position"))]
+const fn code_pos(_: &Code) -> Option<Position> {
+    None
+}
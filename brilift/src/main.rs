@@ -1,5 +1,6 @@
 use argh::FromArgs;
 use bril_rs as bril;
+use bril_rs::extensions::Extension;
 use brilift::{compile, jit_run};
 use std::str::FromStr;
 
@@ -85,9 +86,18 @@ fn main() {
     )
     .unwrap();
 
-    // Load the Bril program from stdin.
+    // Load the Bril program from stdin, as JSON or in the binary encoding.
     let prog = bril::load_program();
 
+    // Reject programs using extensions that the translator does not handle.
+    if let Err(e) =
+        prog.extensions()
+            .check(&[Extension::Float, Extension::Memory, Extension::Position])
+    {
+        eprintln!("error: {e}");
+        std::process::exit(2);
+    }
+
     if args.jit {
         jit_run(&prog, args.args, args.dump_ir);
     } else {
@@ -1,4 +1,4 @@
-use bril_rs::{Function, Instruction, Position, Program};
+use bril_rs::{EffectOps, Function, Instruction, Position, Program, ValueOps};
 use fxhash::FxHashMap;
 
 use crate::error::{InterpError, PositionalInterpError};
@@ -88,6 +88,9 @@ pub struct NumifiedInstruction {
   pub dest: Option<usize>,
   pub args: Vec<usize>,
   pub funcs: Vec<usize>,
+  // Block indices for labels that can be jumped to from the middle of a block, i.e. by `guard`
+  // These are filled in by `build_cfg` once all of the labels are known
+  pub labels: Vec<usize>,
 }
 
 fn get_num_from_map(
@@ -118,14 +121,49 @@ impl NumifiedInstruction {
     num_of_vars: &mut usize,
     // A map from variables to numbers
     num_var_map: &mut FxHashMap<String, usize>,
+    // A map from variables to the numbers of their shadow variables, which are used by `set` and `get`
+    shadow_var_map: &mut FxHashMap<String, usize>,
     // A map from function names to numbers
     func_map: &FxHashMap<String, usize>,
   ) -> Result<Self, PositionalInterpError> {
     Ok(match instr {
+      // `get` copies out of the shadow variable of its destination
+      Instruction::Value {
+        op: ValueOps::Get,
+        dest,
+        ..
+      } => Self {
+        dest: Some(get_num_from_map(dest, num_of_vars, num_var_map)),
+        args: vec![get_num_from_map(dest, num_of_vars, shadow_var_map)],
+        funcs: Vec::new(),
+        labels: Vec::new(),
+      },
+      // `set` copies its second argument into the shadow variable of its first argument
+      Instruction::Effect {
+        op: EffectOps::Set,
+        args,
+        ..
+      } => Self {
+        dest: None,
+        args: args
+          .iter()
+          .enumerate()
+          .map(|(i, v)| {
+            if i == 0 {
+              get_num_from_map(v, num_of_vars, shadow_var_map)
+            } else {
+              get_num_from_map(v, num_of_vars, num_var_map)
+            }
+          })
+          .collect(),
+        funcs: Vec::new(),
+        labels: Vec::new(),
+      },
       Instruction::Constant { dest, .. } => Self {
         dest: Some(get_num_from_map(dest, num_of_vars, num_var_map)),
         args: Vec::new(),
         funcs: Vec::new(),
+        labels: Vec::new(),
       },
       Instruction::Value {
         dest,
@@ -148,6 +186,7 @@ impl NumifiedInstruction {
               .ok_or_else(|| InterpError::FuncNotFound(f.to_string()).add_pos(pos.clone()))
           })
           .collect::<Result<Vec<usize>, PositionalInterpError>>()?,
+        labels: Vec::new(),
       },
       Instruction::Effect {
         args, funcs, pos, ..
@@ -166,6 +205,7 @@ impl NumifiedInstruction {
               .ok_or_else(|| InterpError::FuncNotFound(f.to_string()).add_pos(pos.clone()))
           })
           .collect::<Result<Vec<usize>, PositionalInterpError>>()?,
+        labels: Vec::new(),
       },
     })
   }
@@ -202,6 +242,7 @@ impl BBFunction {
 
     let mut num_of_vars = 0;
     let mut num_var_map = FxHashMap::default();
+    let mut shadow_var_map = FxHashMap::default();
 
     let args_as_nums = func
       .args
@@ -231,6 +272,7 @@ impl BBFunction {
             &i,
             &mut num_of_vars,
             &mut num_var_map,
+            &mut shadow_var_map,
             func_map,
           )?);
           curr_block.instrs.push(i);
@@ -242,6 +284,7 @@ impl BBFunction {
             &code,
             &mut num_of_vars,
             &mut num_var_map,
+            &mut shadow_var_map,
             func_map,
           )?);
           curr_block.instrs.push(code);
@@ -267,7 +310,10 @@ impl BBFunction {
     ))
   }
 
-  fn build_cfg(&mut self, label_map: &FxHashMap<String, usize>) -> Result<(), InterpError> {
+  fn build_cfg(
+    &mut self,
+    label_map: &FxHashMap<String, usize>,
+  ) -> Result<(), PositionalInterpError> {
     if self.blocks.is_empty() {
       return Ok(());
     }
@@ -298,6 +344,26 @@ impl BBFunction {
           block.exit.push(i + 1);
         }
       }
+
+      for (instr, numified_instr) in block.instrs.iter().zip(block.numified_instrs.iter_mut()) {
+        if let bril_rs::Instruction::Effect {
+          op: bril_rs::EffectOps::Guard,
+          labels,
+          pos,
+          ..
+        } = instr
+        {
+          numified_instr.labels = labels
+            .iter()
+            .map(|l| {
+              label_map
+                .get(l)
+                .copied()
+                .ok_or_else(|| InterpError::MissingLabel(l.clone()).add_pos(pos.clone()))
+            })
+            .collect::<Result<Vec<usize>, PositionalInterpError>>()?;
+        }
+      }
     }
     Ok(())
   }
@@ -82,6 +82,8 @@ fn type_check_instruction<'a>(
   func: &BBFunction,
   prog: &BBProgram,
   env: &mut FxHashMap<&'a str, &'a Type>,
+  // The types of the shadow variables written by `set` and read by `get`
+  shadow_env: &mut FxHashMap<&'a str, &'a Type>,
 ) -> Result<(), InterpError> {
   match instr {
     Instruction::Constant {
@@ -314,6 +316,36 @@ fn type_check_instruction<'a>(
 
       update_env(env, dest, op_type)
     }
+    Instruction::Value {
+      op: ValueOps::Get,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(0, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      // The shadow variable of `dest` has the same type as `dest`
+      update_env(shadow_env, dest, op_type)?;
+      update_env(env, dest, op_type)
+    }
+    Instruction::Value {
+      op: ValueOps::Undef,
+      dest,
+      op_type,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(0, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      update_env(env, dest, op_type)
+    }
     Instruction::Value {
       op: ValueOps::Alloc,
       dest,
@@ -428,7 +460,7 @@ fn type_check_instruction<'a>(
       })
     }
     Instruction::Effect {
-      op: EffectOps::Nop,
+      op: EffectOps::Nop | EffectOps::Speculate | EffectOps::Commit,
       args,
       funcs,
       labels,
@@ -439,6 +471,22 @@ fn type_check_instruction<'a>(
       check_num_labels(0, labels)?;
       Ok(())
     }
+    Instruction::Effect {
+      op: EffectOps::Set,
+      args,
+      funcs,
+      labels,
+      pos: _,
+    } => {
+      check_num_args(2, args)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(0, labels)?;
+      // Only the shadow variable of `args[0]` is written, so `args[0]` itself is still only defined by a `get`
+      let src_type = *env
+        .get(&args[1] as &str)
+        .ok_or_else(|| InterpError::VarUndefined(args[1].clone()))?;
+      update_env(shadow_env, &args[0], src_type)
+    }
     Instruction::Effect {
       op: EffectOps::Call,
       args,
@@ -498,13 +546,17 @@ fn type_check_instruction<'a>(
       Ok(())
     }
     Instruction::Effect {
-      op: EffectOps::Speculate | EffectOps::Guard | EffectOps::Commit,
-      args: _,
-      funcs: _,
-      labels: _,
+      op: EffectOps::Guard,
+      args,
+      funcs,
+      labels,
       pos: _,
     } => {
-      unimplemented!()
+      check_num_args(1, args)?;
+      check_asmt_type(&Type::Bool, get_type(env, 0, args)?)?;
+      check_num_funcs(0, funcs)?;
+      check_num_labels(1, labels)?;
+      Ok(())
     }
   }
 }
@@ -520,8 +572,13 @@ fn type_check_func(bbfunc: &BBFunction, bbprog: &BBProgram) -> Result<(), Positi
   bbfunc.args.iter().for_each(|a| {
     env.insert(&a.name, &a.arg_type);
   });
+  let mut shadow_env: FxHashMap<&str, &Type> = FxHashMap::default();
 
-  let mut work_list = vec![0];
+  let mut work_list = if bbfunc.blocks.is_empty() {
+    Vec::new()
+  } else {
+    vec![0]
+  };
   let mut done_list = Vec::new();
 
   while let Some(b) = work_list.pop() {
@@ -531,11 +588,13 @@ fn type_check_func(bbfunc: &BBFunction, bbprog: &BBProgram) -> Result<(), Positi
       .iter()
       .zip(block.numified_instrs.iter())
       .try_for_each(|(i, num_i)| {
-        type_check_instruction(i, num_i, bbfunc, bbprog, &mut env)
+        type_check_instruction(i, num_i, bbfunc, bbprog, &mut env, &mut shadow_env)
           .map_err(|e| e.add_pos(i.get_pos()))
       })?;
     done_list.push(b);
-    block.exit.iter().for_each(|e| {
+    // A `guard` can also jump to a label from the middle of the block
+    let guard_exits = block.numified_instrs.iter().flat_map(|i| i.labels.iter());
+    block.exit.iter().chain(guard_exits).for_each(|e| {
       if !done_list.contains(e) && !work_list.contains(e) {
         work_list.push(*e);
       }
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use bril_rs::{conversion::PositionalConversionError, Position};
+use bril_rs::{conversion::PositionalConversionError, error::LoadError, Position};
 use std::error::Error;
 use thiserror::Error;
 
@@ -59,6 +59,14 @@ pub enum InterpError {
   IoError(#[from] std::io::Error),
   #[error("value ${0} cannot be converted to char")]
   ToCharError(i64),
+  #[error("{0} not allowed during speculation")]
+  NotAllowedDuringSpeculation(String),
+  #[error("commit in non-speculative state")]
+  CommitOutsideSpeculation,
+  #[error("abort in non-speculative state")]
+  AbortOutsideSpeculation,
+  #[error("implicit return in speculative state")]
+  ImplicitReturnInSpeculation,
   #[error("You probably shouldn't see this error, this is here to handle conversions between InterpError and PositionalError")]
   PositionalInterpErrorConversion(#[from] PositionalInterpError),
 }
@@ -161,3 +169,22 @@ impl From<PositionalConversionError> for PositionalInterpError {
     }
   }
 }
+
+impl From<LoadError> for PositionalInterpError {
+  fn from(e: LoadError) -> Self {
+    match e {
+      LoadError::Parse(e) => Self {
+        pos: e.pos.clone(),
+        e,
+      },
+      LoadError::Conversion(e) => (*e).into(),
+      // serde_json already includes the line and column in its error message
+      LoadError::Io(_) | LoadError::Json(_) | LoadError::Binary(_) | LoadError::Bytecode(_) => {
+        Self {
+          e: Box::new(e),
+          pos: None,
+        }
+      }
+    }
+  }
+}
@@ -47,7 +47,7 @@ impl Environment {
 
   pub fn get(&self, ident: usize) -> &Value {
     // A bril program is well formed when, dynamically, every variable is defined before its use.
-    // If this is violated, this will return Value::Uninitialized and the whole interpreter will come crashing down.
+    // If this is violated, this will return Value::Uninitialized, which `check_args_defined` reports before any instruction uses it.
     self.env.get(self.current_pointer + ident).unwrap()
   }
 
@@ -85,6 +85,25 @@ impl Environment {
   pub fn pop_frame(&mut self) {
     (self.current_pointer, self.current_frame_size) = self.stack_pointers.pop().unwrap();
   }
+
+  // Copy out the values of the current frame so that they can be restored later
+  pub fn snapshot_frame(&self) -> Vec<Value> {
+    self.env[self.current_pointer..self.current_pointer + self.current_frame_size].to_vec()
+  }
+
+  // Overwrite the current frame with a snapshot taken by `snapshot_frame`
+  pub fn restore_frame(&mut self, frame: &[Value]) {
+    self.env[self.current_pointer..self.current_pointer + self.current_frame_size]
+      .copy_from_slice(frame);
+  }
+}
+
+// The state saved by a `speculate` instruction which a failing `guard` rolls back to.
+// Speculation is not allowed across function calls so only the current frame needs to be saved.
+// Aborting does not roll back the heap.
+struct Checkpoint<'a> {
+  frame: Vec<Value>,
+  current_label: Option<&'a String>,
 }
 
 // todo: This is basically a copy of the heap implement in brili and we could probably do something smarter. This currently isn't that worth it to optimize because most benchmarks do not use the memory extension nor do they run for very long. You (the reader in the future) may be working with bril programs that you would like to speed up that extensively use the bril memory extension. In that case, it would be worth seeing how to implement Heap without a map based memory. Maybe try to re-implement malloc for a large Vec<Value>?
@@ -165,6 +184,45 @@ fn get_arg<'a, T: From<&'a Value>>(vars: &'a Environment, index: usize, args: &[
   T::from(vars.get(args[index]))
 }
 
+// Whether every variable that `instr` reads has a value, which is checked before `instr` runs because `get_arg` can't fail.
+// `phi`, `get`, and `set` only copy values, so they may pass on an undefined variable, but `get` still needs a `set` to have written its shadow variable first.
+fn check_args_defined(
+  env: &Environment,
+  instr: &Instruction,
+  args: &[usize],
+) -> Result<(), InterpError> {
+  match instr {
+    Instruction::Value {
+      op: bril_rs::ValueOps::Phi,
+      ..
+    } => Ok(()),
+    Instruction::Value {
+      op: bril_rs::ValueOps::Get,
+      dest,
+      ..
+    } => match env.get(args[0]) {
+      Value::Uninitialized => Err(InterpError::VarUndefined(dest.clone())),
+      _ => Ok(()),
+    },
+    Instruction::Effect {
+      op: bril_rs::EffectOps::Set,
+      args: names,
+      ..
+    } => match env.get(args[1]) {
+      Value::Uninitialized => Err(InterpError::VarUndefined(names[1].clone())),
+      _ => Ok(()),
+    },
+    _ => instr
+      .args()
+      .iter()
+      .zip(args)
+      .try_for_each(|(name, a)| match env.get(*a) {
+        Value::Uninitialized | Value::Undefined => Err(InterpError::VarUndefined(name.clone())),
+        _ => Ok(()),
+      }),
+  }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 enum Value {
   Int(i64),
@@ -174,6 +232,8 @@ enum Value {
   Pointer(Pointer),
   #[default]
   Uninitialized,
+  // The value of a variable after `undef`, which only `set` and `get` can pass on
+  Undefined,
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -201,7 +261,7 @@ impl fmt::Display for Value {
       Self::Float(v) => write!(f, "{v:.17}"),
       Self::Char(c) => write!(f, "{c}"),
       Self::Pointer(p) => write!(f, "{p:?}"),
-      Self::Uninitialized => unreachable!(),
+      Self::Uninitialized | Self::Undefined => unreachable!(),
     }
   }
 }
@@ -219,7 +279,7 @@ fn optimized_val_output<T: std::io::Write>(out: &mut T, val: &Value) -> Result<(
       out.write_all(c.encode_utf8(buf).as_bytes())
     }
     Value::Pointer(p) => out.write_all(format!("{p:?}").as_bytes()),
-    Value::Uninitialized => unreachable!(),
+    Value::Uninitialized | Value::Undefined => unreachable!(),
   }
 }
 
@@ -322,10 +382,12 @@ fn execute_value_op<T: std::io::Write>(
   labels: &[String],
   funcs: &[usize],
   last_label: Option<&String>,
+  speculating: bool,
 ) -> Result<(), InterpError> {
   use bril_rs::ValueOps::{
     Add, Alloc, And, Call, Ceq, Cge, Cgt, Char2int, Cle, Clt, Div, Eq, Fadd, Fdiv, Feq, Fge, Fgt,
-    Fle, Flt, Fmul, Fsub, Ge, Gt, Id, Int2char, Le, Load, Lt, Mul, Not, Or, Phi, PtrAdd, Sub,
+    Fle, Flt, Fmul, Fsub, Ge, Get, Gt, Id, Int2char, Le, Load, Lt, Mul, Not, Or, Phi, PtrAdd, Sub,
+    Undef,
   };
   match op {
     Add => {
@@ -390,10 +452,14 @@ fn execute_value_op<T: std::io::Write>(
       let arg1 = get_arg::<bool>(&state.env, 1, args);
       state.env.set(dest, Value::Bool(arg0 || arg1));
     }
-    Id => {
+    // `get` has been numified to read from the shadow variable of `dest`
+    Id | Get => {
       let src = get_arg::<Value>(&state.env, 0, args);
       state.env.set(dest, src);
     }
+    Undef => {
+      state.env.set(dest, Value::Undefined);
+    }
     Fadd => {
       let arg0 = get_arg::<f64>(&state.env, 0, args);
       let arg1 = get_arg::<f64>(&state.env, 1, args);
@@ -479,6 +545,9 @@ fn execute_value_op<T: std::io::Write>(
       state.env.set(dest, Value::Char(arg0_char));
     }
     Call => {
+      if speculating {
+        return Err(InterpError::NotAllowedDuringSpeculation(op.to_string()));
+      }
       let callee_func = state.prog.get(funcs[0]).unwrap();
 
       make_func_args(callee_func, args, &mut state.env);
@@ -520,18 +589,22 @@ fn execute_value_op<T: std::io::Write>(
   Ok(())
 }
 
-fn execute_effect_op<T: std::io::Write>(
+fn execute_effect_op<'a, T: std::io::Write>(
   state: &mut State<T>,
   op: bril_rs::EffectOps,
   args: &[usize],
   funcs: &[usize],
+  labels: &[usize],
   curr_block: &BasicBlock,
+  // The stack of nested speculative contexts for the current function call
+  speculation: &mut Vec<Checkpoint<'a>>,
+  current_label: &mut Option<&'a String>,
   // There are two output variables where values are stored to effect the loop execution.
   next_block_idx: &mut Option<usize>,
   result: &mut Option<Value>,
 ) -> Result<(), InterpError> {
   use bril_rs::EffectOps::{
-    Branch, Call, Commit, Free, Guard, Jump, Nop, Print, Return, Speculate, Store,
+    Branch, Call, Commit, Free, Guard, Jump, Nop, Print, Return, Set, Speculate, Store,
   };
   match op {
     Jump => {
@@ -543,6 +616,9 @@ fn execute_effect_op<T: std::io::Write>(
       *next_block_idx = Some(curr_block.exit[exit_idx]);
     }
     Return => {
+      if !speculation.is_empty() {
+        return Err(InterpError::NotAllowedDuringSpeculation(op.to_string()));
+      }
       if !args.is_empty() {
         *result = Some(get_arg::<Value>(&state.env, 0, args));
       }
@@ -568,6 +644,9 @@ fn execute_effect_op<T: std::io::Write>(
     }
     Nop => {}
     Call => {
+      if !speculation.is_empty() {
+        return Err(InterpError::NotAllowedDuringSpeculation(op.to_string()));
+      }
       let callee_func = state.prog.get(funcs[0]).unwrap();
 
       make_func_args(callee_func, args, &mut state.env);
@@ -584,7 +663,32 @@ fn execute_effect_op<T: std::io::Write>(
       let arg0 = get_arg::<&Pointer>(&state.env, 0, args);
       state.heap.free(arg0)?;
     }
-    Speculate | Commit | Guard => unimplemented!(),
+    // The first argument has been numified to the shadow variable being set
+    Set => {
+      let src = get_arg::<Value>(&state.env, 1, args);
+      state.env.set(args[0], src);
+    }
+    Speculate => {
+      speculation.push(Checkpoint {
+        frame: state.env.snapshot_frame(),
+        current_label: *current_label,
+      });
+    }
+    Commit => {
+      speculation
+        .pop()
+        .ok_or(InterpError::CommitOutsideSpeculation)?;
+    }
+    Guard => {
+      if !get_arg::<bool>(&state.env, 0, args) {
+        let checkpoint = speculation
+          .pop()
+          .ok_or(InterpError::AbortOutsideSpeculation)?;
+        state.env.restore_frame(&checkpoint.frame);
+        *current_label = checkpoint.current_label;
+        *next_block_idx = Some(labels[0]);
+      }
+    }
   }
   Ok(())
 }
@@ -598,12 +702,14 @@ fn execute<'a, T: std::io::Write>(
   let mut curr_block_idx = 0;
   // A possible return value
   let mut result = None;
+  let mut speculation = Vec::new();
 
   loop {
     let curr_block = &func.blocks[curr_block_idx];
     let curr_instrs = &curr_block.instrs;
     let curr_numified_instrs = &curr_block.numified_instrs;
-    // WARNING!!! We can add the # of instructions at once because you can only jump to a new block at the end. This may need to be changed if speculation is implemented
+    // We add the # of instructions at once because you can usually only jump to a new block at the end.
+    // The exception is a failing `guard` which corrects this count when it leaves the block early.
     state.instruction_count += curr_instrs.len();
     last_label = current_label;
     current_label = curr_block.label.as_ref();
@@ -611,7 +717,11 @@ fn execute<'a, T: std::io::Write>(
     // A place to store the next block that will be jumped to if specified by an instruction
     let mut next_block_idx = None;
 
-    for (code, numified_code) in curr_instrs.iter().zip(curr_numified_instrs.iter()) {
+    for (instr_idx, (code, numified_code)) in curr_instrs
+      .iter()
+      .zip(curr_numified_instrs.iter())
+      .enumerate()
+    {
       match code {
         Instruction::Constant {
           op: bril_rs::ConstOps::Const,
@@ -650,6 +760,8 @@ fn execute<'a, T: std::io::Write>(
           funcs: _,
           pos,
         } => {
+          check_args_defined(&state.env, code, &numified_code.args)
+            .map_err(|e| e.add_pos(pos.clone()))?;
           execute_value_op(
             state,
             *op,
@@ -658,6 +770,7 @@ fn execute<'a, T: std::io::Write>(
             labels,
             &numified_code.funcs,
             last_label,
+            !speculation.is_empty(),
           )
           .map_err(|e| e.add_pos(pos.clone()))?;
         }
@@ -668,16 +781,27 @@ fn execute<'a, T: std::io::Write>(
           funcs: _,
           pos,
         } => {
+          check_args_defined(&state.env, code, &numified_code.args)
+            .map_err(|e| e.add_pos(pos.clone()))?;
           execute_effect_op(
             state,
             *op,
             &numified_code.args,
             &numified_code.funcs,
+            &numified_code.labels,
             curr_block,
+            &mut speculation,
+            &mut current_label,
             &mut next_block_idx,
             &mut result,
           )
           .map_err(|e| e.add_pos(pos.clone()))?;
+
+          // A failing `guard` can jump out of the middle of a block
+          if next_block_idx.is_some() {
+            state.instruction_count -= curr_instrs.len() - (instr_idx + 1);
+            break;
+          }
         }
       }
     }
@@ -687,8 +811,10 @@ fn execute<'a, T: std::io::Write>(
       curr_block_idx = idx;
     } else if curr_block.exit.len() == 1 {
       curr_block_idx = curr_block.exit[0];
-    } else {
+    } else if speculation.is_empty() {
       return Ok(result);
+    } else {
+      return Err(InterpError::ImplicitReturnInSpeculation.add_pos(func.pos.clone()));
     }
   }
 }
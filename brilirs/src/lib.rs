@@ -9,7 +9,7 @@
 #![doc = include_str!("../README.md")]
 
 use basic_block::BBProgram;
-use bril_rs::Program;
+use bril_rs::{error::LoadError, Program};
 use error::PositionalInterpError;
 
 /// The internal representation of brilirs, provided a ```TryFrom<Program>``` conversion
@@ -25,7 +25,7 @@ pub mod interp;
 
 #[doc(hidden)]
 pub fn run_input<T: std::io::Write, U: std::io::Write>(
-  input: impl std::io::Read,
+  mut input: impl std::io::Read,
   out: T,
   input_args: &[String],
   profiling: bool,
@@ -37,10 +37,16 @@ pub fn run_input<T: std::io::Write, U: std::io::Write>(
   // It's a little confusing because of the naming conventions.
   //      - bril_rs takes file.json as input
   //      - bril2json takes file.bril as input
-  let prog: Program = if text {
-    bril2json::parse_abstract_program_from_read(input, true, true, src_name).try_into()?
+  //      - binary input from bril_rs::binary is detected by its header
+  let mut buffer = Vec::new();
+  input.read_to_end(&mut buffer).map_err(LoadError::from)?;
+  let prog: Program = if bril_rs::binary::is_binary(&buffer) {
+    bril_rs::binary::read_binary_from_slice(&buffer).map_err(LoadError::from)?
+  } else if text {
+    bril2json::try_parse_abstract_program_from_read(buffer.as_slice(), true, true, src_name)?
+      .try_into()?
   } else {
-    bril_rs::load_abstract_program_from_read(input).try_into()?
+    bril_rs::try_load_abstract_program_from_read(buffer.as_slice())?.try_into()?
   };
   let bbprog: BBProgram = prog.try_into()?;
   check::type_check(&bbprog)?;